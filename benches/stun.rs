@@ -0,0 +1,98 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use stun_zc::attr::Integrity;
+use stun_zc::prelude::*;
+
+const TXID: TxId = TxId([0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6, 0x86, 0xfa, 0x87, 0xdf, 0xae]);
+
+fn encode(attrs: &[StunAttr<'_>]) -> Vec<u8> {
+	let msg = Stun {
+		typ: StunTyp::Req(StunMethod::Binding),
+		txid: TXID,
+		attrs: attrs.into(),
+	};
+	let mut buff = vec![0; 1500];
+	let len = msg.encode(&mut buff).unwrap();
+	buff.truncate(len);
+	buff
+}
+
+fn benches(c: &mut Criterion) {
+	let key = ShortTermKey::new("VOkJxbRl1RmTxUk/WvJxBt");
+	// An ICE connectivity check
+	let ice = [
+		StunAttr::Software("STUN test client"),
+		StunAttr::Priority(0x6e0001ff),
+		StunAttr::IceControlled(0x932ff9b151263b36),
+		StunAttr::Username("evtj:h6vY"),
+		StunAttr::integrity(&key),
+		StunAttr::Fingerprint,
+	];
+	// A TURN Send indication
+	let payload = [0x5a; 160];
+	let turn = [
+		StunAttr::xor_peer("192.0.2.1:32853".parse().unwrap()),
+		StunAttr::data(&payload),
+		StunAttr::Fingerprint,
+	];
+
+	for (name, attrs) in [("ice", &ice[..]), ("turn", &turn[..])] {
+		let packet = encode(attrs);
+		c.bench_function(&format!("decode/{name}"), |b| {
+			b.iter(|| Stun::decode(black_box(&packet)).unwrap())
+		});
+		c.bench_function(&format!("flat/{name}"), |b| {
+			let msg = Stun::decode(&packet).unwrap();
+			b.iter(|| black_box(&msg).flat())
+		});
+		c.bench_function(&format!("encode/{name}"), |b| {
+			let msg = Stun {
+				typ: StunTyp::Req(StunMethod::Binding),
+				txid: TXID,
+				attrs: attrs.into(),
+			};
+			let mut buff = [0; 1500];
+			b.iter(|| black_box(&msg).encode(&mut buff).unwrap())
+		});
+	}
+
+	let packet = encode(&ice);
+	let msg = Stun::decode(&packet).unwrap();
+	let integrity = msg.flat().integrity.unwrap();
+	c.bench_function("integrity/verify", |b| {
+		b.iter(|| assert!(black_box(&integrity).verify(&key)))
+	});
+	c.bench_function("integrity/sign", |b| {
+		let attrs = [StunAttr::Username("evtj:h6vY"), StunAttr::Integrity(Integrity::sign(&key))];
+		let msg = Stun {
+			typ: StunTyp::Req(StunMethod::Binding),
+			txid: TXID,
+			attrs: attrs[..].into(),
+		};
+		let mut buff = [0; 1500];
+		b.iter(|| black_box(&msg).encode(&mut buff).unwrap())
+	});
+	c.bench_function("xor/ipv6", |b| {
+		let attrs = [StunAttr::xor_mapped("[2001:db8:1234:5678:11:2233:4455:6677]:32853".parse().unwrap())];
+		let msg = Stun {
+			typ: StunTyp::Res(StunMethod::Binding),
+			txid: TXID,
+			attrs: attrs[..].into(),
+		};
+		let mut buff = [0; 1500];
+		let len = msg.encode(&mut buff).unwrap();
+		b.iter(|| Stun::decode(black_box(&buff[..len])).unwrap().flat().xmapped)
+	});
+	c.bench_function("fingerprint", |b| {
+		let attrs = [StunAttr::Fingerprint];
+		let msg = Stun {
+			typ: StunTyp::Req(StunMethod::Binding),
+			txid: TXID,
+			attrs: attrs[..].into(),
+		};
+		let mut buff = [0; 1500];
+		b.iter(|| black_box(&msg).encode(&mut buff).unwrap())
+	});
+}
+
+criterion_group!(stun, benches);
+criterion_main!(stun);
@@ -1,5 +1,5 @@
 use eyre::Result;
-use stun_zc::{attr::StunAttr, Stun, StunTyp};
+use stun_zc::prelude::*;
 
 fn main() -> Result<()> {
 	let sock = std::net::UdpSocket::bind("[::]:3478")?;
@@ -15,13 +15,13 @@ fn main() -> Result<()> {
 			}
 			Ok(m) => m,
 		};
-		println!("{addr} {:?} {:?}", m.typ, m.txid);
+		println!("{addr} {:?} {}", m.typ, m.txid);
 		for a in &m {
 			println!(" - {a:?}");
 		}
 
 		match m.typ {
-			StunTyp::Req(0x001) => {
+			StunTyp::Req(StunMethod::Binding) => {
 				let attrs = [
 					// StunAttr::Mapped(addr.into()),
 					StunAttr::XMapped(addr),
@@ -3,10 +3,23 @@ use std::{
 	net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
 	str::Utf8Error,
 };
+use super::StunEncodeErr;
+#[cfg(feature = "turn")]
 use super::Stun;
+use crate::auth::IntegrityKey;
+use crate::xor::{xor16, xor_prefix};
 
 use hmac::Mac;
 use sha1::Sha1;
+use sha2::Sha256;
+
+#[cfg(feature = "vendor")]
+pub mod ms;
+#[cfg(feature = "alloc")]
+pub mod owned;
+pub mod typ;
+#[cfg(feature = "vendor")]
+use ms::{MsSequenceNumber, MsServiceQuality};
 
 #[derive(Debug, Clone)]
 pub enum StunAttrDecodeErr {
@@ -33,6 +46,8 @@ pub struct AttrContext<'i> {
 	pub zero_xor_bytes: bool, // TODO: Is this ok?
 	pub attrs_prefix: &'i [u8],
 	pub attr_len: u16,
+	// The padding bytes that followed the value on the wire (empty when encoding)
+	pub padding: &'i [u8],
 }
 impl<'i> AttrContext<'i> {
 	// Used by xor encoded addresses:
@@ -40,13 +55,16 @@ impl<'i> AttrContext<'i> {
 		if self.zero_xor_bytes {
 			&[0u8; 16]
 		} else {
-			self.header[4..][..16].try_into().unwrap()
+			let [_, _, _, _, xor_bytes @ ..] = self.header;
+			xor_bytes
 		}
 	}
 	// Used by Integrity and Fingerprint attributes:
 	pub fn reduce_over_prefix<F: FnMut(&[u8])>(&self, mut func: F) {
 		func(&self.header[..2]); // STUN Type
-		func(&(self.attrs_prefix.len() as u16 + self.attr_len).to_be_bytes()); // Simulated STUN length
+		// Simulated STUN length (wraps instead of panicking on an oversized prefix; the check fails anyway)
+		let length = (self.attrs_prefix.len() as u16).wrapping_add(self.attr_len);
+		func(&length.to_be_bytes());
 		func(&self.header[4..]); // Rest of the STUN header (equivalent to xor_bytes)
 		func(self.attrs_prefix); // All of the written attributes up-to but not including the current attribute
 	}
@@ -60,6 +78,11 @@ pub trait StunAttrValue<'i> {
 		Self: Sized;
 }
 
+// Values longer than a u16 can't be encoded, so saturate and let StunAttr::len catch the overflow
+fn saturate(len: usize) -> u16 {
+	u16::try_from(len).unwrap_or(u16::MAX)
+}
+
 // This might not be exactly the same as IpAddr::to_canonical, but whatevs
 fn to_canonical(ip: IpAddr) -> IpAddr {
 	if let IpAddr::V6(v6) = ip {
@@ -86,17 +109,17 @@ impl StunAttrValue<'_> for SocketAddr {
 		};
 		buff[1] = family;
 		let port = self.port().to_be_bytes();
-		let xport: [u8; 2] = std::array::from_fn(|i| port[i] ^ xor_bytes[i]);
+		let xport = xor_prefix(port, xor_bytes);
 		buff[2..][..2].copy_from_slice(&xport);
 		match ip {
 			IpAddr::V4(ip) => {
 				let octs = ip.octets();
-				let xocts: [u8; 4] = std::array::from_fn(|i| octs[i] ^ xor_bytes[i]);
+				let xocts = xor_prefix(octs, xor_bytes);
 				buff[4..][..4].copy_from_slice(&xocts)
 			}
 			IpAddr::V6(ip) => {
 				let octs = ip.octets();
-				let xocts: [u8; 16] = std::array::from_fn(|i| octs[i] ^ xor_bytes[i]);
+				let xocts = xor16(octs, xor_bytes);
 				buff[4..][..16].copy_from_slice(&xocts);
 			}
 		}
@@ -109,12 +132,12 @@ impl StunAttrValue<'_> for SocketAddr {
 
 		let family = buff[1];
 		let xport = &buff[2..][..2];
-		let port = u16::from_be_bytes(std::array::from_fn(|i| xport[i] ^ xor_bytes[i]));
+		let port = u16::from_be_bytes(xor_prefix(xport.try_into()?, xor_bytes));
 
 		let xip = &buff[4..];
 		let ip = match (family, xip.len()) {
-			(0x01, 4) => Ipv4Addr::from(std::array::from_fn(|i| xip[i] ^ xor_bytes[i])).into(),
-			(0x02, 16) => Ipv6Addr::from(std::array::from_fn(|i| xip[i] ^ xor_bytes[i])).into(),
+			(0x01, 4) => Ipv4Addr::from(xor_prefix::<4>(xip.try_into()?, xor_bytes)).into(),
+			(0x02, 16) => Ipv6Addr::from(xor16(xip.try_into()?, xor_bytes)).into(),
 			_ => return Err(StunAttrDecodeErr::ValueUnexpectedLength),
 		};
 		Ok(SocketAddr::new(ip, port))
@@ -126,7 +149,7 @@ impl StunAttrValue<'_> for () {
 	}
 	fn encode(&self, _: &mut [u8], _: AttrContext<'_>) {}
 	fn decode(buff: &[u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
-		if buff.len() == 0 {
+		if buff.is_empty() {
 			Ok(())
 		} else {
 			Err(StunAttrDecodeErr::ValueUnexpectedLength)
@@ -135,7 +158,7 @@ impl StunAttrValue<'_> for () {
 }
 impl<'i> StunAttrValue<'i> for &'i str {
 	fn length(&self) -> u16 {
-		self.len() as u16
+		saturate(self.len())
 	}
 	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
 		buff.copy_from_slice(self.as_bytes())
@@ -146,7 +169,7 @@ impl<'i> StunAttrValue<'i> for &'i str {
 }
 impl<'i> StunAttrValue<'i> for &'i [u8] {
 	fn length(&self) -> u16 {
-		self.len() as u16
+		saturate(self.len())
 	}
 	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
 		buff.copy_from_slice(self)
@@ -196,9 +219,35 @@ pub struct Error<'i> {
 	pub code: u16,
 	pub message: &'i str,
 }
+impl Error<'_> {
+	// The reason phrases suggested by the RFCs that registered each code
+	pub fn reason(code: u16) -> Option<&'static str> {
+		Some(match code {
+			300 => "Try Alternate",
+			400 => "Bad Request",
+			401 => "Unauthorized",
+			403 => "Forbidden",
+			405 => "Mobility Forbidden",
+			420 => "Unknown Attribute",
+			437 => "Allocation Mismatch",
+			438 => "Stale Nonce",
+			440 => "Address Family not Supported",
+			441 => "Wrong Credentials",
+			442 => "Unsupported Transport Protocol",
+			443 => "Peer Address Family Mismatch",
+			446 => "Connection Already Exists",
+			447 => "Connection Timeout or Failure",
+			486 => "Allocation Quota Reached",
+			487 => "Role Conflict",
+			500 => "Server Error",
+			508 => "Insufficient Capacity",
+			_ => return None,
+		})
+	}
+}
 impl<'i> StunAttrValue<'i> for Error<'i> {
 	fn length(&self) -> u16 {
-		4 + self.message.len() as u16
+		saturate(4 + self.message.len())
 	}
 	fn decode(buff: &'i [u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
 		if buff.len() < 4 {
@@ -218,6 +267,147 @@ impl<'i> StunAttrValue<'i> for Error<'i> {
 	}
 }
 
+// ADDRESS-ERROR-CODE: the ERROR-CODE layout, with the address family (0x01 IPv4, 0x02 IPv6) that
+// couldn't be allocated in the first byte
+#[cfg(feature = "turn")]
+#[derive(Debug, Clone)]
+pub struct AddressError<'i> {
+	pub family: u8,
+	pub code: u16,
+	pub message: &'i str,
+}
+#[cfg(feature = "turn")]
+impl<'i> StunAttrValue<'i> for AddressError<'i> {
+	fn length(&self) -> u16 {
+		saturate(4 + self.message.len())
+	}
+	fn decode(buff: &'i [u8], ctx: AttrContext<'i>) -> Result<Self, StunAttrDecodeErr> {
+		let Error { code, message } = Error::decode(buff, ctx)?;
+		Ok(Self {
+			family: buff[0],
+			code,
+			message,
+		})
+	}
+	fn encode(&self, buff: &mut [u8], ctx: AttrContext<'_>) {
+		Error {
+			code: self.code,
+			message: self.message,
+		}
+		.encode(buff, ctx);
+		buff[0] = self.family;
+	}
+}
+
+// The ICMP error behind a relayed Data indication. Error data holds e.g. the next-hop MTU for
+// "fragmentation needed" / "packet too big".
+#[cfg(feature = "turn")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Icmp {
+	pub typ: u8,
+	pub code: u8,
+	pub data: u32,
+}
+#[cfg(feature = "turn")]
+impl StunAttrValue<'_> for Icmp {
+	fn length(&self) -> u16 {
+		8
+	}
+	fn decode(buff: &[u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
+		let [_, _, typ, code, d0, d1, d2, d3] = <[u8; 8]>::try_from(buff)?;
+		Ok(Self {
+			typ,
+			code,
+			data: u32::from_be_bytes([d0, d1, d2, d3]),
+		})
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff[0] = 0;
+		buff[1] = 0;
+		buff[2] = self.typ;
+		buff[3] = self.code;
+		buff[4..8].copy_from_slice(&self.data.to_be_bytes());
+	}
+}
+
+// ACCESS-TOKEN (RFC 7635): the nonce in the clear, followed by the AEAD encrypted block (including
+// its authentication tag). The decrypted block is an AccessTokenBlock.
+#[cfg(feature = "turn")]
+#[derive(Debug, Clone)]
+pub struct AccessToken<'i> {
+	pub nonce: &'i [u8],
+	pub encrypted: &'i [u8],
+}
+#[cfg(feature = "turn")]
+impl<'i> StunAttrValue<'i> for AccessToken<'i> {
+	fn length(&self) -> u16 {
+		saturate(2 + self.nonce.len() + self.encrypted.len())
+	}
+	fn decode(buff: &'i [u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
+		let (&[n0, n1], rest) = buff
+			.split_first_chunk::<2>()
+			.ok_or(StunAttrDecodeErr::ValueUnexpectedLength)?;
+		let (nonce, encrypted) = rest
+			.split_at_checked(u16::from_be_bytes([n0, n1]) as usize)
+			.ok_or(StunAttrDecodeErr::ValueUnexpectedLength)?;
+		Ok(Self { nonce, encrypted })
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff[..2].copy_from_slice(&(self.nonce.len() as u16).to_be_bytes());
+		let (nonce, encrypted) = buff[2..].split_at_mut(self.nonce.len());
+		nonce.copy_from_slice(self.nonce);
+		encrypted.copy_from_slice(self.encrypted);
+	}
+}
+// The plaintext of an ACCESS-TOKEN's encrypted block. The timestamp is 48 bits of seconds and 16
+// bits of fractional seconds since the unix epoch; lifetime is in seconds.
+#[cfg(feature = "turn")]
+#[derive(Debug, Clone)]
+pub struct AccessTokenBlock<'i> {
+	pub mac_key: &'i [u8],
+	pub timestamp: u64,
+	pub lifetime: u32,
+}
+#[cfg(feature = "turn")]
+impl<'i> AccessTokenBlock<'i> {
+	pub fn len(&self) -> usize {
+		2 + self.mac_key.len() + 8 + 4
+	}
+	pub fn decode(buff: &'i [u8]) -> Result<Self, StunAttrDecodeErr> {
+		let (&[k0, k1], rest) = buff
+			.split_first_chunk::<2>()
+			.ok_or(StunAttrDecodeErr::ValueUnexpectedLength)?;
+		let (mac_key, rest) = rest
+			.split_at_checked(u16::from_be_bytes([k0, k1]) as usize)
+			.ok_or(StunAttrDecodeErr::ValueUnexpectedLength)?;
+		let (timestamp, rest) = rest
+			.split_first_chunk::<8>()
+			.ok_or(StunAttrDecodeErr::ValueUnexpectedLength)?;
+		let lifetime = rest
+			.first_chunk::<4>()
+			.ok_or(StunAttrDecodeErr::ValueUnexpectedLength)?;
+		Ok(Self {
+			mac_key,
+			timestamp: u64::from_be_bytes(*timestamp),
+			lifetime: u32::from_be_bytes(*lifetime),
+		})
+	}
+	// Writes the block into buff, ready for encryption. Returns the length written.
+	pub fn encode(&self, buff: &mut [u8]) -> Result<usize, StunEncodeErr> {
+		let key_len = u16::try_from(self.mac_key.len()).map_err(|_| StunEncodeErr::TooLarge)?;
+		let buff = buff
+			.get_mut(..self.len())
+			.ok_or(StunEncodeErr::BufferTooSmall(self.len()))?;
+		let (len, rest) = buff.split_at_mut(2);
+		len.copy_from_slice(&key_len.to_be_bytes());
+		let (mac_key, rest) = rest.split_at_mut(self.mac_key.len());
+		mac_key.copy_from_slice(self.mac_key);
+		rest[..8].copy_from_slice(&self.timestamp.to_be_bytes());
+		rest[8..].copy_from_slice(&self.lifetime.to_be_bytes());
+		Ok(buff.len())
+	}
+}
+
 #[derive(Debug, Clone)]
 pub enum UnknownAttributes<'i> {
 	Parse(&'i [u8]),
@@ -226,8 +416,8 @@ pub enum UnknownAttributes<'i> {
 impl<'i> StunAttrValue<'i> for UnknownAttributes<'i> {
 	fn length(&self) -> u16 {
 		match self {
-			Self::Parse(s) => s.len() as u16,
-			Self::List(l) => (l.len() * 2) as u16,
+			Self::Parse(s) => saturate(s.len()),
+			Self::List(l) => saturate(l.len() * 2),
 		}
 	}
 	fn encode(&self, mut buff: &mut [u8], _: AttrContext<'_>) {
@@ -242,15 +432,17 @@ impl<'i> StunAttrValue<'i> for UnknownAttributes<'i> {
 		}
 	}
 	fn decode(buff: &'i [u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
-		if buff.len() % 2 != 0 {
+		if !buff.len().is_multiple_of(2) {
 			Err(StunAttrDecodeErr::ValueUnexpectedLength)
 		} else {
 			Ok(Self::Parse(buff))
 		}
 	}
 }
+#[cfg(feature = "turn")]
 #[derive(Debug, Clone)]
 pub struct EvenPort(pub bool);
+#[cfg(feature = "turn")]
 impl StunAttrValue<'_> for EvenPort {
 	fn length(&self) -> u16 {
 		1
@@ -271,8 +463,81 @@ impl StunAttrValue<'_> for EvenPort {
 		};
 	}
 }
-#[derive(Debug, Clone)]
-pub struct RequestedTransport(pub u8);
+// LIFETIME: seconds until a TURN allocation expires. RFC 8656 allocations default to 10 minutes and
+// servers cap them at an hour.
+#[cfg(feature = "turn")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lifetime(pub u32);
+#[cfg(feature = "turn")]
+impl Lifetime {
+	pub const DEFAULT: Self = Self(600);
+	pub const MAX: Self = Self(3600);
+	// Deletes the allocation when sent in a Refresh
+	pub const ZERO: Self = Self(0);
+	pub fn secs(self) -> u32 {
+		self.0
+	}
+}
+#[cfg(feature = "turn")]
+impl From<Lifetime> for std::time::Duration {
+	fn from(value: Lifetime) -> Self {
+		Self::from_secs(value.0.into())
+	}
+}
+// Whole seconds, saturating at u32::MAX
+#[cfg(feature = "turn")]
+impl From<std::time::Duration> for Lifetime {
+	fn from(value: std::time::Duration) -> Self {
+		Self(value.as_secs().try_into().unwrap_or(u32::MAX))
+	}
+}
+#[cfg(feature = "turn")]
+impl StunAttrValue<'_> for Lifetime {
+	fn length(&self) -> u16 {
+		self.0.length()
+	}
+	fn decode(buff: &[u8], ctx: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
+		u32::decode(buff, ctx).map(Self)
+	}
+	fn encode(&self, buff: &mut [u8], ctx: AttrContext<'_>) {
+		self.0.encode(buff, ctx)
+	}
+}
+// The IP protocol number of the transport to relay over
+#[cfg(feature = "turn")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestedTransport {
+	Udp,
+	Tcp,
+	Other(u8),
+}
+#[cfg(feature = "turn")]
+impl RequestedTransport {
+	pub fn protocol(self) -> u8 {
+		self.into()
+	}
+}
+#[cfg(feature = "turn")]
+impl From<u8> for RequestedTransport {
+	fn from(value: u8) -> Self {
+		match value {
+			17 => Self::Udp,
+			6 => Self::Tcp,
+			v => Self::Other(v),
+		}
+	}
+}
+#[cfg(feature = "turn")]
+impl From<RequestedTransport> for u8 {
+	fn from(value: RequestedTransport) -> Self {
+		match value {
+			RequestedTransport::Udp => 17,
+			RequestedTransport::Tcp => 6,
+			RequestedTransport::Other(v) => v,
+		}
+	}
+}
+#[cfg(feature = "turn")]
 impl StunAttrValue<'_> for RequestedTransport {
 	fn length(&self) -> u16 {
 		4
@@ -281,6 +546,40 @@ impl StunAttrValue<'_> for RequestedTransport {
 	where
 		Self: Sized,
 	{
+		if buff.len() != 4 {
+			return Err(StunAttrDecodeErr::ValueUnexpectedLength);
+		}
+		Ok(buff[0].into())
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff[0] = self.protocol();
+		buff[1] = 0;
+		buff[2] = 0;
+		buff[3] = 0;
+	}
+}
+// REQUESTED-ADDRESS-FAMILY: which family the relayed address should be (RFC 8656 section 18.1),
+// with the same numbering as the address attributes
+#[cfg(feature = "turn")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AddressFamily(pub u8);
+#[cfg(feature = "turn")]
+impl AddressFamily {
+	pub const IPV4: Self = Self(0x01);
+	pub const IPV6: Self = Self(0x02);
+	pub fn of(addr: SocketAddr) -> Self {
+		match addr {
+			SocketAddr::V4(_) => Self::IPV4,
+			SocketAddr::V6(_) => Self::IPV6,
+		}
+	}
+}
+#[cfg(feature = "turn")]
+impl StunAttrValue<'_> for AddressFamily {
+	fn length(&self) -> u16 {
+		4
+	}
+	fn decode(buff: &[u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
 		if buff.len() != 4 {
 			return Err(StunAttrDecodeErr::ValueUnexpectedLength);
 		}
@@ -293,6 +592,126 @@ impl StunAttrValue<'_> for RequestedTransport {
 		buff[3] = 0;
 	}
 }
+#[cfg(feature = "nat-discovery")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChangeRequest {
+	pub change_ip: bool,
+	pub change_port: bool,
+}
+#[cfg(feature = "nat-discovery")]
+impl StunAttrValue<'_> for ChangeRequest {
+	fn length(&self) -> u16 {
+		4
+	}
+	fn decode(buff: &[u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr>
+	where
+		Self: Sized,
+	{
+		let [_, _, _, flags] = <[u8; 4]>::try_from(buff)?;
+		Ok(Self {
+			change_ip: flags & 0b100 != 0,
+			change_port: flags & 0b010 != 0,
+		})
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff[..3].fill(0);
+		buff[3] = (self.change_ip as u8) << 2 | (self.change_port as u8) << 1;
+	}
+}
+#[cfg(feature = "nat-discovery")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponsePort(pub u16);
+#[cfg(feature = "nat-discovery")]
+impl StunAttrValue<'_> for ResponsePort {
+	fn length(&self) -> u16 {
+		4
+	}
+	fn decode(buff: &[u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr>
+	where
+		Self: Sized,
+	{
+		let [p0, p1, _, _] = <[u8; 4]>::try_from(buff)?;
+		Ok(Self(u16::from_be_bytes([p0, p1])))
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff[..2].copy_from_slice(&self.0.to_be_bytes());
+		buff[2] = 0;
+		buff[3] = 0;
+	}
+}
+// Only the length of PADDING matters: it's zeros on the way out and ignored on the way in
+#[cfg(feature = "nat-discovery")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Padding(pub u16);
+#[cfg(feature = "nat-discovery")]
+impl StunAttrValue<'_> for Padding {
+	fn length(&self) -> u16 {
+		self.0
+	}
+	fn decode(buff: &[u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr>
+	where
+		Self: Sized,
+	{
+		Ok(Self(saturate(buff.len())))
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff.fill(0);
+	}
+}
+// How many times the request had been sent when the response was generated, and how many responses
+// the server has sent to it (RFC 7982)
+#[cfg(feature = "nat-discovery")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransmitCounter {
+	pub req: u8,
+	pub resp: u8,
+}
+#[cfg(feature = "nat-discovery")]
+impl StunAttrValue<'_> for TransmitCounter {
+	fn length(&self) -> u16 {
+		4
+	}
+	fn decode(buff: &[u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr>
+	where
+		Self: Sized,
+	{
+		let [_, _, req, resp] = <[u8; 4]>::try_from(buff)?;
+		Ok(Self { req, resp })
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff[0] = 0;
+		buff[1] = 0;
+		buff[2] = self.req;
+		buff[3] = self.resp;
+	}
+}
+// libwebrtc's network id and cost (higher is more expensive, e.g. 10 for ethernet, 900 for cellular)
+#[cfg(feature = "vendor")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GoogNetworkInfo {
+	pub network_id: u16,
+	pub cost: u16,
+}
+#[cfg(feature = "vendor")]
+impl StunAttrValue<'_> for GoogNetworkInfo {
+	fn length(&self) -> u16 {
+		4
+	}
+	fn decode(buff: &[u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr>
+	where
+		Self: Sized,
+	{
+		let [i0, i1, c0, c1] = <[u8; 4]>::try_from(buff)?;
+		Ok(Self {
+			network_id: u16::from_be_bytes([i0, i1]),
+			cost: u16::from_be_bytes([c0, c1]),
+		})
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff[..2].copy_from_slice(&self.network_id.to_be_bytes());
+		buff[2..].copy_from_slice(&self.cost.to_be_bytes());
+	}
+}
 #[derive(Debug, Clone)]
 pub struct ZeroXor<V>(pub V);
 impl<'i, V: StunAttrValue<'i>> StunAttrValue<'i> for ZeroXor<V> {
@@ -365,16 +784,23 @@ pub enum Integrity<'i> {
 	},
 }
 impl<'i> Integrity<'i> {
-	pub fn verify(&self, key_data: &[u8]) -> bool {
+	pub fn sign<K: IntegrityKey>(key: &'i K) -> Self {
+		Self::Set {
+			key_data: key.as_bytes(),
+		}
+	}
+	pub fn verify<K: IntegrityKey + ?Sized>(&self, key: &K) -> bool {
+		let key_data = key.as_bytes();
 		match self {
 			Self::Set {
 				key_data: actual_key_data,
 			} => key_data == *actual_key_data,
 			Self::Check { val: actual, ctx } => {
-				let mut hmac = hmac::Hmac::<Sha1>::new_from_slice(key_data).expect("bad key_data");
+				let Ok(mut hmac) = hmac::Hmac::<Sha1>::new_from_slice(key_data) else {
+					return false;
+				};
 				ctx.reduce_over_prefix(|buf| hmac.update(buf));
-				let expected = hmac.finalize().into_bytes();
-				expected.as_slice() == actual.as_slice()
+				hmac.verify_slice(actual.as_slice()).is_ok()
 			}
 		}
 	}
@@ -394,27 +820,198 @@ impl<'i> StunAttrValue<'i> for Integrity<'i> {
 		match self {
 			Self::Check { val, .. } => val.encode(buff, ctx),
 			Self::Set { key_data } => {
-				let mut hmac = hmac::Hmac::<Sha1>::new_from_slice(key_data)
-					.expect("Unable to create Hmac key");
+				// Hmac accepts keys of any length, but don't panic if that ever changes
+				let Ok(mut hmac) = hmac::Hmac::<Sha1>::new_from_slice(key_data) else {
+					buff.fill(0);
+					return;
+				};
+				ctx.reduce_over_prefix(|buf| hmac.update(buf));
+				let actual: [u8; 20] = hmac.finalize().into_bytes().into();
+				(&actual).encode(buff, ctx);
+			}
+		}
+	}
+}
+// The value can be truncated to as few as 16 bytes, but we always send the full 32
+#[derive(Debug, Clone)]
+pub enum IntegritySha256<'i> {
+	Check {
+		val: &'i [u8],
+		ctx: AttrContext<'i>,
+	},
+	Set {
+		key_data: &'i [u8],
+	},
+}
+impl<'i> IntegritySha256<'i> {
+	pub fn sign<K: IntegrityKey>(key: &'i K) -> Self {
+		Self::Set {
+			key_data: key.as_bytes(),
+		}
+	}
+	pub fn verify<K: IntegrityKey + ?Sized>(&self, key: &K) -> bool {
+		let key_data = key.as_bytes();
+		match self {
+			Self::Set {
+				key_data: actual_key_data,
+			} => key_data == *actual_key_data,
+			Self::Check { val: actual, ctx } => {
+				// Decoding checks this too, but a Check can be built by hand
+				if actual.len() < 16 || !actual.len().is_multiple_of(4) {
+					return false;
+				}
+				let Ok(mut hmac) = hmac::Hmac::<Sha256>::new_from_slice(key_data) else {
+					return false;
+				};
 				ctx.reduce_over_prefix(|buf| hmac.update(buf));
-				let actual = hmac.finalize().into_bytes();
-				<&[u8; 20]>::try_from(actual.as_slice())
-					.unwrap()
-					.encode(buff, ctx);
+				// In constant time, like verify_slice
+				hmac.verify_truncated_left(actual).is_ok()
+			}
+		}
+	}
+}
+impl<'i> StunAttrValue<'i> for IntegritySha256<'i> {
+	fn length(&self) -> u16 {
+		match self {
+			Self::Check { val, .. } => saturate(val.len()),
+			Self::Set { .. } => 32,
+		}
+	}
+	fn decode(buff: &'i [u8], ctx: AttrContext<'i>) -> Result<Self, StunAttrDecodeErr>
+	where
+		Self: Sized,
+	{
+		if !(16..=32).contains(&buff.len()) || !buff.len().is_multiple_of(4) {
+			return Err(StunAttrDecodeErr::ValueUnexpectedLength);
+		}
+		Ok(Self::Check { val: buff, ctx })
+	}
+	fn encode(&self, buff: &mut [u8], ctx: AttrContext<'_>) {
+		match self {
+			Self::Check { val, .. } => val.encode(buff, ctx),
+			Self::Set { key_data } => {
+				let Ok(mut hmac) = hmac::Hmac::<Sha256>::new_from_slice(key_data) else {
+					buff.fill(0);
+					return;
+				};
+				ctx.reduce_over_prefix(|buf| hmac.update(buf));
+				let actual: [u8; 32] = hmac.finalize().into_bytes().into();
+				(&actual).encode(buff, ctx);
+			}
+		}
+	}
+}
+// RFC 8489 section 18.5: an algorithm number and its parameters (none for MD5 and SHA-256)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordAlgorithm<'i> {
+	pub algorithm: u16,
+	pub params: &'i [u8],
+}
+impl PasswordAlgorithm<'static> {
+	pub const MD5: Self = Self {
+		algorithm: typ::PASSWORD_ALGORITHM_MD5,
+		params: &[],
+	};
+	pub const SHA256: Self = Self {
+		algorithm: typ::PASSWORD_ALGORITHM_SHA256,
+		params: &[],
+	};
+}
+impl<'i> PasswordAlgorithm<'i> {
+	// Splits the first algorithm off the front of a PASSWORD-ALGORITHMS value
+	fn split(buff: &'i [u8]) -> Option<(Self, &'i [u8])> {
+		let (&[a0, a1, l0, l1], rest) = buff.split_first_chunk::<4>()?;
+		let len = u16::from_be_bytes([l0, l1]) as usize;
+		let (params, _) = rest.split_at_checked(len)?;
+		let rest = rest.get(len.next_multiple_of(4)..).unwrap_or_default();
+		let algorithm = u16::from_be_bytes([a0, a1]);
+		Some((Self { algorithm, params }, rest))
+	}
+}
+impl<'i> StunAttrValue<'i> for PasswordAlgorithm<'i> {
+	fn length(&self) -> u16 {
+		saturate(4 + self.params.len())
+	}
+	fn decode(buff: &'i [u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
+		match Self::split(buff) {
+			Some((ret, _)) if 4 + ret.params.len() == buff.len() => Ok(ret),
+			_ => Err(StunAttrDecodeErr::ValueUnexpectedLength),
+		}
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff[..2].copy_from_slice(&self.algorithm.to_be_bytes());
+		buff[2..4].copy_from_slice(&(self.params.len() as u16).to_be_bytes());
+		buff[4..].copy_from_slice(self.params);
+	}
+}
+// The algorithms a server supports, most preferred first. Each one's parameters are padded to a
+// multiple of 4.
+#[derive(Debug, Clone)]
+pub enum PasswordAlgorithms<'i> {
+	Parse(&'i [u8]),
+	List(&'i [PasswordAlgorithm<'i>]),
+}
+impl<'i> PasswordAlgorithms<'i> {
+	pub fn iter(&self) -> impl Iterator<Item = PasswordAlgorithm<'i>> + '_ {
+		let (mut parse, mut list) = match self {
+			Self::Parse(buff) => (*buff, [].iter()),
+			Self::List(l) => (&[][..], l.iter()),
+		};
+		std::iter::from_fn(move || {
+			if let Some(alg) = list.next() {
+				return Some(*alg);
+			}
+			let (alg, rest) = PasswordAlgorithm::split(parse)?;
+			parse = rest;
+			Some(alg)
+		})
+	}
+}
+impl<'i> StunAttrValue<'i> for PasswordAlgorithms<'i> {
+	fn length(&self) -> u16 {
+		match self {
+			Self::Parse(s) => saturate(s.len()),
+			Self::List(l) => saturate(l.iter().map(|a| 4 + a.params.len().next_multiple_of(4)).sum()),
+		}
+	}
+	fn decode(buff: &'i [u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
+		let mut rest = buff;
+		while !rest.is_empty() {
+			let Some((_, next)) = PasswordAlgorithm::split(rest) else {
+				return Err(StunAttrDecodeErr::ValueUnexpectedLength);
+			};
+			rest = next;
+		}
+		Ok(Self::Parse(buff))
+	}
+	fn encode(&self, mut buff: &mut [u8], _: AttrContext<'_>) {
+		match self {
+			Self::Parse(s) => buff.copy_from_slice(s),
+			Self::List(l) => {
+				for alg in l.iter() {
+					let len = alg.params.len();
+					buff[..2].copy_from_slice(&alg.algorithm.to_be_bytes());
+					buff[2..4].copy_from_slice(&(len as u16).to_be_bytes());
+					buff[4..][..len].copy_from_slice(alg.params);
+					buff[4 + len..][..len.next_multiple_of(4) - len].fill(0);
+					buff = &mut buff[4 + len.next_multiple_of(4)..];
+				}
 			}
 		}
 	}
 }
+#[cfg(feature = "turn")]
 #[derive(Debug, Clone)]
 pub enum Data<'i> {
 	Slice(&'i [u8]),
 	Nested(Stun<'i>)
 }
+#[cfg(feature = "turn")]
 impl<'i> StunAttrValue<'i> for Data<'i> {
 	fn length(&self) -> u16 {
 		match self {
-			Self::Slice(s) => s.len() as u16,
-			Self::Nested(m) => m.len() as u16
+			Self::Slice(s) => saturate(s.len()),
+			Self::Nested(m) => m.len().map_or(u16::MAX, saturate)
 		}
 	}
 	fn decode(buff: &'i [u8], ctx: AttrContext<'i>) -> Result<Self, StunAttrDecodeErr> {
@@ -424,19 +1021,65 @@ impl<'i> StunAttrValue<'i> for Data<'i> {
 	fn encode(&self, buff: &mut [u8], ctx: AttrContext<'_>) {
 		match self {
 			Self::Slice(s) => s.encode(buff, ctx),
-			Self::Nested(m) => { m.encode(buff); }
+			Self::Nested(m) => { let _ = m.encode(buff); }
 		}
 	}
 }
+// An attribute we don't understand. The original padding bytes are kept so that the attribute can be
+// forwarded without changing any bytes covered by the sender's MESSAGE-INTEGRITY.
 #[derive(Debug, Clone)]
-pub struct Channel(u16);
-impl StunAttrValue<'_> for Channel {
+pub struct Unknown<'i> {
+	pub value: &'i [u8],
+	pub padding: &'i [u8],
+}
+impl<'i> StunAttrValue<'i> for Unknown<'i> {
+	fn length(&self) -> u16 {
+		self.value.length()
+	}
+	fn decode(buff: &'i [u8], ctx: AttrContext<'i>) -> Result<Self, StunAttrDecodeErr> {
+		Ok(Self {
+			value: buff,
+			padding: ctx.padding,
+		})
+	}
+	fn encode(&self, buff: &mut [u8], ctx: AttrContext<'_>) {
+		self.value.encode(buff, ctx)
+	}
+}
+impl<'i> From<&'i [u8]> for Unknown<'i> {
+	fn from(value: &'i [u8]) -> Self {
+		Self { value, padding: &[] }
+	}
+}
+
+// CHANNEL-NUMBER: a channel number in 0x4000..=0x4FFF followed by 2 bytes of RFFU. Any number
+// decodes, so that a server can answer an out of range ChannelBind with a 400 (RFC 8656 section
+// 12.2) instead of failing to parse it: check decoded numbers with valid.
+#[cfg(feature = "turn")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelNumber(u16);
+#[cfg(feature = "turn")]
+impl ChannelNumber {
+	pub const MIN: u16 = 0x4000;
+	pub const MAX: u16 = 0x4FFF;
+	pub fn new(number: u16) -> Option<Self> {
+		Self(number).valid().then_some(Self(number))
+	}
+	pub fn valid(self) -> bool {
+		(Self::MIN..=Self::MAX).contains(&self.0)
+	}
+	pub fn get(self) -> u16 {
+		self.0
+	}
+}
+#[cfg(feature = "turn")]
+impl StunAttrValue<'_> for ChannelNumber {
 	fn length(&self) -> u16 {
 		4
 	}
 	fn decode(buff: &[u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
-		if buff.len() != 4 { return Err(StunAttrDecodeErr::ValueUnexpectedLength) }
-		Ok(Self(u16::from_be_bytes(buff[..2].try_into().unwrap())))
+		let [c0, c1, _, _] = <[u8; 4]>::try_from(buff)?;
+		Ok(Self(u16::from_be_bytes([c0, c1])))
 	}
 	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
 		buff[..2].copy_from_slice(&self.0.to_be_bytes());
@@ -444,18 +1087,13 @@ impl StunAttrValue<'_> for Channel {
 		buff[3] = 0;
 	}
 }
-impl From<u16> for Channel {
-	fn from(value: u16) -> Self {
-		Self(value)
-	}
-}
-impl From<Channel> for u16 {
-	fn from(value: Channel) -> Self {
+#[cfg(feature = "turn")]
+impl From<ChannelNumber> for u16 {
+	fn from(value: ChannelNumber) -> Self {
 		value.0
 	}
 }
 
-
 #[derive(Debug, Clone)]
 pub enum StunAttr<'i> {
 	// RFC 5389:
@@ -471,26 +1109,222 @@ pub enum StunAttr<'i> {
 	/* 0x8023 */ AlternateServer(ZeroXor<SocketAddr>),
 	/* 0x8028 */ Fingerprint,
 
+	// RFC 8489:
+	/* 0x001C */ IntegritySha256(IntegritySha256<'i>),
+	/* 0x8003 */ AlternateDomain(&'i str),
+	/* 0x001D */ PasswordAlgorithm(PasswordAlgorithm<'i>),
+	/* 0x8002 */ PasswordAlgorithms(PasswordAlgorithms<'i>),
+
 	// RFC 5766:
-	/* 0x000C */ Channel(Channel),
-	/* 0x000D */ Lifetime(u32),
+	#[cfg(feature = "turn")]
+	/* 0x000C */ Channel(ChannelNumber),
+	#[cfg(feature = "turn")]
+	/* 0x000D */ Lifetime(Lifetime),
+	#[cfg(feature = "turn")]
 	/* 0x0012 */ XPeer(SocketAddr),
+	#[cfg(feature = "turn")]
 	/* 0x0013 */ Data(Data<'i>),
+	#[cfg(feature = "turn")]
 	/* 0x0016 */ XRelayed(SocketAddr),
+	#[cfg(feature = "turn")]
+	/* 0x0017 */ RequestedFamily(AddressFamily),
+	#[cfg(feature = "turn")]
 	/* 0x0018 */ EvenPort(EvenPort),
+	#[cfg(feature = "turn")]
 	/* 0x0019 */ RequestedTransport(RequestedTransport),
+	#[cfg(feature = "turn")]
 	/* 0x001A */ DontFragment,
-	/* 0x0022 */ ReservationToken(u32),
+	#[cfg(feature = "turn")]
+	/* 0x0022 */ ReservationToken(u64),
+	#[cfg(feature = "turn")]
+	/* 0x8001 */ AddressError(AddressError<'i>),
+	#[cfg(feature = "turn")]
+	/* 0x8004 */ Icmp(Icmp),
+
+	// RFC 6062:
+	#[cfg(feature = "turn")]
+	/* 0x002A */ ConnectionId(u32),
+
+	// RFC 7635:
+	#[cfg(feature = "turn")]
+	/* 0x001B */ AccessToken(AccessToken<'i>),
+
+	// Pre-RFC 5766 TURN drafts:
+	#[cfg(feature = "turn")]
+	/* 0x0010 */ Bandwidth(u32),
+	#[cfg(feature = "turn")]
+	/* 0x0021 */ TimerVal(u32),
 
 	// RFC 5245 / 8445:
+	#[cfg(feature = "ice")]
 	/* 0x0024 */ Priority(u32),
+	#[cfg(feature = "ice")]
 	/* 0x0025 */ UseCandidate,
+	#[cfg(feature = "ice")]
 	/* 0x8029 */ IceControlled(u64),
+	#[cfg(feature = "ice")]
 	/* 0x802A */ IceControlling(u64),
 
-	Other(u16, &'i [u8]),
+	// RFC 5780:
+	#[cfg(feature = "nat-discovery")]
+	/* 0x0003 */ ChangeRequest(ChangeRequest),
+	#[cfg(feature = "nat-discovery")]
+	/* 0x802B */ ResponseOrigin(ZeroXor<SocketAddr>),
+	#[cfg(feature = "nat-discovery")]
+	/* 0x802C */ OtherAddress(ZeroXor<SocketAddr>),
+	#[cfg(feature = "nat-discovery")]
+	/* 0x0026 */ Padding(Padding),
+	#[cfg(feature = "nat-discovery")]
+	/* 0x0027 */ ResponsePort(ResponsePort),
+
+	// RFC 7982:
+	#[cfg(feature = "nat-discovery")]
+	/* 0x8025 */ TransmitCounter(TransmitCounter),
+
+	// RFC 3489 (classic STUN):
+	#[cfg(feature = "nat-discovery")]
+	/* 0x0002 */ ResponseAddress(ZeroXor<SocketAddr>),
+	#[cfg(feature = "nat-discovery")]
+	/* 0x0004 */ SourceAddress(ZeroXor<SocketAddr>),
+	#[cfg(feature = "nat-discovery")]
+	/* 0x0005 */ ChangedAddress(ZeroXor<SocketAddr>),
+	#[cfg(feature = "nat-discovery")]
+	/* 0x000B */ ReflectedFrom(ZeroXor<SocketAddr>),
+
+	// libwebrtc:
+	#[cfg(feature = "vendor")]
+	/* 0xC057 */ GoogNetworkInfo(GoogNetworkInfo),
+	#[cfg(feature = "vendor")]
+	/* 0xC058 */ GoogLastIceCheckReceived(&'i [u8]),
+
+	// [MS-TURN] / [MS-ICE2]:
+	#[cfg(feature = "vendor")]
+	/* 0x8008 */ MsVersion(u32),
+	#[cfg(feature = "vendor")]
+	/* 0x8050 */ MsSequenceNumber(MsSequenceNumber<'i>),
+	#[cfg(feature = "vendor")]
+	/* 0x8055 */ MsServiceQuality(MsServiceQuality),
+	#[cfg(feature = "vendor")]
+	/* 0x8070 */ MsImplementationVersion(u32),
+
+	Other(u16, Unknown<'i>),
 }
 impl<'i> StunAttr<'i> {
+	pub fn mapped(addr: SocketAddr) -> Self {
+		Self::Mapped(addr.into())
+	}
+	pub fn xor_mapped(addr: SocketAddr) -> Self {
+		Self::XMapped(addr)
+	}
+	#[cfg(feature = "turn")]
+	pub fn xor_peer(addr: SocketAddr) -> Self {
+		Self::XPeer(addr)
+	}
+	#[cfg(feature = "turn")]
+	pub fn xor_relayed(addr: SocketAddr) -> Self {
+		Self::XRelayed(addr)
+	}
+	pub fn alternate_server(addr: SocketAddr) -> Self {
+		Self::AlternateServer(addr.into())
+	}
+	pub fn alternate_domain(domain: &'i str) -> Self {
+		Self::AlternateDomain(domain)
+	}
+	pub fn integrity<K: IntegrityKey>(key: &'i K) -> Self {
+		Self::Integrity(Integrity::sign(key))
+	}
+	pub fn integrity_sha256<K: IntegrityKey>(key: &'i K) -> Self {
+		Self::IntegritySha256(IntegritySha256::sign(key))
+	}
+	pub fn error(code: u16, message: &'i str) -> Self {
+		Self::Error(Error { code, message })
+	}
+	// ERROR-CODE with the standard reason phrase (or none for an unregistered code)
+	pub fn error_code(code: u16) -> Self {
+		Self::error(code, Error::reason(code).unwrap_or(""))
+	}
+	pub fn unknown_attributes(typs: &'i [u16]) -> Self {
+		Self::UnknownAttributes(UnknownAttributes::List(typs))
+	}
+	#[cfg(feature = "turn")]
+	pub fn address_error(family: u8, code: u16, message: &'i str) -> Self {
+		Self::AddressError(AddressError {
+			family,
+			code,
+			message,
+		})
+	}
+	#[cfg(feature = "turn")]
+	pub fn icmp(typ: u8, code: u8, data: u32) -> Self {
+		Self::Icmp(Icmp { typ, code, data })
+	}
+	#[cfg(feature = "turn")]
+	pub fn connection_id(id: u32) -> Self {
+		Self::ConnectionId(id)
+	}
+	#[cfg(feature = "turn")]
+	pub fn access_token(nonce: &'i [u8], encrypted: &'i [u8]) -> Self {
+		Self::AccessToken(AccessToken { nonce, encrypted })
+	}
+	#[cfg(feature = "turn")]
+	pub fn channel(number: ChannelNumber) -> Self {
+		Self::Channel(number)
+	}
+	#[cfg(feature = "turn")]
+	pub fn lifetime(secs: u32) -> Self {
+		Self::Lifetime(Lifetime(secs))
+	}
+	#[cfg(feature = "turn")]
+	pub fn data(data: &'i [u8]) -> Self {
+		Self::Data(Data::Slice(data))
+	}
+	#[cfg(feature = "turn")]
+	pub fn even_port(reserve_next: bool) -> Self {
+		Self::EvenPort(EvenPort(reserve_next))
+	}
+	#[cfg(feature = "turn")]
+	pub fn requested_transport_udp() -> Self {
+		Self::requested_transport(RequestedTransport::Udp)
+	}
+	#[cfg(feature = "turn")]
+	pub fn requested_transport_tcp() -> Self {
+		Self::requested_transport(RequestedTransport::Tcp)
+	}
+	#[cfg(feature = "turn")]
+	pub fn requested_transport(transport: RequestedTransport) -> Self {
+		Self::RequestedTransport(transport)
+	}
+	#[cfg(feature = "nat-discovery")]
+	pub fn change_request(change_ip: bool, change_port: bool) -> Self {
+		Self::ChangeRequest(ChangeRequest {
+			change_ip,
+			change_port,
+		})
+	}
+	#[cfg(feature = "nat-discovery")]
+	pub fn response_origin(addr: SocketAddr) -> Self {
+		Self::ResponseOrigin(addr.into())
+	}
+	#[cfg(feature = "nat-discovery")]
+	pub fn other_address(addr: SocketAddr) -> Self {
+		Self::OtherAddress(addr.into())
+	}
+	#[cfg(feature = "nat-discovery")]
+	pub fn padding(len: u16) -> Self {
+		Self::Padding(Padding(len))
+	}
+	#[cfg(feature = "nat-discovery")]
+	pub fn response_port(port: u16) -> Self {
+		Self::ResponsePort(ResponsePort(port))
+	}
+	#[cfg(feature = "nat-discovery")]
+	pub fn transmit_counter(req: u8, resp: u8) -> Self {
+		Self::TransmitCounter(TransmitCounter { req, resp })
+	}
+	#[cfg(feature = "vendor")]
+	pub fn goog_network_info(network_id: u16, cost: u16) -> Self {
+		Self::GoogNetworkInfo(GoogNetworkInfo { network_id, cost })
+	}
 	pub fn typ(&self) -> u16 {
 		match self {
 			Self::Mapped(_) => 0x0001,
@@ -504,25 +1338,91 @@ impl<'i> StunAttr<'i> {
 			Self::Software(_) => 0x8022,
 			Self::AlternateServer(_) => 0x8023,
 			Self::Fingerprint => 0x8028,
+			Self::IntegritySha256(_) => 0x001C,
+			Self::AlternateDomain(_) => 0x8003,
+			Self::PasswordAlgorithm(_) => 0x001D,
+			Self::PasswordAlgorithms(_) => 0x8002,
+			#[cfg(feature = "turn")]
 			Self::Channel(_) => 0x000C,
+			#[cfg(feature = "turn")]
 			Self::Lifetime(_) => 0x000D,
+			#[cfg(feature = "turn")]
 			Self::XPeer(_) => 0x0012,
+			#[cfg(feature = "turn")]
 			Self::Data(_) => 0x0013,
+			#[cfg(feature = "turn")]
 			Self::XRelayed(_) => 0x0016,
+			#[cfg(feature = "turn")]
+			Self::RequestedFamily(_) => 0x0017,
+			#[cfg(feature = "turn")]
 			Self::EvenPort(_) => 0x0018,
+			#[cfg(feature = "turn")]
 			Self::RequestedTransport(_) => 0x0019,
+			#[cfg(feature = "turn")]
 			Self::DontFragment => 0x001A,
+			#[cfg(feature = "turn")]
 			Self::ReservationToken(_) => 0x0022,
+			#[cfg(feature = "turn")]
+			Self::AddressError(_) => 0x8001,
+			#[cfg(feature = "turn")]
+			Self::Icmp(_) => 0x8004,
+			#[cfg(feature = "turn")]
+			Self::ConnectionId(_) => 0x002A,
+			#[cfg(feature = "turn")]
+			Self::AccessToken(_) => 0x001B,
+			#[cfg(feature = "turn")]
+			Self::Bandwidth(_) => 0x0010,
+			#[cfg(feature = "turn")]
+			Self::TimerVal(_) => 0x0021,
+			#[cfg(feature = "ice")]
 			Self::Priority(_) => 0x0024,
+			#[cfg(feature = "ice")]
 			Self::UseCandidate => 0x0025,
+			#[cfg(feature = "ice")]
 			Self::IceControlled(_) => 0x8029,
+			#[cfg(feature = "ice")]
 			Self::IceControlling(_) => 0x802A,
+			#[cfg(feature = "nat-discovery")]
+			Self::ChangeRequest(_) => 0x0003,
+			#[cfg(feature = "nat-discovery")]
+			Self::ResponseOrigin(_) => 0x802B,
+			#[cfg(feature = "nat-discovery")]
+			Self::OtherAddress(_) => 0x802C,
+			#[cfg(feature = "nat-discovery")]
+			Self::Padding(_) => 0x0026,
+			#[cfg(feature = "nat-discovery")]
+			Self::ResponsePort(_) => 0x0027,
+			#[cfg(feature = "nat-discovery")]
+			Self::TransmitCounter(_) => 0x8025,
+			#[cfg(feature = "nat-discovery")]
+			Self::ResponseAddress(_) => 0x0002,
+			#[cfg(feature = "nat-discovery")]
+			Self::SourceAddress(_) => 0x0004,
+			#[cfg(feature = "nat-discovery")]
+			Self::ChangedAddress(_) => 0x0005,
+			#[cfg(feature = "nat-discovery")]
+			Self::ReflectedFrom(_) => 0x000B,
+			#[cfg(feature = "vendor")]
+			Self::GoogNetworkInfo(_) => 0xC057,
+			#[cfg(feature = "vendor")]
+			Self::GoogLastIceCheckReceived(_) => 0xC058,
+			#[cfg(feature = "vendor")]
+			Self::MsVersion(_) => 0x8008,
+			#[cfg(feature = "vendor")]
+			Self::MsSequenceNumber(_) => 0x8050,
+			#[cfg(feature = "vendor")]
+			Self::MsServiceQuality(_) => 0x8055,
+			#[cfg(feature = "vendor")]
+			Self::MsImplementationVersion(_) => 0x8070,
 			Self::Other(typ, _) => *typ,
 		}
 	}
 	pub fn value(&self) -> &dyn StunAttrValue<'i> {
 		match self {
-			Self::DontFragment | Self::UseCandidate => &(),
+			#[cfg(feature = "turn")]
+			Self::DontFragment => &(),
+			#[cfg(feature = "ice")]
+			Self::UseCandidate => &(),
 			Self::Mapped(v) => v,
 			Self::Username(v) => v,
 			Self::Integrity(v) => v,
@@ -534,39 +1434,113 @@ impl<'i> StunAttr<'i> {
 			Self::Software(v) => v,
 			Self::AlternateServer(v) => v,
 			Self::Fingerprint => &Fingerprint,
+			Self::IntegritySha256(v) => v,
+			Self::AlternateDomain(v) => v,
+			Self::PasswordAlgorithm(v) => v,
+			Self::PasswordAlgorithms(v) => v,
+			#[cfg(feature = "turn")]
 			Self::Channel(v) => v,
+			#[cfg(feature = "turn")]
 			Self::Lifetime(v) => v,
+			#[cfg(feature = "turn")]
 			Self::XPeer(v) => v,
+			#[cfg(feature = "turn")]
 			Self::Data(v) => v,
+			#[cfg(feature = "turn")]
 			Self::XRelayed(v) => v,
+			#[cfg(feature = "turn")]
+			Self::RequestedFamily(v) => v,
+			#[cfg(feature = "turn")]
 			Self::EvenPort(v) => v,
+			#[cfg(feature = "turn")]
 			Self::RequestedTransport(v) => v,
+			#[cfg(feature = "turn")]
 			Self::ReservationToken(v) => v,
+			#[cfg(feature = "turn")]
+			Self::AddressError(v) => v,
+			#[cfg(feature = "turn")]
+			Self::Icmp(v) => v,
+			#[cfg(feature = "turn")]
+			Self::ConnectionId(v) => v,
+			#[cfg(feature = "turn")]
+			Self::AccessToken(v) => v,
+			#[cfg(feature = "turn")]
+			Self::Bandwidth(v) => v,
+			#[cfg(feature = "turn")]
+			Self::TimerVal(v) => v,
+			#[cfg(feature = "ice")]
 			Self::Priority(v) => v,
+			#[cfg(feature = "ice")]
 			Self::IceControlled(v) => v,
+			#[cfg(feature = "ice")]
 			Self::IceControlling(v) => v,
+			#[cfg(feature = "nat-discovery")]
+			Self::ChangeRequest(v) => v,
+			#[cfg(feature = "nat-discovery")]
+			Self::ResponseOrigin(v) => v,
+			#[cfg(feature = "nat-discovery")]
+			Self::OtherAddress(v) => v,
+			#[cfg(feature = "nat-discovery")]
+			Self::Padding(v) => v,
+			#[cfg(feature = "nat-discovery")]
+			Self::ResponsePort(v) => v,
+			#[cfg(feature = "nat-discovery")]
+			Self::TransmitCounter(v) => v,
+			#[cfg(feature = "nat-discovery")]
+			Self::ResponseAddress(v) => v,
+			#[cfg(feature = "nat-discovery")]
+			Self::SourceAddress(v) => v,
+			#[cfg(feature = "nat-discovery")]
+			Self::ChangedAddress(v) => v,
+			#[cfg(feature = "nat-discovery")]
+			Self::ReflectedFrom(v) => v,
+			#[cfg(feature = "vendor")]
+			Self::GoogNetworkInfo(v) => v,
+			#[cfg(feature = "vendor")]
+			Self::GoogLastIceCheckReceived(v) => v,
+			#[cfg(feature = "vendor")]
+			Self::MsVersion(v) => v,
+			#[cfg(feature = "vendor")]
+			Self::MsSequenceNumber(v) => v,
+			#[cfg(feature = "vendor")]
+			Self::MsServiceQuality(v) => v,
+			#[cfg(feature = "vendor")]
+			Self::MsImplementationVersion(v) => v,
 			Self::Other(_, v) => v,
 		}
 	}
 	pub fn length(&self) -> u16 {
 		self.value().length()
 	}
-	pub fn len(&self) -> u16 {
-		let mut ret = 4 + self.length();
-		while ret % 4 != 0 {
-			ret += 1;
-		}
-		ret
+	// Length of the attribute header + value, not including padding
+	pub fn len(&self) -> Result<u16, StunEncodeErr> {
+		4u16.checked_add(self.length()).ok_or(StunEncodeErr::TooLarge)
 	}
-	pub fn encode(&self, buff: &mut [u8], ctx: AttrContext<'_>) {
+	// Length of the attribute as it occupies the message, including padding
+	pub fn padded_len(&self) -> Result<u16, StunEncodeErr> {
+		self.len()?
+			.checked_next_multiple_of(4)
+			.ok_or(StunEncodeErr::TooLarge)
+	}
+	pub fn encode(&self, buff: &mut [u8], ctx: AttrContext<'_>) -> Result<(), StunEncodeErr> {
+		let padded_len = self.padded_len()? as usize;
+		let buff = buff
+			.get_mut(..padded_len)
+			.ok_or(StunEncodeErr::BufferTooSmall(padded_len))?;
 		buff[0..][..2].copy_from_slice(&self.typ().to_be_bytes());
 		buff[2..][..2].copy_from_slice(&self.length().to_be_bytes());
 		let mut length = self.length();
 		self.value().encode(&mut buff[4..][..length as usize], ctx);
-		while length % 4 != 0 {
-			buff[4 + length as usize] = 0;
+		let padding = match self {
+			Self::Other(_, v) => v.padding,
+			_ => &[],
+		};
+		let mut padding = padding.iter();
+		while !length.is_multiple_of(4) {
+			buff[4 + length as usize] = padding.next().copied().unwrap_or(0);
 			length += 1;
 		}
+		Ok(())
 	}
 	pub fn decode(
 		typ: u16,
@@ -588,26 +1562,89 @@ impl<'i> StunAttr<'i> {
 				Fingerprint::decode(buff, ctx)?;
 				Self::Fingerprint
 			}
+			0x001C => Self::IntegritySha256(StunAttrValue::decode(buff, ctx)?),
+			0x8003 => Self::AlternateDomain(StunAttrValue::decode(buff, ctx)?),
+			0x001D => Self::PasswordAlgorithm(StunAttrValue::decode(buff, ctx)?),
+			0x8002 => Self::PasswordAlgorithms(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
 			0x000C => Self::Channel(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
 			0x000D => Self::Lifetime(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
 			0x0012 => Self::XPeer(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
 			0x0013 => Self::Data(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
 			0x0016 => Self::XRelayed(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
+			0x0017 => Self::RequestedFamily(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
 			0x0018 => Self::EvenPort(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
 			0x0019 => Self::RequestedTransport(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
 			0x001A => {
 				<()>::decode(buff, ctx.clone())?;
 				Self::DontFragment
 			}
+			#[cfg(feature = "turn")]
 			0x0022 => Self::ReservationToken(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
+			0x8001 => Self::AddressError(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
+			0x8004 => Self::Icmp(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
+			0x002A => Self::ConnectionId(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
+			0x001B => Self::AccessToken(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
+			0x0010 => Self::Bandwidth(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "turn")]
+			0x0021 => Self::TimerVal(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "ice")]
 			0x0024 => Self::Priority(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "ice")]
 			0x0025 => {
 				<()>::decode(buff, ctx.clone())?;
 				Self::UseCandidate
 			}
+			#[cfg(feature = "ice")]
 			0x8029 => Self::IceControlled(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "ice")]
 			0x802A => Self::IceControlling(StunAttrValue::decode(buff, ctx)?),
-			typ => Self::Other(typ, buff),
+			#[cfg(feature = "nat-discovery")]
+			0x0003 => Self::ChangeRequest(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "nat-discovery")]
+			0x802B => Self::ResponseOrigin(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "nat-discovery")]
+			0x802C => Self::OtherAddress(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "nat-discovery")]
+			0x0026 => Self::Padding(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "nat-discovery")]
+			0x0027 => Self::ResponsePort(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "nat-discovery")]
+			0x8025 => Self::TransmitCounter(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "nat-discovery")]
+			0x0002 => Self::ResponseAddress(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "nat-discovery")]
+			0x0004 => Self::SourceAddress(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "nat-discovery")]
+			0x0005 => Self::ChangedAddress(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "nat-discovery")]
+			0x000B => Self::ReflectedFrom(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "vendor")]
+			0xC057 => Self::GoogNetworkInfo(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "vendor")]
+			0xC058 => Self::GoogLastIceCheckReceived(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "vendor")]
+			0x8008 => Self::MsVersion(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "vendor")]
+			0x8050 => Self::MsSequenceNumber(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "vendor")]
+			0x8055 => Self::MsServiceQuality(StunAttrValue::decode(buff, ctx)?),
+			#[cfg(feature = "vendor")]
+			0x8070 => Self::MsImplementationVersion(StunAttrValue::decode(buff, ctx)?),
+			typ => Self::Other(typ, StunAttrValue::decode(buff, ctx)?),
 		})
 	}
 }
@@ -0,0 +1,51 @@
+// Microsoft's STUN / TURN extensions ([MS-TURN], [MS-ICE2]), as used by Teams and Skype edge servers
+use super::{AttrContext, StunAttrDecodeErr, StunAttrValue};
+
+// MS-SEQUENCE-NUMBER: orders the messages of a TURN connection
+#[derive(Debug, Clone)]
+pub struct MsSequenceNumber<'i> {
+	pub connection_id: &'i [u8; 20],
+	pub sequence: u32,
+}
+impl<'i> StunAttrValue<'i> for MsSequenceNumber<'i> {
+	fn length(&self) -> u16 {
+		24
+	}
+	fn decode(buff: &'i [u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
+		let (connection_id, sequence) = buff
+			.split_first_chunk::<20>()
+			.ok_or(StunAttrDecodeErr::ValueUnexpectedLength)?;
+		Ok(Self {
+			connection_id,
+			sequence: u32::from_be_bytes(sequence.try_into()?),
+		})
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff[..20].copy_from_slice(self.connection_id);
+		buff[20..].copy_from_slice(&self.sequence.to_be_bytes());
+	}
+}
+
+// MS-SERVICE-QUALITY: the kind of media on the allocation (1 audio, 2 video, 3 supplemental video,
+// 4 data) and whether it should be relayed with best effort (0) or reliably (1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsServiceQuality {
+	pub stream_type: u16,
+	pub quality: u16,
+}
+impl StunAttrValue<'_> for MsServiceQuality {
+	fn length(&self) -> u16 {
+		4
+	}
+	fn decode(buff: &[u8], _: AttrContext<'_>) -> Result<Self, StunAttrDecodeErr> {
+		let [s0, s1, q0, q1] = <[u8; 4]>::try_from(buff)?;
+		Ok(Self {
+			stream_type: u16::from_be_bytes([s0, s1]),
+			quality: u16::from_be_bytes([q0, q1]),
+		})
+	}
+	fn encode(&self, buff: &mut [u8], _: AttrContext<'_>) {
+		buff[..2].copy_from_slice(&self.stream_type.to_be_bytes());
+		buff[2..].copy_from_slice(&self.quality.to_be_bytes());
+	}
+}
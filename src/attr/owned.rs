@@ -0,0 +1,45 @@
+// A single attribute detached from the buffer it was decoded from, e.g. to hold onto a USERNAME
+// across an await while the receive buffer is reused. The attribute is kept encoded, with XOR'd
+// addresses stored plain, and decoded again on access. Verify MESSAGE-INTEGRITY before detaching:
+// a detached integrity attribute no longer has the message it covered.
+use super::typ::FINGERPRINT;
+use super::{AttrContext, StunAttr, StunAttrDecodeErr};
+use crate::attrs::parse_at;
+use crate::StunEncodeErr;
+
+// Without a header, XOR'd addresses are XOR'd with zeros
+const NO_HEADER: [u8; 20] = [0; 20];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StunAttrOwned {
+	bytes: Vec<u8>,
+}
+impl StunAttrOwned {
+	pub fn typ(&self) -> u16 {
+		u16::from_be_bytes([self.bytes[0], self.bytes[1]])
+	}
+	pub fn get(&self) -> Result<StunAttr<'_>, StunAttrDecodeErr> {
+		// FINGERPRINT's value only makes sense at the end of its message
+		if self.typ() == FINGERPRINT {
+			return Ok(StunAttr::Fingerprint);
+		}
+		parse_at(&self.bytes, &NO_HEADER, 0)
+			.map(|(attr, _)| attr)
+			.unwrap_or(Err(StunAttrDecodeErr::AttrLengthExceedsPacketLength))
+	}
+}
+impl TryFrom<&StunAttr<'_>> for StunAttrOwned {
+	type Error = StunEncodeErr;
+	fn try_from(value: &StunAttr<'_>) -> Result<Self, StunEncodeErr> {
+		let mut bytes = vec![0; value.padded_len()? as usize];
+		let ctx = AttrContext {
+			header: &NO_HEADER,
+			zero_xor_bytes: true,
+			attrs_prefix: &[],
+			attr_len: value.len()?,
+			padding: &[],
+		};
+		value.encode(&mut bytes, ctx)?;
+		Ok(Self { bytes })
+	}
+}
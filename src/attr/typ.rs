@@ -0,0 +1,69 @@
+// Attribute types from the IETF-assigned part of the IANA STUN Attributes registry (including the
+// ones only reserved from RFC 3489), plus the vendor ones this crate knows; the rest of the vendor
+// assignments aren't listed. Available whether or not the attribute's feature set is enabled.
+
+// Comprehension-required:
+pub const MAPPED_ADDRESS: u16 = 0x0001;
+pub const RESPONSE_ADDRESS: u16 = 0x0002;
+pub const CHANGE_REQUEST: u16 = 0x0003;
+pub const SOURCE_ADDRESS: u16 = 0x0004;
+pub const CHANGED_ADDRESS: u16 = 0x0005;
+pub const USERNAME: u16 = 0x0006;
+pub const PASSWORD: u16 = 0x0007;
+pub const MESSAGE_INTEGRITY: u16 = 0x0008;
+pub const ERROR_CODE: u16 = 0x0009;
+pub const UNKNOWN_ATTRIBUTES: u16 = 0x000A;
+pub const REFLECTED_FROM: u16 = 0x000B;
+pub const CHANNEL_NUMBER: u16 = 0x000C;
+pub const LIFETIME: u16 = 0x000D;
+pub const BANDWIDTH: u16 = 0x0010;
+pub const XOR_PEER_ADDRESS: u16 = 0x0012;
+pub const DATA: u16 = 0x0013;
+pub const REALM: u16 = 0x0014;
+pub const NONCE: u16 = 0x0015;
+pub const XOR_RELAYED_ADDRESS: u16 = 0x0016;
+pub const REQUESTED_ADDRESS_FAMILY: u16 = 0x0017;
+pub const EVEN_PORT: u16 = 0x0018;
+pub const REQUESTED_TRANSPORT: u16 = 0x0019;
+pub const DONT_FRAGMENT: u16 = 0x001A;
+pub const ACCESS_TOKEN: u16 = 0x001B;
+pub const MESSAGE_INTEGRITY_SHA256: u16 = 0x001C;
+pub const PASSWORD_ALGORITHM: u16 = 0x001D;
+pub const USERHASH: u16 = 0x001E;
+pub const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+pub const TIMER_VAL: u16 = 0x0021;
+pub const RESERVATION_TOKEN: u16 = 0x0022;
+pub const PRIORITY: u16 = 0x0024;
+pub const USE_CANDIDATE: u16 = 0x0025;
+pub const PADDING: u16 = 0x0026;
+pub const RESPONSE_PORT: u16 = 0x0027;
+pub const CONNECTION_ID: u16 = 0x002A;
+
+// Comprehension-optional:
+pub const ADDITIONAL_ADDRESS_FAMILY: u16 = 0x8000;
+pub const ADDRESS_ERROR_CODE: u16 = 0x8001;
+pub const PASSWORD_ALGORITHMS: u16 = 0x8002;
+pub const ALTERNATE_DOMAIN: u16 = 0x8003;
+pub const ICMP: u16 = 0x8004;
+pub const MS_VERSION: u16 = 0x8008;
+pub const SOFTWARE: u16 = 0x8022;
+pub const ALTERNATE_SERVER: u16 = 0x8023;
+pub const TRANSACTION_TRANSMIT_COUNTER: u16 = 0x8025;
+pub const CACHE_TIMEOUT: u16 = 0x8027;
+pub const FINGERPRINT: u16 = 0x8028;
+pub const ICE_CONTROLLED: u16 = 0x8029;
+pub const ICE_CONTROLLING: u16 = 0x802A;
+pub const RESPONSE_ORIGIN: u16 = 0x802B;
+pub const OTHER_ADDRESS: u16 = 0x802C;
+pub const ECN_CHECK: u16 = 0x802D;
+pub const THIRD_PARTY_AUTHORIZATION: u16 = 0x802E;
+pub const MOBILITY_TICKET: u16 = 0x8030;
+pub const MS_SEQUENCE_NUMBER: u16 = 0x8050;
+pub const MS_SERVICE_QUALITY: u16 = 0x8055;
+pub const MS_IMPLEMENTATION_VERSION: u16 = 0x8070;
+pub const GOOG_NETWORK_INFO: u16 = 0xC057;
+pub const GOOG_LAST_ICE_CHECK_RECEIVED: u16 = 0xC058;
+
+// PASSWORD-ALGORITHM(S) algorithm numbers
+pub const PASSWORD_ALGORITHM_MD5: u16 = 0x0001;
+pub const PASSWORD_ALGORITHM_SHA256: u16 = 0x0002;
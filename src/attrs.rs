@@ -1,6 +1,9 @@
 use crate::attr::{AttrContext, StunAttr, StunAttrDecodeErr};
+use crate::StunEncodeErr;
 
 pub mod flat;
+pub mod index;
+pub mod list;
 
 #[derive(Debug, Clone)]
 pub enum StunAttrs<'i> {
@@ -12,39 +15,47 @@ pub enum StunAttrs<'i> {
 	// Flat(&'i StunAttrsFlat<'i>) // TODO: Add?
 }
 impl<'i> StunAttrs<'i> {
-	pub fn length(&self) -> u16 {
+	pub fn length(&self) -> Result<u16, StunEncodeErr> {
 		match self {
-			Self::Parse { buff, .. } => buff.len() as u16,
+			Self::Parse { buff, .. } => u16::try_from(buff.len()).map_err(|_| StunEncodeErr::TooLarge),
 			Self::List(l) => {
-				let mut ret = 0;
+				let mut ret = 0u16;
 				for attr in l.iter() {
-					ret += attr.len();
+					ret = ret.checked_add(attr.padded_len()?).ok_or(StunEncodeErr::TooLarge)?;
 				}
-				ret
+				Ok(ret)
 			}
 		}
 	}
-	pub fn encode(&self, buff: &mut [u8], header: &[u8; 20]) {
+	// Returns the length written
+	pub fn encode(&self, buff: &mut [u8], header: &[u8; 20]) -> Result<usize, StunEncodeErr> {
+		let required = self.length()? as usize;
+		let buff = buff
+			.get_mut(..required)
+			.ok_or(StunEncodeErr::BufferTooSmall(required))?;
 		match self {
 			Self::Parse { buff: parse, .. } => buff.copy_from_slice(parse),
 			Self::List(l) => {
 				let mut length = 0;
 				let (mut attrs_prefix, mut to_write) = buff.split_at_mut(length);
 				for attr in l.iter() {
-					let attr_len = attr.len();
+					let attr_len = attr.len()?;
+					let padded_len = attr.padded_len()?;
 					let ctx = AttrContext {
 						header,
 						attrs_prefix,
 						attr_len,
 						zero_xor_bytes: false,
+						padding: &[],
 					};
-					attr.encode(&mut to_write[..attr_len as usize], ctx);
+					attr.encode(to_write, ctx)?;
 
-					length += attr.len() as usize;
+					length += padded_len as usize;
 					(attrs_prefix, to_write) = buff.split_at_mut(length);
 				}
 			}
 		}
+		Ok(required)
 	}
 }
 impl<'i, 'a> IntoIterator for &'a StunAttrs<'i> {
@@ -57,7 +68,7 @@ impl<'i, 'a> IntoIterator for &'a StunAttrs<'i> {
 				header,
 				length: 0,
 			},
-			StunAttrs::List(l) => StunAttrsIter::List(l.into_iter()),
+			StunAttrs::List(l) => StunAttrsIter::List(l.iter()),
 		}
 	}
 }
@@ -67,6 +78,36 @@ impl<'i> From<&'i [StunAttr<'i>]> for StunAttrs<'i> {
 	}
 }
 
+// Decodes the attribute starting at offset, returning it along with the offset of the next attribute
+pub(crate) fn parse_at<'i>(
+	buff: &'i [u8],
+	header: &'i [u8; 20],
+	offset: usize,
+) -> Option<(Result<StunAttr<'i>, StunAttrDecodeErr>, usize)> {
+	let (attrs_prefix, unread) = buff.split_at_checked(offset)?;
+	let (&[t0, t1, l0, l1], value) = unread.split_first_chunk::<4>()?;
+	let typ = u16::from_be_bytes([t0, t1]);
+	let attr_length = u16::from_be_bytes([l0, l1]);
+	let attr_len = attr_length.saturating_add(4);
+	let padded_len = (4 + attr_length as usize).next_multiple_of(4);
+	let ret = match value.split_at_checked(attr_length as usize) {
+		None => Err(StunAttrDecodeErr::AttrLengthExceedsPacketLength),
+		Some((data, rest)) => {
+			let ctx = AttrContext {
+				header,
+				attrs_prefix,
+				attr_len,
+				zero_xor_bytes: false,
+				padding: rest.get(..padded_len - 4 - attr_length as usize).unwrap_or(rest),
+			};
+			StunAttr::decode(typ, data, ctx)
+		}
+	};
+
+	// The final attribute's padding may be missing from a malformed packet
+	Some((ret, (offset + padded_len).min(buff.len())))
+}
+
 pub enum StunAttrsIter<'i, 'a> {
 	Parse {
 		header: &'i [u8; 20],
@@ -85,33 +126,9 @@ impl<'i, 'a> Iterator for StunAttrsIter<'i, 'a> {
 				header,
 				length,
 			} => {
-				let (attrs_prefix, unread) = buff.split_at(*length);
-				if unread.len() < 4 {
-					return None;
-				}
-				let typ = u16::from_be_bytes(unread[0..][..2].try_into().unwrap());
-				let attr_length = u16::from_be_bytes(unread[2..][..2].try_into().unwrap());
-				let attr_len = 4 + attr_length;
-				let ret = Some(if unread.len() < attr_len as usize {
-					Err(StunAttrDecodeErr::AttrLengthExceedsPacketLength)
-				} else {
-					let ctx = AttrContext {
-						header,
-						attrs_prefix,
-						attr_len,
-						zero_xor_bytes: false,
-					};
-					let data = &unread[4..][..attr_length as usize];
-					StunAttr::decode(typ, data, ctx)
-				});
-
-				let mut padded_len = attr_len;
-				while padded_len % 4 != 0 {
-					padded_len += 1;
-				}
-				*length = *length + padded_len as usize;
-
-				ret
+				let (ret, next) = parse_at(buff, header, *length)?;
+				*length = next;
+				Some(ret)
 			}
 		}
 	}
@@ -1,9 +1,21 @@
 use std::net::SocketAddr;
 
-use crate::attr::{Integrity, Error, UnknownAttributes, StunAttr, Data};
+use crate::attr::{Integrity, IntegritySha256, Error, PasswordAlgorithm, PasswordAlgorithms, UnknownAttributes, StunAttr, ZeroXor};
+#[cfg(feature = "nat-discovery")]
+use crate::attr::{ChangeRequest, Padding, ResponsePort, TransmitCounter};
+#[cfg(feature = "vendor")]
+use crate::attr::ms::{MsSequenceNumber, MsServiceQuality};
+#[cfg(feature = "vendor")]
+use crate::attr::GoogNetworkInfo;
+#[cfg(feature = "turn")]
+use crate::attr::{
+	AccessToken, AddressError, AddressFamily, ChannelNumber, Data, Icmp, Lifetime,
+	RequestedTransport,
+};
+use crate::auth::IntegrityKey;
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Flat<'i> {
 	pub mapped: Option<SocketAddr>,
 	pub username: Option<&'i str>,
@@ -16,119 +28,399 @@ pub struct Flat<'i> {
 	pub software: Option<&'i str>,
 	pub alternate_server: Option<SocketAddr>,
 	pub fingerprint: Option<()>,
-	pub channel: Option<u16>,
-	pub lifetime: Option<u32>,
+	pub integrity_sha256: Option<IntegritySha256<'i>>,
+	pub alternate_domain: Option<&'i str>,
+	pub password_algorithm: Option<PasswordAlgorithm<'i>>,
+	pub password_algorithms: Option<PasswordAlgorithms<'i>>,
+	#[cfg(feature = "turn")]
+	pub channel: Option<ChannelNumber>,
+	#[cfg(feature = "turn")]
+	pub lifetime: Option<Lifetime>,
+	#[cfg(feature = "turn")]
 	pub xpeer: Option<SocketAddr>,
+	#[cfg(feature = "turn")]
 	pub data: Option<&'i [u8]>,
+	#[cfg(feature = "turn")]
 	pub xrelayed: Option<SocketAddr>,
+	#[cfg(feature = "turn")]
+	pub requested_family: Option<AddressFamily>,
+	#[cfg(feature = "turn")]
 	pub even_port: Option<bool>,
-	pub requested_transport: Option<u8>,
+	#[cfg(feature = "turn")]
+	pub requested_transport: Option<RequestedTransport>,
+	#[cfg(feature = "turn")]
 	pub dont_fragment: Option<()>,
-	pub reservation_token: Option<u32>,
+	#[cfg(feature = "turn")]
+	pub reservation_token: Option<u64>,
+	#[cfg(feature = "turn")]
+	pub address_error: Option<AddressError<'i>>,
+	#[cfg(feature = "turn")]
+	pub icmp: Option<Icmp>,
+	#[cfg(feature = "turn")]
+	pub connection_id: Option<u32>,
+	#[cfg(feature = "turn")]
+	pub access_token: Option<AccessToken<'i>>,
+	#[cfg(feature = "turn")]
+	pub bandwidth: Option<u32>,
+	#[cfg(feature = "turn")]
+	pub timer_val: Option<u32>,
+	#[cfg(feature = "ice")]
 	pub priority: Option<u32>,
+	#[cfg(feature = "ice")]
 	pub use_candidate: Option<()>,
+	#[cfg(feature = "ice")]
 	pub ice_controlled: Option<u64>,
-	pub ice_controlling: Option<u64>
+	#[cfg(feature = "ice")]
+	pub ice_controlling: Option<u64>,
+	#[cfg(feature = "nat-discovery")]
+	pub change_request: Option<ChangeRequest>,
+	#[cfg(feature = "nat-discovery")]
+	pub response_origin: Option<SocketAddr>,
+	#[cfg(feature = "nat-discovery")]
+	pub other_address: Option<SocketAddr>,
+	#[cfg(feature = "nat-discovery")]
+	pub padding: Option<u16>,
+	#[cfg(feature = "nat-discovery")]
+	pub response_port: Option<u16>,
+	#[cfg(feature = "nat-discovery")]
+	pub transmit_counter: Option<TransmitCounter>,
+	#[cfg(feature = "nat-discovery")]
+	pub response_address: Option<SocketAddr>,
+	#[cfg(feature = "nat-discovery")]
+	pub source_address: Option<SocketAddr>,
+	#[cfg(feature = "nat-discovery")]
+	pub changed_address: Option<SocketAddr>,
+	#[cfg(feature = "nat-discovery")]
+	pub reflected_from: Option<SocketAddr>,
+	#[cfg(feature = "vendor")]
+	pub goog_network_info: Option<GoogNetworkInfo>,
+	#[cfg(feature = "vendor")]
+	pub goog_last_ice_check_received: Option<&'i [u8]>,
+	#[cfg(feature = "vendor")]
+	pub ms_version: Option<u32>,
+	#[cfg(feature = "vendor")]
+	pub ms_sequence_number: Option<MsSequenceNumber<'i>>,
+	#[cfg(feature = "vendor")]
+	pub ms_service_quality: Option<MsServiceQuality>,
+	#[cfg(feature = "vendor")]
+	pub ms_implementation_version: Option<u32>
 }
 impl<'i> Flat<'i> {
 	// check_auth only works if the packet contains a username.
-	pub fn check_auth<T: AsRef<[u8]>, F: FnOnce(&str, Option<&str>) -> Option<T>>(
+	pub fn check_auth<T: IntegrityKey, F: FnOnce(&str, Option<&str>) -> Option<T>>(
 		&self,
 		f: F,
 	) -> Option<(&'i str, T)> {
 		let username = self.username?;
 		let realm = self.realm;
-		let integrity = self.integrity.clone()?;
-		let password = f(username, realm)?;
+		let key = f(username, realm)?;
 
-		integrity
-			.verify(password.as_ref())
-			.then_some((username, password))
+		// Prefer the SHA256 integrity when the message has both
+		let verified = match (&self.integrity_sha256, &self.integrity) {
+			(Some(integrity), _) => integrity.verify(&key),
+			(None, Some(integrity)) => integrity.verify(&key),
+			(None, None) => false,
+		};
+		verified.then_some((username, key))
+	}
+	// The reverse of collecting: appends an attribute for each field that is set, ending with the
+	// integrity and fingerprint attributes. Set integrity to Integrity::sign(..) to build a signed
+	// response.
+	pub fn to_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		if let Some(v) = self.mapped {
+			out.push(StunAttr::Mapped(ZeroXor(v)));
+		}
+		if let Some(v) = self.xmapped {
+			out.push(StunAttr::XMapped(v));
+		}
+		if let Some(v) = self.username {
+			out.push(StunAttr::Username(v));
+		}
+		if let Some(v) = self.realm {
+			out.push(StunAttr::Realm(v));
+		}
+		if let Some(v) = self.nonce {
+			out.push(StunAttr::Nonce(v));
+		}
+		if let Some(v) = &self.error {
+			out.push(StunAttr::Error(v.clone()));
+		}
+		if let Some(v) = &self.unknown_attributes {
+			out.push(StunAttr::UnknownAttributes(v.clone()));
+		}
+		if let Some(v) = self.software {
+			out.push(StunAttr::Software(v));
+		}
+		if let Some(v) = self.alternate_server {
+			out.push(StunAttr::AlternateServer(ZeroXor(v)));
+		}
+		if let Some(v) = self.alternate_domain {
+			out.push(StunAttr::AlternateDomain(v));
+		}
+		if let Some(v) = self.password_algorithm {
+			out.push(StunAttr::PasswordAlgorithm(v));
+		}
+		if let Some(v) = &self.password_algorithms {
+			out.push(StunAttr::PasswordAlgorithms(v.clone()));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.channel {
+			out.push(StunAttr::channel(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.lifetime {
+			out.push(StunAttr::Lifetime(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.xpeer {
+			out.push(StunAttr::XPeer(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.xrelayed {
+			out.push(StunAttr::XRelayed(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.requested_family {
+			out.push(StunAttr::RequestedFamily(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.even_port {
+			out.push(StunAttr::even_port(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.requested_transport {
+			out.push(StunAttr::RequestedTransport(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(()) = self.dont_fragment {
+			out.push(StunAttr::DontFragment);
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.reservation_token {
+			out.push(StunAttr::ReservationToken(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = &self.address_error {
+			out.push(StunAttr::AddressError(v.clone()));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.icmp {
+			out.push(StunAttr::Icmp(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.connection_id {
+			out.push(StunAttr::ConnectionId(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = &self.access_token {
+			out.push(StunAttr::AccessToken(v.clone()));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.bandwidth {
+			out.push(StunAttr::Bandwidth(v));
+		}
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.timer_val {
+			out.push(StunAttr::TimerVal(v));
+		}
+		#[cfg(feature = "ice")]
+		if let Some(v) = self.priority {
+			out.push(StunAttr::Priority(v));
+		}
+		#[cfg(feature = "ice")]
+		if let Some(()) = self.use_candidate {
+			out.push(StunAttr::UseCandidate);
+		}
+		#[cfg(feature = "ice")]
+		if let Some(v) = self.ice_controlled {
+			out.push(StunAttr::IceControlled(v));
+		}
+		#[cfg(feature = "ice")]
+		if let Some(v) = self.ice_controlling {
+			out.push(StunAttr::IceControlling(v));
+		}
+		#[cfg(feature = "nat-discovery")]
+		if let Some(v) = self.change_request {
+			out.push(StunAttr::ChangeRequest(v));
+		}
+		#[cfg(feature = "nat-discovery")]
+		if let Some(v) = self.response_origin {
+			out.push(StunAttr::ResponseOrigin(ZeroXor(v)));
+		}
+		#[cfg(feature = "nat-discovery")]
+		if let Some(v) = self.other_address {
+			out.push(StunAttr::OtherAddress(ZeroXor(v)));
+		}
+		#[cfg(feature = "nat-discovery")]
+		if let Some(v) = self.response_port {
+			out.push(StunAttr::ResponsePort(ResponsePort(v)));
+		}
+		#[cfg(feature = "nat-discovery")]
+		if let Some(v) = self.transmit_counter {
+			out.push(StunAttr::TransmitCounter(v));
+		}
+		#[cfg(feature = "nat-discovery")]
+		if let Some(v) = self.response_address {
+			out.push(StunAttr::ResponseAddress(ZeroXor(v)));
+		}
+		#[cfg(feature = "nat-discovery")]
+		if let Some(v) = self.source_address {
+			out.push(StunAttr::SourceAddress(ZeroXor(v)));
+		}
+		#[cfg(feature = "nat-discovery")]
+		if let Some(v) = self.changed_address {
+			out.push(StunAttr::ChangedAddress(ZeroXor(v)));
+		}
+		#[cfg(feature = "nat-discovery")]
+		if let Some(v) = self.reflected_from {
+			out.push(StunAttr::ReflectedFrom(ZeroXor(v)));
+		}
+		#[cfg(feature = "vendor")]
+		if let Some(v) = self.goog_network_info {
+			out.push(StunAttr::GoogNetworkInfo(v));
+		}
+		#[cfg(feature = "vendor")]
+		if let Some(v) = self.goog_last_ice_check_received {
+			out.push(StunAttr::GoogLastIceCheckReceived(v));
+		}
+		#[cfg(feature = "vendor")]
+		if let Some(v) = self.ms_version {
+			out.push(StunAttr::MsVersion(v));
+		}
+		#[cfg(feature = "vendor")]
+		if let Some(v) = &self.ms_sequence_number {
+			out.push(StunAttr::MsSequenceNumber(v.clone()));
+		}
+		#[cfg(feature = "vendor")]
+		if let Some(v) = self.ms_service_quality {
+			out.push(StunAttr::MsServiceQuality(v));
+		}
+		#[cfg(feature = "vendor")]
+		if let Some(v) = self.ms_implementation_version {
+			out.push(StunAttr::MsImplementationVersion(v));
+		}
+		// Bulky values go after the small ones
+		#[cfg(feature = "turn")]
+		if let Some(v) = self.data {
+			out.push(StunAttr::Data(Data::Slice(v)));
+		}
+		#[cfg(feature = "nat-discovery")]
+		if let Some(v) = self.padding {
+			out.push(StunAttr::Padding(Padding(v)));
+		}
+		// These cover everything before them, so they go last (in this order)
+		if let Some(v) = &self.integrity {
+			out.push(StunAttr::Integrity(v.clone()));
+		}
+		if let Some(v) = &self.integrity_sha256 {
+			out.push(StunAttr::IntegritySha256(v.clone()));
+		}
+		if let Some(()) = self.fingerprint {
+			out.push(StunAttr::Fingerprint);
+		}
 	}
 }
-impl<'i> FromIterator<StunAttr<'i>> for Flat<'i> {
-	fn from_iter<T: IntoIterator<Item = StunAttr<'i>>>(iter: T) -> Self {
-		let mut mapped = None;
-		let mut username = None;
-		let mut integrity = None;
-		let mut error = None;
-		let mut unknown_attributes = None;
-		let mut realm = None;
-		let mut nonce = None;
-		let mut xmapped = None;
-		let mut software = None;
-		let mut alternate_server = None;
-		let mut fingerprint = None;
-		let mut channel = None;
-		let mut lifetime = None;
-		let mut xpeer = None;
-		let mut data = None;
-		let mut xrelayed = None;
-		let mut even_port = None;
-		let mut requested_transport = None;
-		let mut dont_fragment = None;
-		let mut reservation_token = None;
-		let mut priority = None;
-		let mut use_candidate = None;
-		let mut ice_controlled = None;
-		let mut ice_controlling = None;
-
+impl<'i> Extend<StunAttr<'i>> for Flat<'i> {
+	fn extend<T: IntoIterator<Item = StunAttr<'i>>>(&mut self, iter: T) {
 		for a in iter {
 			match a {
 				// The .is_none is important because in STUN if attributes are duplicate, only the first attribute is returned
-				StunAttr::Mapped(v) if mapped.is_none() => { mapped = Some(v.into())}
-				StunAttr::Username(v) if username.is_none() => { username = Some(v) }
-				StunAttr::Integrity(v) if integrity.is_none() => {integrity = Some(v) }
-				StunAttr::Error(v) if error.is_none() => { error = Some(v) }
-				StunAttr::UnknownAttributes(v) if unknown_attributes.is_none() => { unknown_attributes = Some(v) }
-				StunAttr::Realm(v) if realm.is_none() => {realm = Some(v)}
-				StunAttr::Nonce(v) if nonce.is_none() => {nonce = Some(v)}
-				StunAttr::XMapped(v) if xmapped.is_none() => {xmapped = Some(v)}
-				StunAttr::Software(v) if software.is_none() => {software = Some(v)}
-				StunAttr::AlternateServer(v) if alternate_server.is_none() => {alternate_server = Some(v.into())}
-				StunAttr::Fingerprint if fingerprint.is_none() => {fingerprint = Some(())}
-				StunAttr::Channel(v) if channel.is_none() => {channel = Some(v.into())}
-				StunAttr::Lifetime(v) if lifetime.is_none() => {lifetime = Some(v)}
-				StunAttr::XPeer(v) if xpeer.is_none() => {xpeer = Some(v)}
-				StunAttr::Data(Data::Slice(v)) if data.is_none() => {data = Some(v)}
-				StunAttr::XRelayed(v) if xrelayed.is_none() => {xrelayed = Some(v)}
-				StunAttr::EvenPort(v) if even_port.is_none() => {even_port = Some(v.0)}
-				StunAttr::RequestedTransport(v) if requested_transport.is_none() => {requested_transport = Some(v.0)}
-				StunAttr::DontFragment if dont_fragment.is_none() => {dont_fragment = Some(())}
-				StunAttr::ReservationToken(v) if reservation_token.is_none() => {reservation_token = Some(v)}
-				StunAttr::Priority(v) if priority.is_none() => {priority = Some(v)}
-				StunAttr::UseCandidate if use_candidate.is_none() => {use_candidate = Some(())}
-				StunAttr::IceControlled(v) if ice_controlled.is_none() => {ice_controlled = Some(v)}
-				StunAttr::IceControlling(v) if ice_controlling.is_none() => {ice_controlling = Some(v)},
+				StunAttr::Mapped(v) if self.mapped.is_none() => { self.mapped = Some(v.into())}
+				StunAttr::Username(v) if self.username.is_none() => { self.username = Some(v) }
+				StunAttr::Integrity(v) if self.integrity.is_none() => {self.integrity = Some(v) }
+				StunAttr::Error(v) if self.error.is_none() => { self.error = Some(v) }
+				StunAttr::UnknownAttributes(v) if self.unknown_attributes.is_none() => { self.unknown_attributes = Some(v) }
+				StunAttr::Realm(v) if self.realm.is_none() => {self.realm = Some(v)}
+				StunAttr::Nonce(v) if self.nonce.is_none() => {self.nonce = Some(v)}
+				StunAttr::XMapped(v) if self.xmapped.is_none() => {self.xmapped = Some(v)}
+				StunAttr::Software(v) if self.software.is_none() => {self.software = Some(v)}
+				StunAttr::AlternateServer(v) if self.alternate_server.is_none() => {self.alternate_server = Some(v.into())}
+				StunAttr::Fingerprint if self.fingerprint.is_none() => {self.fingerprint = Some(())}
+				StunAttr::IntegritySha256(v) if self.integrity_sha256.is_none() => {self.integrity_sha256 = Some(v)}
+				StunAttr::AlternateDomain(v) if self.alternate_domain.is_none() => {self.alternate_domain = Some(v)}
+				StunAttr::PasswordAlgorithm(v) if self.password_algorithm.is_none() => {self.password_algorithm = Some(v)}
+				StunAttr::PasswordAlgorithms(v) if self.password_algorithms.is_none() => {self.password_algorithms = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::Channel(v) if self.channel.is_none() => {self.channel = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::Lifetime(v) if self.lifetime.is_none() => {self.lifetime = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::XPeer(v) if self.xpeer.is_none() => {self.xpeer = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::Data(Data::Slice(v)) if self.data.is_none() => {self.data = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::XRelayed(v) if self.xrelayed.is_none() => {self.xrelayed = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::RequestedFamily(v) if self.requested_family.is_none() => {self.requested_family = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::EvenPort(v) if self.even_port.is_none() => {self.even_port = Some(v.0)}
+				#[cfg(feature = "turn")]
+				StunAttr::RequestedTransport(v) if self.requested_transport.is_none() => {self.requested_transport = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::DontFragment if self.dont_fragment.is_none() => {self.dont_fragment = Some(())}
+				#[cfg(feature = "turn")]
+				StunAttr::ReservationToken(v) if self.reservation_token.is_none() => {self.reservation_token = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::AddressError(v) if self.address_error.is_none() => {self.address_error = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::Icmp(v) if self.icmp.is_none() => {self.icmp = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::ConnectionId(v) if self.connection_id.is_none() => {self.connection_id = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::AccessToken(v) if self.access_token.is_none() => {self.access_token = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::Bandwidth(v) if self.bandwidth.is_none() => {self.bandwidth = Some(v)}
+				#[cfg(feature = "turn")]
+				StunAttr::TimerVal(v) if self.timer_val.is_none() => {self.timer_val = Some(v)}
+				#[cfg(feature = "ice")]
+				StunAttr::Priority(v) if self.priority.is_none() => {self.priority = Some(v)}
+				#[cfg(feature = "ice")]
+				StunAttr::UseCandidate if self.use_candidate.is_none() => {self.use_candidate = Some(())}
+				#[cfg(feature = "ice")]
+				StunAttr::IceControlled(v) if self.ice_controlled.is_none() => {self.ice_controlled = Some(v)}
+				#[cfg(feature = "ice")]
+				StunAttr::IceControlling(v) if self.ice_controlling.is_none() => {self.ice_controlling = Some(v)},
+				#[cfg(feature = "nat-discovery")]
+				StunAttr::ChangeRequest(v) if self.change_request.is_none() => {self.change_request = Some(v)}
+				#[cfg(feature = "nat-discovery")]
+				StunAttr::ResponseOrigin(v) if self.response_origin.is_none() => {self.response_origin = Some(v.into())}
+				#[cfg(feature = "nat-discovery")]
+				StunAttr::OtherAddress(v) if self.other_address.is_none() => {self.other_address = Some(v.into())}
+				#[cfg(feature = "nat-discovery")]
+				StunAttr::Padding(v) if self.padding.is_none() => {self.padding = Some(v.0)}
+				#[cfg(feature = "nat-discovery")]
+				StunAttr::ResponsePort(v) if self.response_port.is_none() => {self.response_port = Some(v.0)}
+				#[cfg(feature = "nat-discovery")]
+				StunAttr::TransmitCounter(v) if self.transmit_counter.is_none() => {self.transmit_counter = Some(v)}
+				#[cfg(feature = "nat-discovery")]
+				StunAttr::ResponseAddress(v) if self.response_address.is_none() => {self.response_address = Some(v.into())}
+				#[cfg(feature = "nat-discovery")]
+				StunAttr::SourceAddress(v) if self.source_address.is_none() => {self.source_address = Some(v.into())}
+				#[cfg(feature = "nat-discovery")]
+				StunAttr::ChangedAddress(v) if self.changed_address.is_none() => {self.changed_address = Some(v.into())}
+				#[cfg(feature = "nat-discovery")]
+				StunAttr::ReflectedFrom(v) if self.reflected_from.is_none() => {self.reflected_from = Some(v.into())}
+				#[cfg(feature = "vendor")]
+				StunAttr::GoogNetworkInfo(v) if self.goog_network_info.is_none() => {self.goog_network_info = Some(v)}
+				#[cfg(feature = "vendor")]
+				StunAttr::GoogLastIceCheckReceived(v) if self.goog_last_ice_check_received.is_none() => {self.goog_last_ice_check_received = Some(v)}
+				#[cfg(feature = "vendor")]
+				StunAttr::MsVersion(v) if self.ms_version.is_none() => {self.ms_version = Some(v)}
+				#[cfg(feature = "vendor")]
+				StunAttr::MsSequenceNumber(v) if self.ms_sequence_number.is_none() => {self.ms_sequence_number = Some(v)}
+				#[cfg(feature = "vendor")]
+				StunAttr::MsServiceQuality(v) if self.ms_service_quality.is_none() => {self.ms_service_quality = Some(v)}
+				#[cfg(feature = "vendor")]
+				StunAttr::MsImplementationVersion(v) if self.ms_implementation_version.is_none() => {self.ms_implementation_version = Some(v)}
 				_ => {}
 			}
 		}
-
-		Self {
-			mapped,
-			username,
-			integrity,
-			error,
-			unknown_attributes,
-			realm,
-			nonce,
-			xmapped,
-			software,
-			alternate_server,
-			fingerprint,
-			channel,
-			lifetime,
-			xpeer,
-			data,
-			xrelayed,
-			even_port,
-			requested_transport,
-			dont_fragment,
-			reservation_token,
-			priority,
-			use_candidate,
-			ice_controlled,
-			ice_controlling,
-		}
+	}
+}
+impl<'i> FromIterator<StunAttr<'i>> for Flat<'i> {
+	fn from_iter<T: IntoIterator<Item = StunAttr<'i>>>(iter: T) -> Self {
+		let mut ret = Self::default();
+		ret.extend(iter);
+		ret
 	}
 }
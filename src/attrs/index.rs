@@ -0,0 +1,161 @@
+use std::ops::Range;
+
+use crate::attr::{StunAttr, StunAttrDecodeErr};
+use crate::attrs::flat::Flat;
+use crate::attrs::{parse_at, StunAttrs};
+use crate::{SizeLimit, Stun, StunDecodeErr};
+
+pub const INDEX_CAPACITY: usize = 32;
+// Twice the capacity, so that the table never fills and probes stay short
+const SLOTS: usize = 2 * INDEX_CAPACITY;
+
+// Where a parsed message's attributes are, recorded while decoding so that later lookups go
+// straight to the attribute instead of walking (and re-decoding) everything before it. Only the
+// first attribute of each type is kept, in a small hash table keyed on the type.
+#[derive(Debug, Clone)]
+pub struct AttrIndex {
+	slots: [Option<Entry>; SLOTS],
+	len: usize,
+	// False if the message had more attribute types than fit in the index (or wasn't a parsed message)
+	complete: bool,
+}
+// Offsets into the attributes: where the attribute starts and where its value ends
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+	typ: u16,
+	start: u16,
+	end: u16,
+}
+impl AttrIndex {
+	fn new(complete: bool) -> Self {
+		Self {
+			slots: [None; SLOTS],
+			len: 0,
+			complete,
+		}
+	}
+	// Walks the attributes once: checks that every one of them decodes (like Stun::decode does),
+	// and indexes and flattens the ones that iterating the message would return
+	fn build<'i>(
+		buff: &'i [u8],
+		header: &'i [u8; 20],
+	) -> Result<(Self, Flat<'i>), StunAttrDecodeErr> {
+		let mut index = Self::new(true);
+		let mut flat = Flat::default();
+		let mut visible = true;
+		let mut integrity = false;
+		let mut integrity_sha256 = false;
+		let mut offset = 0;
+		while let Some((attr, next)) = parse_at(buff, header, offset) {
+			let attr = attr?;
+			// Same rules as StunIter: nothing after FINGERPRINT, only FINGERPRINT after
+			// MESSAGE-INTEGRITY-SHA256, and only those two after MESSAGE-INTEGRITY
+			if visible {
+				match attr {
+					StunAttr::Fingerprint => {}
+					_ if integrity_sha256 => visible = false,
+					StunAttr::IntegritySha256(_) => integrity_sha256 = true,
+					_ if integrity => visible = false,
+					StunAttr::Integrity(_) => integrity = true,
+					_ => {}
+				}
+			}
+			if visible {
+				// parse_at succeeded, so the attribute's header is all there
+				let length = u16::from_be_bytes([buff[offset + 2], buff[offset + 3]]);
+				index.insert(attr.typ(), offset, offset + 4 + length as usize);
+				visible = !matches!(attr, StunAttr::Fingerprint);
+				flat.extend([attr]);
+			}
+			offset = next;
+		}
+		Ok((index, flat))
+	}
+	// The slot holding typ, or the empty one where it would go
+	fn slot(&self, typ: u16) -> usize {
+		let mut i = typ as usize % SLOTS;
+		while let Some(entry) = &self.slots[i] {
+			if entry.typ == typ {
+				break;
+			}
+			i = (i + 1) % SLOTS;
+		}
+		i
+	}
+	fn insert(&mut self, typ: u16, start: usize, end: usize) {
+		let i = self.slot(typ);
+		if self.slots[i].is_some() {
+			return;
+		}
+		if self.len == INDEX_CAPACITY {
+			self.complete = false;
+			return;
+		}
+		// The attributes of a decoded message fit in its 16bit length
+		self.slots[i] = Some(Entry {
+			typ,
+			start: start as u16,
+			end: end as u16,
+		});
+		self.len += 1;
+	}
+	fn entry(&self, typ: u16) -> Option<Entry> {
+		self.slots[self.slot(typ)]
+	}
+	// Where the first attribute of the given type starts
+	pub fn offset(&self, typ: u16) -> Option<usize> {
+		self.entry(typ).map(|e| e.start as usize)
+	}
+	// Where the value of the first attribute of the given type is
+	pub fn value(&self, typ: u16) -> Option<Range<usize>> {
+		self.entry(typ)
+			.map(|e| e.start as usize + 4..e.end as usize)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Indexed<'i> {
+	pub msg: Stun<'i>,
+	index: AttrIndex,
+	flat: Flat<'i>,
+}
+impl<'i> Indexed<'i> {
+	// For a message that's already been decoded. Lists aren't indexed, lookups search them instead.
+	pub fn new(msg: Stun<'i>) -> Self {
+		let built = match msg.attrs {
+			StunAttrs::Parse { buff, header } => AttrIndex::build(buff, header).ok(),
+			StunAttrs::List(_) => None,
+		};
+		let (index, flat) = built.unwrap_or_else(|| (AttrIndex::new(false), msg.flat()));
+		Self { msg, index, flat }
+	}
+	// Decodes the message, validating, indexing and flattening its attributes in a single pass
+	pub fn decode(buff: &'i [u8]) -> Result<Self, StunDecodeErr> {
+		let msg = Stun::decode_header(buff, SizeLimit::default(), false)?;
+		let StunAttrs::Parse { buff, header } = msg.attrs else {
+			return Ok(Self::new(msg));
+		};
+		let (index, flat) = AttrIndex::build(buff, header).map_err(StunDecodeErr::AttrErr)?;
+		Ok(Self { msg, index, flat })
+	}
+	pub fn index(&self) -> &AttrIndex {
+		&self.index
+	}
+	// Returns the first attribute of the given type
+	pub fn get(&self, typ: u16) -> Option<StunAttr<'i>> {
+		let (true, StunAttrs::Parse { buff, header }) = (self.index.complete, &self.msg.attrs) else {
+			return (&self.msg).into_iter().find(|a| a.typ() == typ);
+		};
+		// The message was validated by decode, so the attribute decodes
+		parse_at(buff, header, self.index.offset(typ)?)?.0.ok()
+	}
+	pub fn contains(&self, typ: u16) -> bool {
+		match self.index.complete {
+			true => self.index.offset(typ).is_some(),
+			false => self.get(typ).is_some(),
+		}
+	}
+	pub fn flat(&self) -> &Flat<'i> {
+		&self.flat
+	}
+}
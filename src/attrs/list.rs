@@ -0,0 +1,85 @@
+// A growable attribute list backed by fixed storage: a caller's &mut [StunAttr] or an array. Lets a
+// server that doesn't allocate pick its response's attributes at runtime.
+use std::marker::PhantomData;
+
+use crate::attr::StunAttr;
+use crate::attrs::StunAttrs;
+
+#[derive(Debug, Clone)]
+pub struct StunAttrsMut<'i, S> {
+	storage: S,
+	len: usize,
+	_attrs: PhantomData<StunAttr<'i>>,
+}
+impl<'i, const N: usize> Default for StunAttrsMut<'i, [StunAttr<'i>; N]> {
+	fn default() -> Self {
+		// The placeholders are never visible: only the first len slots are
+		Self::new(std::array::from_fn(|_| StunAttr::Fingerprint))
+	}
+}
+impl<'i, S: AsRef<[StunAttr<'i>]> + AsMut<[StunAttr<'i>]>> StunAttrsMut<'i, S> {
+	// Anything already in storage is overwritten
+	pub fn new(storage: S) -> Self {
+		Self {
+			storage,
+			len: 0,
+			_attrs: PhantomData,
+		}
+	}
+	pub fn capacity(&self) -> usize {
+		self.storage.as_ref().len()
+	}
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	// Hands the attribute back if the storage is full
+	pub fn push(&mut self, attr: StunAttr<'i>) -> Result<(), StunAttr<'i>> {
+		match self.storage.as_mut().get_mut(self.len) {
+			Some(slot) => {
+				*slot = attr;
+				self.len += 1;
+				Ok(())
+			}
+			None => Err(attr),
+		}
+	}
+	pub fn pop(&mut self) -> Option<StunAttr<'i>> {
+		self.len = self.len.checked_sub(1)?;
+		Some(self.storage.as_ref()[self.len].clone())
+	}
+	pub fn clear(&mut self) {
+		self.len = 0;
+	}
+	pub fn as_slice(&self) -> &[StunAttr<'i>] {
+		&self.storage.as_ref()[..self.len]
+	}
+	pub fn attrs(&self) -> StunAttrs<'_> {
+		StunAttrs::List(self.as_slice())
+	}
+	// Stops at the first attribute that doesn't fit and hands it back; the ones before it are kept
+	pub fn try_extend<T: IntoIterator<Item = StunAttr<'i>>>(
+		&mut self,
+		iter: T,
+	) -> Result<(), StunAttr<'i>> {
+		for attr in iter {
+			self.push(attr)?;
+		}
+		Ok(())
+	}
+}
+impl<'i, S: AsRef<[StunAttr<'i>]> + AsMut<[StunAttr<'i>]>> Extend<StunAttr<'i>>
+	for StunAttrsMut<'i, S>
+{
+	// Panics if the attributes don't fit, use try_extend to find out instead
+	fn extend<T: IntoIterator<Item = StunAttr<'i>>>(&mut self, iter: T) {
+		if self.try_extend(iter).is_err() {
+			panic!(
+				"attributes exceed the list's capacity of {}",
+				self.capacity()
+			);
+		}
+	}
+}
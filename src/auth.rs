@@ -0,0 +1,332 @@
+use std::convert::Infallible;
+use std::fmt;
+
+use md5::digest::Output;
+use md5::{Digest, Md5};
+use sha2::Sha256;
+
+use crate::attr::{typ, Integrity, PasswordAlgorithm, PasswordAlgorithms, StunAttr};
+use crate::{SizeLimit, Stun, StunDecodeErr};
+
+#[cfg(feature = "oauth")]
+pub mod oauth;
+#[cfg(feature = "precis")]
+pub mod precis;
+mod rest;
+
+pub use rest::{rest_credentials, rest_key, rest_password, RestErr};
+
+// Key material for MESSAGE-INTEGRITY. Only the derived key types implement this, so a raw password
+// can't be passed where a long-term key is expected (or the other way around). Keys are owned and
+// Send + Sync so that credential stores can be shared between server worker threads.
+pub trait IntegrityKey: Send + Sync {
+	fn as_bytes(&self) -> &[u8];
+}
+
+// Short-term credentials (e.g. ICE) use the password itself as the key
+#[derive(Clone, PartialEq, Eq)]
+pub struct ShortTermKey(Vec<u8>);
+impl ShortTermKey {
+	pub fn new(password: &str) -> Self {
+		Self(password.as_bytes().to_vec())
+	}
+	// Prepares the password with the OpaqueString profile first
+	#[cfg(feature = "precis")]
+	pub fn new_prepared(password: &str) -> Result<Self, precis::PrecisErr> {
+		Ok(Self::new(&precis::opaque_string(password)?))
+	}
+}
+impl IntegrityKey for ShortTermKey {
+	fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+impl fmt::Debug for ShortTermKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("ShortTermKey(..)")
+	}
+}
+
+// Long-term credentials use MD5(username ":" realm ":" password), or SHA-256 of the same when the
+// server offers it in PASSWORD-ALGORITHMS (RFC 8489 section 9.2.2)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LongTermKey(LongTermDigest);
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LongTermDigest {
+	Md5([u8; 16]),
+	Sha256([u8; 32]),
+}
+fn derive<D: Digest>(username: &str, realm: &str, password: &str) -> Output<D> {
+	let mut hasher = D::new();
+	hasher.update(username);
+	hasher.update(":");
+	hasher.update(realm);
+	hasher.update(":");
+	hasher.update(password);
+	hasher.finalize()
+}
+impl LongTermKey {
+	pub fn new(username: &str, realm: &str, password: &str) -> Self {
+		Self(LongTermDigest::Md5(derive::<Md5>(username, realm, password).into()))
+	}
+	pub fn new_sha256(username: &str, realm: &str, password: &str) -> Self {
+		Self(LongTermDigest::Sha256(derive::<Sha256>(username, realm, password).into()))
+	}
+	// Derives the key with the given algorithm. None for unknown algorithms, or parameters that
+	// the algorithm doesn't take (neither MD5 nor SHA-256 take any).
+	pub fn with_algorithm(
+		algorithm: PasswordAlgorithm<'_>,
+		username: &str,
+		realm: &str,
+		password: &str,
+	) -> Option<Self> {
+		if !algorithm.params.is_empty() {
+			return None;
+		}
+		match algorithm.algorithm {
+			typ::PASSWORD_ALGORITHM_MD5 => Some(Self::new(username, realm, password)),
+			typ::PASSWORD_ALGORITHM_SHA256 => Some(Self::new_sha256(username, realm, password)),
+			_ => None,
+		}
+	}
+	// Picks the algorithm to use from a server's PASSWORD-ALGORITHMS: SHA-256 if offered, then
+	// MD5. The chosen algorithm must be echoed back in PASSWORD-ALGORITHM along with the
+	// PASSWORD-ALGORITHMS attribute itself.
+	pub fn select<'i>(offered: &PasswordAlgorithms<'i>) -> Option<PasswordAlgorithm<'i>> {
+		let supported = |want| {
+			offered
+				.iter()
+				.find(|a| a.algorithm == want && a.params.is_empty())
+		};
+		supported(typ::PASSWORD_ALGORITHM_SHA256).or_else(|| supported(typ::PASSWORD_ALGORITHM_MD5))
+	}
+	// Prepares the username with the UsernameCasePreserved profile, and the realm and password with
+	// the OpaqueString profile, before deriving the key
+	#[cfg(feature = "precis")]
+	pub fn new_prepared(
+		username: &str,
+		realm: &str,
+		password: &str,
+	) -> Result<Self, precis::PrecisErr> {
+		Ok(Self::new(
+			&precis::username_case_preserved(username)?,
+			&precis::opaque_string(realm)?,
+			&precis::opaque_string(password)?,
+		))
+	}
+	// For servers that store the already-derived key instead of the password
+	pub fn from_digest(digest: [u8; 16]) -> Self {
+		Self(LongTermDigest::Md5(digest))
+	}
+	pub fn from_digest_sha256(digest: [u8; 32]) -> Self {
+		Self(LongTermDigest::Sha256(digest))
+	}
+	pub fn algorithm(&self) -> PasswordAlgorithm<'static> {
+		match self.0 {
+			LongTermDigest::Md5(_) => PasswordAlgorithm::MD5,
+			LongTermDigest::Sha256(_) => PasswordAlgorithm::SHA256,
+		}
+	}
+}
+impl IntegrityKey for LongTermKey {
+	fn as_bytes(&self) -> &[u8] {
+		match &self.0 {
+			LongTermDigest::Md5(d) => d,
+			LongTermDigest::Sha256(d) => d,
+		}
+	}
+}
+impl fmt::Debug for LongTermKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("LongTermKey(..)")
+	}
+}
+
+// Where a server's keys come from. Lookups return owned keys, so a provider can build them on the
+// fly (a database query, a REST API secret, ...) and be shared between worker threads. Ok(None)
+// means there's no such user; Err is for when the provider couldn't tell, which a server should
+// answer with 500 rather than a challenge.
+pub trait CredentialProvider: Send + Sync {
+	type Key: IntegrityKey;
+	type Err;
+	fn key(&self, username: &str, realm: Option<&str>) -> Result<Option<Self::Key>, Self::Err>;
+}
+impl<K, E, F> CredentialProvider for F
+where
+	K: IntegrityKey,
+	F: Fn(&str, Option<&str>) -> Result<Option<K>, E> + Send + Sync,
+{
+	type Key = K;
+	type Err = E;
+	fn key(&self, username: &str, realm: Option<&str>) -> Result<Option<K>, E> {
+		self(username, realm)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub enum StunAuthErr<E = Infallible> {
+	Decode(StunDecodeErr),
+	MissingIntegrity,
+	MissingUsername,
+	UnknownUser,
+	BadIntegrity,
+	// The CredentialProvider failed
+	Provider(E),
+}
+impl<E> From<StunDecodeErr> for StunAuthErr<E> {
+	fn from(value: StunDecodeErr) -> Self {
+		Self::Decode(value)
+	}
+}
+
+// A message whose MESSAGE-INTEGRITY has been verified, along with the credentials that verified it
+#[derive(Debug, Clone)]
+pub struct AuthenticatedStun<'i, K> {
+	pub msg: Stun<'i>,
+	pub username: &'i str,
+	pub realm: Option<&'i str>,
+	pub key: K,
+}
+
+impl<'i> Stun<'i> {
+	// Decodes the message and verifies its integrity in a single pass over the attributes. When the
+	// first MESSAGE-INTEGRITY(-SHA256) attribute is reached, lookup is called with the USERNAME and
+	// REALM seen so far. If the message has both, both are checked.
+	pub fn decode_authenticated<K, F>(
+		buff: &'i [u8],
+		lookup: F,
+	) -> Result<AuthenticatedStun<'i, K>, StunAuthErr>
+	where
+		K: IntegrityKey,
+		F: FnOnce(&str, Option<&str>) -> Option<K>,
+	{
+		authenticate(buff, |username, realm| Ok(lookup(username, realm)))
+	}
+	// decode_authenticated with the key coming from a CredentialProvider
+	pub fn decode_with_provider<P: CredentialProvider + ?Sized>(
+		buff: &'i [u8],
+		provider: &P,
+	) -> Result<AuthenticatedStun<'i, P::Key>, StunAuthErr<P::Err>> {
+		authenticate(buff, |username, realm| provider.key(username, realm))
+	}
+	// Decodes the message, checking FINGERPRINT (if present) and verifying the integrity against a
+	// key that's known up front, e.g. an ICE agent's short-term password. Parse errors (including a
+	// bad FINGERPRINT) come back as StunAuthErr::Decode.
+	pub fn decode_and_verify<K: IntegrityKey + ?Sized>(
+		buff: &'i [u8],
+		key: &K,
+	) -> Result<Self, StunAuthErr> {
+		let msg = Self::decode(buff)?;
+		msg.verify(key)?;
+		Ok(msg)
+	}
+	// Verifies an already decoded message against a known key. Like iterating, this ignores
+	// anything after the integrity attributes.
+	pub fn verify<K: IntegrityKey + ?Sized>(&self, key: &K) -> Result<(), StunAuthErr> {
+		let mut verified = false;
+		for attr in self {
+			let ok = match attr {
+				StunAttr::IntegritySha256(integrity) => integrity.verify(key),
+				StunAttr::Integrity(integrity) => integrity.verify(key),
+				_ => continue,
+			};
+			if !ok {
+				return Err(StunAuthErr::BadIntegrity);
+			}
+			verified = true;
+		}
+		match verified {
+			true => Ok(()),
+			false => Err(StunAuthErr::MissingIntegrity),
+		}
+	}
+	// Puts the integrity attributes where they belong: any existing MESSAGE-INTEGRITY(-SHA256) and
+	// FINGERPRINT are removed, then a MESSAGE-INTEGRITY for key and a FINGERPRINT are appended.
+	// USERNAME (and REALM / NONCE for long-term credentials) are up to the caller.
+	pub fn sign<K: IntegrityKey>(attrs: &mut Vec<StunAttr<'i>>, key: &'i K) {
+		attrs.retain(|a| {
+			!matches!(
+				a,
+				StunAttr::Integrity(_) | StunAttr::IntegritySha256(_) | StunAttr::Fingerprint
+			)
+		});
+		attrs.push(StunAttr::Integrity(Integrity::sign(key)));
+		attrs.push(StunAttr::Fingerprint);
+	}
+}
+
+fn authenticate<'i, K, E, F>(
+	buff: &'i [u8],
+	lookup: F,
+) -> Result<AuthenticatedStun<'i, K>, StunAuthErr<E>>
+where
+	K: IntegrityKey,
+	F: FnOnce(&str, Option<&str>) -> Result<Option<K>, E>,
+{
+	let msg = Stun::decode_header(buff, SizeLimit::default(), false)?;
+	let mut username = None;
+	let mut realm = None;
+	let mut lookup = Some(lookup);
+	let mut verified = None;
+	let mut sha256 = false;
+	for attr in &msg.attrs {
+		match attr.map_err(StunDecodeErr::AttrErr)? {
+			// Only FINGERPRINT may follow MESSAGE-INTEGRITY-SHA256
+			_ if sha256 => {}
+			StunAttr::IntegritySha256(integrity) => {
+				sha256 = true;
+				let key = integrity_key(&mut verified, &mut lookup, username, realm)?;
+				if !integrity.verify(key) {
+					return Err(StunAuthErr::BadIntegrity);
+				}
+			}
+			StunAttr::Integrity(integrity) if verified.is_none() => {
+				let key = integrity_key(&mut verified, &mut lookup, username, realm)?;
+				if !integrity.verify(key) {
+					return Err(StunAuthErr::BadIntegrity);
+				}
+			}
+			// Attributes after MESSAGE-INTEGRITY aren't covered by it
+			_ if verified.is_some() => {}
+			StunAttr::Username(u) if username.is_none() => username = Some(u),
+			StunAttr::Realm(r) if realm.is_none() => realm = Some(r),
+			_ => {}
+		}
+	}
+	let (username, key) = verified.ok_or(StunAuthErr::MissingIntegrity)?;
+	Ok(AuthenticatedStun {
+		msg,
+		username,
+		realm,
+		key,
+	})
+}
+
+// The first integrity attribute looks up the key, a MESSAGE-INTEGRITY-SHA256 following a
+// MESSAGE-INTEGRITY reuses it
+fn integrity_key<'v, 'i, K, E, F>(
+	verified: &'v mut Option<(&'i str, K)>,
+	lookup: &mut Option<F>,
+	username: Option<&'i str>,
+	realm: Option<&str>,
+) -> Result<&'v K, StunAuthErr<E>>
+where
+	F: FnOnce(&str, Option<&str>) -> Result<Option<K>, E>,
+{
+	if verified.is_none() {
+		let lookup = lookup.take().ok_or(StunAuthErr::MissingIntegrity)?;
+		let username = username.ok_or(StunAuthErr::MissingUsername)?;
+		let key = lookup(username, realm)
+			.map_err(StunAuthErr::Provider)?
+			.ok_or(StunAuthErr::UnknownUser)?;
+		*verified = Some((username, key));
+	}
+	verified.as_ref().map(|(_, key)| key).ok_or(StunAuthErr::MissingIntegrity)
+}
+
+const _: () = {
+	const fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+	assert_send_sync::<ShortTermKey>();
+	assert_send_sync::<LongTermKey>();
+	assert_send_sync::<dyn IntegrityKey>();
+};
@@ -0,0 +1,113 @@
+// RFC 7635 third-party authorization. The authorization server and the STUN server share a key
+// (the AS-RS key) that encrypts the ACCESS-TOKEN's block with AES-GCM. Opening a token yields the
+// mac_key, which is the key for the request's MESSAGE-INTEGRITY(-SHA256). The USERNAME of such a
+// request is the kid, which servers can use to pick the AS-RS key.
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN};
+
+use super::IntegrityKey;
+use crate::attr::{AccessToken, AccessTokenBlock};
+use crate::StunEncodeErr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenAlgorithm {
+	Aes128Gcm,
+	Aes256Gcm,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenErr {
+	// The AS-RS key has the wrong length for the algorithm
+	BadKey,
+	// The nonce isn't 12 bytes, or the decrypted block doesn't parse
+	Malformed,
+	// Authentication failed: the wrong key or server name, or a tampered token
+	Decrypt,
+	Expired,
+}
+
+// The key shared between the authorization server and this server
+pub struct AsRsKey(LessSafeKey);
+impl AsRsKey {
+	pub fn new(algorithm: TokenAlgorithm, key: &[u8]) -> Result<Self, TokenErr> {
+		let algorithm = match algorithm {
+			TokenAlgorithm::Aes128Gcm => &aead::AES_128_GCM,
+			TokenAlgorithm::Aes256Gcm => &aead::AES_256_GCM,
+		};
+		let key = UnboundKey::new(algorithm, key).map_err(|_| TokenErr::BadKey)?;
+		Ok(Self(LessSafeKey::new(key)))
+	}
+	// Decrypts the token and checks that it hasn't expired. server_name is the associated data, so
+	// a token issued for a different server fails to decrypt.
+	pub fn open(
+		&self,
+		token: &AccessToken<'_>,
+		server_name: &str,
+		now: SystemTime,
+	) -> Result<MacKey, TokenErr> {
+		let nonce =
+			Nonce::try_assume_unique_for_key(token.nonce).map_err(|_| TokenErr::Malformed)?;
+		let mut block = token.encrypted.to_vec();
+		let plain = self
+			.0
+			.open_in_place(nonce, Aad::from(server_name.as_bytes()), &mut block)
+			.map_err(|_| TokenErr::Decrypt)?;
+		let block = AccessTokenBlock::decode(plain).map_err(|_| TokenErr::Malformed)?;
+
+		// The timestamp is 48.16 fixed point seconds
+		let issued = UNIX_EPOCH + Duration::from_secs(block.timestamp >> 16);
+		let expires = issued + Duration::from_secs(block.lifetime.into());
+		if now > expires {
+			return Err(TokenErr::Expired);
+		}
+		Ok(MacKey(block.mac_key.to_vec()))
+	}
+	// The authorization server's side: encrypts block into out as an ACCESS-TOKEN's encrypted
+	// portion. The nonce must never be reused with the same key. Returns the length written.
+	pub fn seal(
+		&self,
+		block: &AccessTokenBlock<'_>,
+		nonce: [u8; NONCE_LEN],
+		server_name: &str,
+		out: &mut [u8],
+	) -> Result<usize, StunEncodeErr> {
+		let tag_len = self.0.algorithm().tag_len();
+		let len = block.len() + tag_len;
+		if out.len() < len {
+			return Err(StunEncodeErr::BufferTooSmall(len));
+		}
+		let (plain, tag) = out[..len].split_at_mut(block.len());
+		block.encode(plain)?;
+		let sealed = self
+			.0
+			.seal_in_place_separate_tag(
+				Nonce::assume_unique_for_key(nonce),
+				Aad::from(server_name.as_bytes()),
+				plain,
+			)
+			.map_err(|_| StunEncodeErr::TooLarge)?;
+		tag.copy_from_slice(sealed.as_ref());
+		Ok(len)
+	}
+}
+impl fmt::Debug for AsRsKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("AsRsKey(..)")
+	}
+}
+
+// The session key from inside an ACCESS-TOKEN
+#[derive(Clone, PartialEq, Eq)]
+pub struct MacKey(Vec<u8>);
+impl IntegrityKey for MacKey {
+	fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+impl fmt::Debug for MacKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("MacKey(..)")
+	}
+}
@@ -0,0 +1,64 @@
+// RFC 8265 string preparation, as RFC 8489 section 9.2.2 requires for long-term credentials:
+// usernames use the UsernameCasePreserved profile, passwords (and realms) the OpaqueString profile.
+// Unicode normalization (NFC) needs tables this crate doesn't carry, so non-ASCII input has to be in
+// NFC already, which is what nearly every input method produces.
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrecisErr {
+	Empty,
+	Disallowed(char),
+}
+
+// Space separators other than U+0020
+fn is_non_ascii_space(c: char) -> bool {
+	matches!(
+		c,
+		'\u{00A0}' | '\u{1680}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}'
+	)
+}
+
+// Fullwidth forms of the ASCII characters map to the ASCII characters themselves
+fn narrow(c: char) -> char {
+	match c {
+		'\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFF01 + 0x21).unwrap_or(c),
+		c => c,
+	}
+}
+
+// OpaqueString (section 4.2): non-ASCII spaces become U+0020, control characters are rejected
+pub fn opaque_string(s: &str) -> Result<Cow<'_, str>, PrecisErr> {
+	if let Some(c) = s.chars().find(|c| c.is_control()) {
+		return Err(PrecisErr::Disallowed(c));
+	}
+	if s.is_empty() {
+		return Err(PrecisErr::Empty);
+	}
+	if !s.chars().any(is_non_ascii_space) {
+		return Ok(Cow::Borrowed(s));
+	}
+	Ok(Cow::Owned(
+		s.chars()
+			.map(|c| if is_non_ascii_space(c) { ' ' } else { c })
+			.collect(),
+	))
+}
+
+// UsernameCasePreserved (section 3.4): fullwidth characters are narrowed, and spaces and control
+// characters are rejected
+pub fn username_case_preserved(s: &str) -> Result<Cow<'_, str>, PrecisErr> {
+	let ret = match s.chars().any(|c| narrow(c) != c) {
+		false => Cow::Borrowed(s),
+		true => Cow::Owned(s.chars().map(narrow).collect()),
+	};
+	if let Some(c) = ret
+		.chars()
+		.find(|&c| c.is_control() || c == ' ' || is_non_ascii_space(c))
+	{
+		return Err(PrecisErr::Disallowed(c));
+	}
+	if ret.is_empty() {
+		return Err(PrecisErr::Empty);
+	}
+	Ok(ret)
+}
@@ -0,0 +1,62 @@
+// The TURN REST API's ephemeral credentials (draft-uberti-behave-turn-rest, as implemented by
+// coturn's use-auth-secret). The username is "<expiry>:<user>", with the expiry in unix seconds,
+// and the password is base64(HMAC-SHA1(secret, username)). The web service and the TURN server
+// share the secret, so the server can recompute any password without storing it.
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+use super::LongTermKey;
+use crate::base64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestErr {
+	// The username doesn't start with an expiry timestamp
+	Malformed,
+	Expired,
+}
+
+// Issues credentials for user that are good for ttl from now
+pub fn rest_credentials(
+	secret: &str,
+	ttl: Duration,
+	user: &str,
+	now: SystemTime,
+) -> (String, String) {
+	let expiry = (now + ttl)
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_secs();
+	let username = if user.is_empty() {
+		expiry.to_string()
+	} else {
+		format!("{expiry}:{user}")
+	};
+	let password = rest_password(secret, &username);
+	(username, password)
+}
+
+pub fn rest_password(secret: &str, username: &str) -> String {
+	let mut hmac =
+		Hmac::<Sha1>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+	hmac.update(username.as_bytes());
+	base64(&hmac.finalize().into_bytes())
+}
+
+// The server's side: checks that the username hasn't expired and derives the long-term key from
+// the password that the secret implies. Usable as the key lookup for Stun::decode_authenticated.
+pub fn rest_key(
+	secret: &str,
+	username: &str,
+	realm: &str,
+	now: SystemTime,
+) -> Result<LongTermKey, RestErr> {
+	let expiry = username.split(':').next().unwrap_or_default();
+	let expiry: u64 = expiry.parse().map_err(|_| RestErr::Malformed)?;
+	if now > UNIX_EPOCH + Duration::from_secs(expiry) {
+		return Err(RestErr::Expired);
+	}
+	let password = rest_password(secret, username);
+	Ok(LongTermKey::new(username, realm, &password))
+}
@@ -0,0 +1,93 @@
+use std::net::SocketAddr;
+use std::ops::Range;
+
+use crate::{Stun, StunEncodeErr};
+
+// Linux's UDP_MAX_SEGMENTS
+pub const MAX_SEGMENTS: usize = 64;
+// The largest UDP payload (over IPv4), which a whole GSO send has to fit in
+pub const MAX_PAYLOAD: usize = 65507;
+
+#[derive(Debug, Clone)]
+pub enum BatchErr {
+	// The batch should be sent and cleared before pushing more messages
+	Full,
+	// GSO needs every segment except the last to be the same size
+	SegmentMismatch,
+	Encode(StunEncodeErr),
+}
+
+// Encodes messages back to back into one buffer so they can be handed to the kernel in a single
+// UDP_SEGMENT (GSO) send to destination(), using segment_size() as the segment size.
+#[derive(Debug)]
+pub struct Batch<'b> {
+	buff: &'b mut [u8],
+	to: Option<SocketAddr>,
+	len: usize,
+	segment: Option<usize>,
+	offsets: Vec<Range<usize>>,
+	closed: bool,
+}
+impl<'b> Batch<'b> {
+	pub fn new(buff: &'b mut [u8]) -> Self {
+		Self {
+			buff,
+			to: None,
+			len: 0,
+			segment: None,
+			offsets: Vec::new(),
+			closed: false,
+		}
+	}
+	// Every message in a batch goes to the same address, a message for anywhere else is Full
+	pub fn push(&mut self, to: SocketAddr, msg: &Stun<'_>) -> Result<(), BatchErr> {
+		if self.closed || self.offsets.len() >= MAX_SEGMENTS || self.to.is_some_and(|t| t != to) {
+			return Err(BatchErr::Full);
+		}
+		let end = self.buff.len().min(MAX_PAYLOAD);
+		let Some(unused) = self.buff.get_mut(self.len..end) else {
+			return Err(BatchErr::Full);
+		};
+		let len = match msg.encode(unused) {
+			Err(StunEncodeErr::BufferTooSmall(_)) => return Err(BatchErr::Full),
+			Err(e) => return Err(BatchErr::Encode(e)),
+			Ok(len) => len,
+		};
+		match self.segment {
+			None => self.segment = Some(len),
+			Some(segment) if len > segment => return Err(BatchErr::SegmentMismatch),
+			// A shorter message can only be the last segment
+			Some(segment) if len < segment => self.closed = true,
+			Some(_) => {}
+		}
+		self.to = Some(to);
+		self.offsets.push(self.len..self.len + len);
+		self.len += len;
+		Ok(())
+	}
+	pub fn destination(&self) -> Option<SocketAddr> {
+		self.to
+	}
+	pub fn segment_size(&self) -> Option<usize> {
+		self.segment
+	}
+	pub fn offsets(&self) -> &[Range<usize>] {
+		&self.offsets
+	}
+	pub fn len(&self) -> usize {
+		self.offsets.len()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.offsets.is_empty()
+	}
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.buff[..self.len]
+	}
+	pub fn clear(&mut self) {
+		self.to = None;
+		self.len = 0;
+		self.segment = None;
+		self.offsets.clear();
+		self.closed = false;
+	}
+}
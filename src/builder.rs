@@ -0,0 +1,99 @@
+// Writes a message one attribute at a time, straight into the output buffer. Attributes are encoded
+// as they're pushed, so they don't need to outlive the builder or be collected into a list first.
+// The header's length is patched after every push, so the buffer always holds a complete message.
+use crate::attr::{AttrContext, StunAttr};
+use crate::{SizeLimit, StunEncodeErr, StunTyp, TxId};
+
+#[derive(Debug)]
+pub struct StunBuilder<'b> {
+	buff: &'b mut [u8],
+	length: usize,
+	limit: SizeLimit,
+	// The first error hit while extending, reported by finish
+	err: Option<StunEncodeErr>,
+}
+impl<'b> StunBuilder<'b> {
+	pub fn new(buff: &'b mut [u8], typ: StunTyp, txid: TxId) -> Result<Self, StunEncodeErr> {
+		Self::new_limit(buff, typ, txid, SizeLimit::default())
+	}
+	pub fn new_limit(
+		buff: &'b mut [u8],
+		typ: StunTyp,
+		txid: TxId,
+		limit: SizeLimit,
+	) -> Result<Self, StunEncodeErr> {
+		if limit.0 < 20 {
+			return Err(StunEncodeErr::TooLarge);
+		}
+		let Some(header) = buff.first_chunk_mut::<20>() else {
+			return Err(StunEncodeErr::BufferTooSmall(20));
+		};
+		let [t0, t1] = <[u8; 2]>::from(&typ);
+		let [m0, m1, m2, m3] = 0x2112A442u32.to_be_bytes();
+		let [x0, x1, x2, x3, x4, x5, x6, x7, x8, x9, x10, x11] = txid.0;
+		*header = [
+			t0, t1, 0, 0, m0, m1, m2, m3, x0, x1, x2, x3, x4, x5, x6, x7, x8, x9, x10, x11,
+		];
+		Ok(Self {
+			buff,
+			length: 0,
+			limit,
+			err: None,
+		})
+	}
+	// Encodes attr after the attributes already pushed. On error nothing is written and the message
+	// is left as it was.
+	pub fn push(&mut self, attr: StunAttr<'_>) -> Result<&mut Self, StunEncodeErr> {
+		let attr_len = attr.len()?;
+		let padded_len = attr.padded_len()? as usize;
+		let length = self.length + padded_len;
+		if length > 0xFFFC || 20 + length > self.limit.0 {
+			return Err(StunEncodeErr::TooLarge);
+		}
+		let Some((header, attrs)) = self
+			.buff
+			.get_mut(..20 + length)
+			.and_then(|b| b.split_first_chunk_mut::<20>())
+		else {
+			return Err(StunEncodeErr::BufferTooSmall(20 + length));
+		};
+		let (attrs_prefix, to_write) = attrs.split_at_mut(self.length);
+		let ctx = AttrContext {
+			header,
+			attrs_prefix,
+			attr_len,
+			zero_xor_bytes: false,
+			padding: &[],
+		};
+		attr.encode(to_write, ctx)?;
+
+		self.length = length;
+		header[2..4].copy_from_slice(&(length as u16).to_be_bytes());
+		Ok(self)
+	}
+	// The length of the message written so far
+	pub fn len(&self) -> usize {
+		20 + self.length
+	}
+	// Returns the length of the finished message, or the first error hit while extending
+	pub fn finish(self) -> Result<usize, StunEncodeErr> {
+		match self.err {
+			Some(e) => Err(e),
+			None => Ok(self.len()),
+		}
+	}
+}
+impl<'i> Extend<StunAttr<'i>> for StunBuilder<'_> {
+	// Stops at the first attribute that doesn't fit
+	fn extend<T: IntoIterator<Item = StunAttr<'i>>>(&mut self, iter: T) {
+		if self.err.is_some() {
+			return;
+		}
+		for attr in iter {
+			if let Err(e) = self.push(attr) {
+				self.err = Some(e);
+				return;
+			}
+		}
+	}
+}
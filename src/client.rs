@@ -0,0 +1,224 @@
+// Async clients driving the sans-io ClientTransaction: they only add the socket, the clock and the
+// timer, all of which come from an AsyncTransport so that any runtime (or a simulation) can drive
+// them.
+use std::future::{poll_fn, Future};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::pin;
+use std::task::Poll;
+
+use crate::attr::StunAttr;
+use crate::clock::Clock;
+use crate::record::{Direction, Recording};
+use crate::transactions::{Alternate, ClientTransaction, RedirectErr, Redirects, TransactionState};
+use crate::{Stun, StunDecodeErr, StunEncodeErr, StunMethod, StunTyp};
+
+// A datagram socket plus its runtime's clock and timer
+pub trait AsyncTransport: Clock {
+	fn send_to(&self, packet: &[u8], to: SocketAddr) -> impl Future<Output = io::Result<()>>;
+	fn recv_from(&self, buff: &mut [u8]) -> impl Future<Output = io::Result<(usize, SocketAddr)>>;
+	fn sleep_until(&self, deadline: Self::Timestamp) -> impl Future<Output = ()>;
+}
+
+// tokio's clock, which can be paused and advanced in tests
+#[cfg(feature = "tokio")]
+impl Clock for tokio::net::UdpSocket {
+	type Timestamp = std::time::Instant;
+	fn now(&self) -> std::time::Instant {
+		tokio::time::Instant::now().into_std()
+	}
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncTransport for tokio::net::UdpSocket {
+	async fn send_to(&self, packet: &[u8], to: SocketAddr) -> io::Result<()> {
+		tokio::net::UdpSocket::send_to(self, packet, to)
+			.await
+			.map(|_| ())
+	}
+	async fn recv_from(&self, buff: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+		tokio::net::UdpSocket::recv_from(self, buff).await
+	}
+	async fn sleep_until(&self, deadline: std::time::Instant) {
+		tokio::time::sleep_until(deadline.into()).await
+	}
+}
+
+// A transport that records everything sent and received through it, for running the clients below
+// with a Recording attached
+#[derive(Debug)]
+pub struct Recorded<S> {
+	pub transport: S,
+	pub recording: Recording,
+}
+impl<S> Recorded<S> {
+	pub fn new(transport: S, recording: Recording) -> Self {
+		Self {
+			transport,
+			recording,
+		}
+	}
+}
+impl<S: Clock> Clock for Recorded<S> {
+	type Timestamp = S::Timestamp;
+	fn now(&self) -> S::Timestamp {
+		self.transport.now()
+	}
+}
+impl<S: AsyncTransport> AsyncTransport for Recorded<S> {
+	async fn send_to(&self, packet: &[u8], to: SocketAddr) -> io::Result<()> {
+		self.recording.record(Direction::Outbound, to, packet);
+		self.transport.send_to(packet, to).await
+	}
+	async fn recv_from(&self, buff: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+		let (len, from) = self.transport.recv_from(buff).await?;
+		self.recording.record(Direction::Inbound, from, &buff[..len]);
+		Ok((len, from))
+	}
+	async fn sleep_until(&self, deadline: S::Timestamp) {
+		self.transport.sleep_until(deadline).await
+	}
+}
+
+// Waits for a packet, or returns None at the deadline
+async fn recv_until<S: AsyncTransport>(
+	sock: &S,
+	buff: &mut [u8],
+	deadline: S::Timestamp,
+) -> Option<io::Result<(usize, SocketAddr)>> {
+	let mut recv = pin!(sock.recv_from(buff));
+	let mut sleep = pin!(sock.sleep_until(deadline));
+	poll_fn(|cx| {
+		if let Poll::Ready(received) = recv.as_mut().poll(cx) {
+			return Poll::Ready(Some(received));
+		}
+		sleep.as_mut().poll(cx).map(|_| None)
+	})
+	.await
+}
+
+#[derive(Debug)]
+pub enum ClientErr {
+	Io(io::Error),
+	Encode(StunEncodeErr),
+	Decode(StunDecodeErr),
+	Timeout,
+	// The server answered with an error response
+	ErrorResponse(u16),
+	NoMappedAddress,
+	Redirect(RedirectErr),
+}
+impl From<io::Error> for ClientErr {
+	fn from(value: io::Error) -> Self {
+		Self::Io(value)
+	}
+}
+impl From<StunEncodeErr> for ClientErr {
+	fn from(value: StunEncodeErr) -> Self {
+		Self::Encode(value)
+	}
+}
+impl From<StunDecodeErr> for ClientErr {
+	fn from(value: StunDecodeErr) -> Self {
+		Self::Decode(value)
+	}
+}
+impl From<RedirectErr> for ClientErr {
+	fn from(value: RedirectErr) -> Self {
+		Self::Redirect(value)
+	}
+}
+
+// Runs the transaction against server, returning the response (success or error) decoded from
+// buff. Packets from other addresses or with other transaction ids are dropped.
+pub async fn request<'b, S: AsyncTransport>(
+	sock: &S,
+	server: SocketAddr,
+	tx: ClientTransaction<S::Timestamp>,
+	buff: &'b mut [u8],
+) -> Result<Stun<'b>, ClientErr> {
+	let len = transact(sock, server, tx, buff).await?;
+	Ok(Stun::decode(&buff[..len])?)
+}
+
+// Like request, but follows 300 Try Alternate responses. Every attempt is a new transaction, so
+// build writes a fresh request into the buffer each time. Returns the server that answered too, and
+// the ALTERNATE-DOMAIN of the redirect that led there: over TLS, the name its certificate has to
+// match.
+// A redirect is only followed if verify accepts the 300: with authenticated requests that means
+// checking its integrity (RFC 8489 section 10), otherwise anyone on the path could send the client
+// elsewhere. A 300 that isn't accepted is returned like any other error response.
+pub async fn request_redirected<'b, S, F, V>(
+	sock: &S,
+	server: SocketAddr,
+	mut build: F,
+	mut verify: V,
+	buff: &'b mut [u8],
+) -> Result<(SocketAddr, Option<String>, Stun<'b>), ClientErr>
+where
+	S: AsyncTransport,
+	F: FnMut(&mut [u8]) -> Result<usize, StunEncodeErr>,
+	V: FnMut(&Stun<'_>) -> bool,
+{
+	let mut redirects = Redirects::new(server);
+	let mut domain = None;
+	let len = loop {
+		let len = build(buff)?;
+		let tx = ClientTransaction::new(buff[..len].to_vec(), sock.now())?;
+		let len = transact(sock, redirects.current(), tx, buff).await?;
+		let res = Stun::decode(&buff[..len])?;
+		match Alternate::from_response(&res) {
+			Some(alternate) if verify(&res) => {
+				redirects.follow(alternate.server)?;
+				domain = alternate.domain;
+			}
+			_ => break len,
+		}
+	};
+	Ok((redirects.current(), domain, Stun::decode(&buff[..len])?))
+}
+
+// Returns the length of the response in buff
+async fn transact<S: AsyncTransport>(
+	sock: &S,
+	server: SocketAddr,
+	mut tx: ClientTransaction<S::Timestamp>,
+	buff: &mut [u8],
+) -> Result<usize, ClientErr> {
+	loop {
+		if let Some(req) = tx.poll_transmit(sock.now()) {
+			sock.send_to(req, server).await?;
+		}
+		let Some(deadline) = tx.poll_timeout() else {
+			debug_assert_eq!(tx.state(), TransactionState::TimedOut);
+			return Err(ClientErr::Timeout);
+		};
+		let Some(received) = recv_until(sock, buff, deadline).await else {
+			tx.handle_timeout(sock.now());
+			continue;
+		};
+		let (len, from) = received?;
+		if from == server && tx.handle(&buff[..len]).is_some() {
+			return Ok(len);
+		}
+	}
+}
+
+// Asks server (or the server it redirects us to) for our reflexive address
+pub async fn binding_request<S: AsyncTransport>(
+	sock: &S,
+	server: SocketAddr,
+) -> Result<SocketAddr, ClientErr> {
+	let attrs = [StunAttr::Fingerprint];
+	let mut buff = [0u8; 1024];
+	let build = |buff: &mut [u8]| Stun::try_req(StunMethod::Binding, &attrs)?.encode(buff);
+	// The request isn't authenticated, so there's nothing to check the redirect against
+	let (_, _, res) = request_redirected(sock, server, build, |_| true, &mut buff).await?;
+	let flat = res.flat();
+	if let StunTyp::Err(_) = res.typ {
+		return Err(ClientErr::ErrorResponse(flat.error.map_or(0, |e| e.code)));
+	}
+	flat.xmapped
+		.or(flat.mapped)
+		.ok_or(ClientErr::NoMappedAddress)
+}
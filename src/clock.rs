@@ -0,0 +1,63 @@
+use std::cell::Cell;
+use std::ops::Add;
+use std::time::Duration;
+
+// The sans-io components never read the time themselves: the caller passes in the current time as
+// any Timestamp, which lets tests and simulators drive them with virtual time.
+pub trait Timestamp: Copy + Ord + Add<Duration, Output = Self> {
+	fn saturating_duration_since(&self, earlier: Self) -> Duration;
+}
+impl Timestamp for std::time::Instant {
+	fn saturating_duration_since(&self, earlier: Self) -> Duration {
+		std::time::Instant::saturating_duration_since(self, earlier)
+	}
+}
+
+pub trait Clock {
+	type Timestamp: Timestamp;
+	fn now(&self) -> Self::Timestamp;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+	type Timestamp = std::time::Instant;
+	fn now(&self) -> Self::Timestamp {
+		std::time::Instant::now()
+	}
+}
+
+// Time elapsed since the start of a simulation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VirtualTime(pub Duration);
+impl Add<Duration> for VirtualTime {
+	type Output = Self;
+	fn add(self, rhs: Duration) -> Self {
+		Self(self.0 + rhs)
+	}
+}
+impl Timestamp for VirtualTime {
+	fn saturating_duration_since(&self, earlier: Self) -> Duration {
+		self.0.saturating_sub(earlier.0)
+	}
+}
+
+// A clock that only moves when told to
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+	now: Cell<VirtualTime>,
+}
+impl ManualClock {
+	pub fn advance(&self, by: Duration) {
+		self.now.set(self.now.get() + by);
+	}
+	pub fn set(&self, now: VirtualTime) {
+		self.now.set(now);
+	}
+}
+impl Clock for ManualClock {
+	type Timestamp = VirtualTime;
+	fn now(&self) -> Self::Timestamp {
+		self.now.get()
+	}
+}
@@ -0,0 +1,32 @@
+// RFC 7983 (updating RFC 5764 section 5.1.2): telling apart the protocols that share a WebRTC
+// socket by their first byte, without decoding anything
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketClass {
+	Stun,
+	Zrtp,
+	Dtls,
+	TurnChannel,
+	// SRTP or SRTCP (RFC 5761 separates them by payload type, which is left to the caller)
+	Rtp,
+	// Nothing that RFC 7983 assigns, including STUN-range packets without the magic cookie
+	Unknown,
+}
+
+pub fn classify(packet: &[u8]) -> PacketClass {
+	let Some(&first) = packet.first() else {
+		return PacketClass::Unknown;
+	};
+	match first {
+		0..=3 => match packet.get(4..8) {
+			Some(magic) if packet.len() >= 20 && magic == 0x2112A442u32.to_be_bytes() => {
+				PacketClass::Stun
+			}
+			_ => PacketClass::Unknown,
+		},
+		16..=19 => PacketClass::Zrtp,
+		20..=63 => PacketClass::Dtls,
+		64..=79 => PacketClass::TurnChannel,
+		128..=191 => PacketClass::Rtp,
+		_ => PacketClass::Unknown,
+	}
+}
@@ -0,0 +1,70 @@
+use crate::attr::{AttrContext, StunAttr};
+use crate::Stun;
+
+#[derive(Debug, Clone)]
+pub enum Difference<'i> {
+	Typ,
+	Txid,
+	// An attribute that's in a but not in b
+	Missing(StunAttr<'i>),
+	// An attribute that's in b but not in a
+	Extra(StunAttr<'i>),
+	// The nth occurrence of an attribute type has a different value
+	Value { a: StunAttr<'i>, b: StunAttr<'i> },
+	// The attribute appears in both messages, but at a different position relative to the others
+	Order { typ: u16, a_index: usize, b_index: usize },
+}
+
+// Compare attribute values by their encoding (without xor-ing), since not all values are PartialEq
+fn encoded(attr: &StunAttr<'_>) -> Vec<u8> {
+	let mut ret = vec![0; attr.length() as usize];
+	let ctx = AttrContext {
+		header: &[0; 20],
+		zero_xor_bytes: true,
+		attrs_prefix: &[],
+		attr_len: 0,
+		padding: &[],
+	};
+	attr.value().encode(&mut ret, ctx);
+	ret
+}
+
+// Lists the differences between two messages. Attributes are paired up by type and occurrence (the
+// first USERNAME in a with the first USERNAME in b, and so on).
+pub fn diff<'i>(a: &Stun<'i>, b: &Stun<'i>) -> Vec<Difference<'i>> {
+	let mut ret = Vec::new();
+	if <[u8; 2]>::from(&a.typ) != <[u8; 2]>::from(&b.typ) {
+		ret.push(Difference::Typ);
+	}
+	if a.txid != b.txid {
+		ret.push(Difference::Txid);
+	}
+
+	let a_attrs: Vec<_> = a.attrs.into_iter().filter_map(Result::ok).collect();
+	let mut b_attrs: Vec<_> = b.attrs.into_iter().filter_map(Result::ok).map(Some).collect();
+	let mut pairs = Vec::new();
+	for (a_index, a_attr) in a_attrs.into_iter().enumerate() {
+		let found = b_attrs
+			.iter()
+			.position(|b| b.as_ref().is_some_and(|b| b.typ() == a_attr.typ()));
+		let Some(b_index) = found else {
+			ret.push(Difference::Missing(a_attr));
+			continue;
+		};
+		let b_attr = b_attrs[b_index].take().unwrap();
+		pairs.push((a_index, b_index, a_attr.typ()));
+		if encoded(&a_attr) != encoded(&b_attr) {
+			ret.push(Difference::Value { a: a_attr, b: b_attr });
+		}
+	}
+	ret.extend(b_attrs.into_iter().flatten().map(Difference::Extra));
+
+	let mut max_b = None;
+	for (a_index, b_index, typ) in pairs {
+		if max_b.is_some_and(|m| b_index < m) {
+			ret.push(Difference::Order { typ, a_index, b_index });
+		}
+		max_b = max_b.max(Some(b_index));
+	}
+	ret
+}
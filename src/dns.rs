@@ -0,0 +1,287 @@
+// Finding STUN / TURN servers from a host name (RFC 8489 section 8, RFC 5928). SRV records are
+// looked up with a minimal DNS client; when there are none, the host's own addresses are used with
+// the default port. NAPTR based transport selection (RFC 5928 section 3) isn't done: the caller
+// picks the transport. A/AAAA lookups go through the system resolver.
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use crate::server::stream::{STUNS_PORT, STUN_PORT};
+use crate::TxId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Service {
+	StunUdp,
+	StunTcp,
+	// stuns: over TLS
+	Stuns,
+	TurnUdp,
+	TurnTcp,
+	Turns,
+}
+impl Service {
+	pub fn srv_prefix(self) -> &'static str {
+		match self {
+			Self::StunUdp => "_stun._udp",
+			Self::StunTcp => "_stun._tcp",
+			Self::Stuns => "_stuns._tcp",
+			Self::TurnUdp => "_turn._udp",
+			Self::TurnTcp => "_turn._tcp",
+			Self::Turns => "_turns._tcp",
+		}
+	}
+	pub fn default_port(self) -> u16 {
+		match self {
+			Self::Stuns | Self::Turns => STUNS_PORT,
+			_ => STUN_PORT,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+	pub priority: u16,
+	pub weight: u16,
+	pub port: u16,
+	pub target: String,
+}
+
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+fn invalid(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// A recursive query for name's SRV records
+pub fn srv_query(name: &str, id: u16) -> io::Result<Vec<u8>> {
+	let mut ret = Vec::with_capacity(18 + name.len());
+	ret.extend_from_slice(&id.to_be_bytes());
+	// Recursion desired, one question
+	ret.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+	for label in name.trim_end_matches('.').split('.') {
+		if label.is_empty() || label.len() > 63 {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "bad DNS name"));
+		}
+		ret.push(label.len() as u8);
+		ret.extend_from_slice(label.as_bytes());
+	}
+	ret.push(0);
+	ret.extend_from_slice(&TYPE_SRV.to_be_bytes());
+	ret.extend_from_slice(&CLASS_IN.to_be_bytes());
+	Ok(ret)
+}
+
+// Reads the (possibly compressed) name at offset, returning it and the offset just past it
+fn read_name(msg: &[u8], mut offset: usize) -> io::Result<(String, usize)> {
+	let mut name = String::new();
+	let mut end = None;
+	// Each pointer has to go backwards, so this also bounds the number of jumps
+	let mut limit = offset;
+	loop {
+		let len = *msg
+			.get(offset)
+			.ok_or_else(|| invalid("DNS name runs past the end"))? as usize;
+		match len >> 6 {
+			0b00 if len == 0 => break,
+			0b00 => {
+				let label = msg
+					.get(offset + 1..offset + 1 + len)
+					.ok_or_else(|| invalid("DNS label runs past the end"))?;
+				if !name.is_empty() {
+					name.push('.');
+				}
+				name.push_str(&String::from_utf8_lossy(label));
+				offset += 1 + len;
+			}
+			0b11 => {
+				let lo = *msg
+					.get(offset + 1)
+					.ok_or_else(|| invalid("DNS pointer runs past the end"))?;
+				let target = ((len & 0x3F) << 8) | lo as usize;
+				if target >= limit {
+					return Err(invalid("DNS pointer doesn't point backwards"));
+				}
+				end.get_or_insert(offset + 2);
+				offset = target;
+				limit = target;
+			}
+			_ => return Err(invalid("unknown DNS label type")),
+		}
+	}
+	Ok((name, end.unwrap_or(offset + 1)))
+}
+
+fn be16(msg: &[u8], offset: usize) -> io::Result<u16> {
+	let bytes = msg
+		.get(offset..offset + 2)
+		.ok_or_else(|| invalid("DNS message too short"))?;
+	Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+// Parses the SRV records out of the response to srv_query. A name that doesn't exist has no
+// records.
+pub fn parse_srv(msg: &[u8], id: u16) -> io::Result<Vec<SrvRecord>> {
+	if msg.len() < 12 {
+		return Err(invalid("DNS message too short"));
+	}
+	if be16(msg, 0)? != id || msg[2] & 0x80 == 0 {
+		return Err(invalid("not the response to our DNS query"));
+	}
+	match msg[3] & 0x0F {
+		0 => {}
+		3 => return Ok(Vec::new()),
+		_ => return Err(invalid("DNS server failed the query")),
+	}
+	let (questions, answers) = (be16(msg, 4)?, be16(msg, 6)?);
+	let mut offset = 12;
+	for _ in 0..questions {
+		offset = read_name(msg, offset)?.1 + 4;
+	}
+	let mut ret = Vec::new();
+	for _ in 0..answers {
+		offset = read_name(msg, offset)?.1;
+		let (typ, class) = (be16(msg, offset)?, be16(msg, offset + 2)?);
+		let length = be16(msg, offset + 8)? as usize;
+		let data = offset + 10;
+		if msg.len() < data + length {
+			return Err(invalid("DNS record runs past the end"));
+		}
+		// Skips CNAMEs and the like
+		if typ == TYPE_SRV && class == CLASS_IN && length >= 7 {
+			ret.push(SrvRecord {
+				priority: be16(msg, data)?,
+				weight: be16(msg, data + 2)?,
+				port: be16(msg, data + 4)?,
+				target: read_name(msg, data + 6)?.0,
+			});
+		}
+		offset = data + length;
+	}
+	Ok(ret)
+}
+
+// Orders records the way RFC 2782 asks: by priority, then randomly within a priority with the
+// chance of going first proportional to the weight. A target of "." means the service isn't
+// offered.
+pub fn order(mut records: Vec<SrvRecord>) -> Vec<SrvRecord> {
+	records.retain(|r| !r.target.is_empty() && r.target != ".");
+	records.sort_by_key(|r| r.priority);
+	let mut ret = Vec::with_capacity(records.len());
+	for group in records.chunk_by(|a, b| a.priority == b.priority) {
+		let mut group = group.to_vec();
+		// Zero weights go first, so that they still win a draw of 0 against the weighted ones
+		group.sort_by_key(|r| r.weight != 0);
+		while !group.is_empty() {
+			let total: u32 = group.iter().map(|r| r.weight as u32).sum();
+			// The draw only spreads the load, so it doesn't need the OS's random source
+			let pick = to_u32(crate::weak_txid()) % (total + 1);
+			let mut sum = 0;
+			let i = group
+				.iter()
+				.position(|r| {
+					sum += r.weight as u32;
+					sum >= pick
+				})
+				.unwrap_or(0);
+			ret.push(group.remove(i));
+		}
+	}
+	ret
+}
+
+fn to_u32(txid: TxId) -> u32 {
+	u32::from_le_bytes([txid.0[0], txid.0[1], txid.0[2], txid.0[3]])
+}
+
+#[derive(Debug, Clone)]
+pub struct Resolver {
+	pub nameserver: SocketAddr,
+	pub timeout: Duration,
+	pub attempts: usize,
+}
+impl Resolver {
+	pub fn new(nameserver: SocketAddr) -> Self {
+		Self {
+			nameserver,
+			timeout: Duration::from_secs(2),
+			attempts: 3,
+		}
+	}
+	// The first nameserver in /etc/resolv.conf
+	pub fn from_system() -> io::Result<Self> {
+		let conf = std::fs::read_to_string("/etc/resolv.conf")?;
+		conf.lines()
+			.filter_map(|line| line.strip_prefix("nameserver"))
+			.find_map(|ip| ip.trim().parse::<IpAddr>().ok())
+			.map(|ip| Self::new(SocketAddr::new(ip, 53)))
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no nameserver in resolv.conf"))
+	}
+	pub fn srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+		let bind: SocketAddr = match self.nameserver {
+			SocketAddr::V4(_) => "0.0.0.0:0".parse().unwrap(),
+			SocketAddr::V6(_) => "[::]:0".parse().unwrap(),
+		};
+		let sock = UdpSocket::bind(bind)?;
+		sock.connect(self.nameserver)?;
+		sock.set_read_timeout(Some(self.timeout))?;
+		// The id is all that stops someone off the path from answering for the nameserver
+		let txid =
+			crate::random_txid().map_err(|_| io::Error::other("the OS random source failed"))?;
+		let id = to_u32(txid) as u16;
+		let query = srv_query(name, id)?;
+		let mut buff = [0u8; 1232];
+		for _ in 0..self.attempts.max(1) {
+			sock.send(&query)?;
+			match sock.recv(&mut buff) {
+				// A truncated response still has whichever records fit
+				Ok(len) => match parse_srv(&buff[..len], id) {
+					Ok(records) => return Ok(records),
+					Err(_) => continue,
+				},
+				Err(e)
+					if matches!(
+						e.kind(),
+						io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+					) =>
+				{
+					continue
+				}
+				Err(e) => return Err(e),
+			}
+		}
+		Err(io::Error::new(
+			io::ErrorKind::TimedOut,
+			"DNS query timed out",
+		))
+	}
+	// The addresses to try for a server, in order. An IP address or explicit port skips the SRV
+	// lookup (RFC 7064 section 3.1).
+	pub fn resolve(
+		&self,
+		service: Service,
+		host: &str,
+		port: Option<u16>,
+	) -> io::Result<Vec<SocketAddr>> {
+		if let Ok(ip) = host.trim_matches(['[', ']']).parse::<IpAddr>() {
+			return Ok(vec![SocketAddr::new(
+				ip,
+				port.unwrap_or(service.default_port()),
+			)]);
+		}
+		if let Some(port) = port {
+			return Ok((host, port).to_socket_addrs()?.collect());
+		}
+		let name = format!("{}.{}", service.srv_prefix(), host);
+		let mut ret = Vec::new();
+		for record in order(self.srv(&name).unwrap_or_default()) {
+			if let Ok(addrs) = (record.target.as_str(), record.port).to_socket_addrs() {
+				ret.extend(addrs);
+			}
+		}
+		if ret.is_empty() {
+			ret.extend((host, service.default_port()).to_socket_addrs()?);
+		}
+		Ok(ret)
+	}
+}
@@ -0,0 +1,232 @@
+// ICE connectivity checks (RFC 8445 section 7) with short-term credentials. A check from us carries
+// USERNAME "remote ufrag:local ufrag" and is signed with the remote password, its response is signed
+// with that same password. Checks from the peer are the mirror image, using our password.
+use std::net::SocketAddr;
+
+use crate::attr::{typ, Integrity, StunAttr};
+use crate::auth::{IntegrityKey, ShortTermKey, StunAuthErr};
+use crate::{Stun, StunEncodeErr, StunMethod, StunTyp, TxId};
+
+pub mod checks;
+pub mod consent;
+pub mod keepalive;
+pub mod lite;
+pub mod nomination;
+pub mod username;
+
+// Recommended type preferences (RFC 8445 section 5.1.2.2)
+pub const HOST_PREFERENCE: u8 = 126;
+pub const PRFLX_PREFERENCE: u8 = 110;
+pub const SRFLX_PREFERENCE: u8 = 100;
+pub const RELAY_PREFERENCE: u8 = 0;
+
+// A candidate's priority (RFC 8445 section 5.1.2.1), or None unless type_pref is at most 126 and
+// component is 1 to 256
+pub fn priority(type_pref: u8, local_pref: u16, component: u16) -> Option<u32> {
+	if type_pref > 126 || !(1..=256).contains(&component) {
+		return None;
+	}
+	Some(((type_pref as u32) << 24) | ((local_pref as u32) << 8) | (256 - component as u32))
+}
+
+// The PRIORITY of a check from a local candidate: the priority it would have as a peer reflexive
+// candidate, keeping its local preference and component (RFC 8445 section 7.1.1)
+pub fn prflx_priority(local_pref: u16, component: u16) -> Option<u32> {
+	priority(PRFLX_PREFERENCE, local_pref, component)
+}
+
+// Our role and tie-breaker, sent in every check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IceRole {
+	Controlling(u64),
+	Controlled(u64),
+}
+impl IceRole {
+	pub fn tie_breaker(self) -> u64 {
+		match self {
+			Self::Controlling(t) | Self::Controlled(t) => t,
+		}
+	}
+	// The other role, keeping the tie-breaker
+	pub fn switched(self) -> Self {
+		match self {
+			Self::Controlling(t) => Self::Controlled(t),
+			Self::Controlled(t) => Self::Controlling(t),
+		}
+	}
+	// Resolves our role against the role in a check from the peer (RFC 8445 section 7.3.1.1): the
+	// agent with the larger tie-breaker gets to be controlling
+	pub fn conflict(self, remote: IceRole) -> RoleConflict {
+		match (self, remote) {
+			(Self::Controlling(ours), Self::Controlling(theirs)) if ours >= theirs => {
+				RoleConflict::Reject
+			}
+			(Self::Controlled(ours), Self::Controlled(theirs)) if ours < theirs => {
+				RoleConflict::Reject
+			}
+			(Self::Controlling(_), Self::Controlling(_))
+			| (Self::Controlled(_), Self::Controlled(_)) => RoleConflict::Switch,
+			_ => RoleConflict::None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoleConflict {
+	None,
+	// Switch to the other role, then answer the check as usual
+	Switch,
+	// Keep our role and answer the check with 487 Role Conflict
+	Reject,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConnCheckErr {
+	Auth(StunAuthErr),
+	// Not a Binding request
+	NotACheck,
+	// PRIORITY or ICE-CONTROLLING / ICE-CONTROLLED (by type) is missing
+	Missing(u16),
+}
+impl From<StunAuthErr> for ConnCheckErr {
+	fn from(value: StunAuthErr) -> Self {
+		Self::Auth(value)
+	}
+}
+
+// The attributes of a connectivity check, a Binding request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnCheck<'i> {
+	pub username: &'i str,
+	pub priority: u32,
+	pub role: IceRole,
+	pub use_candidate: bool,
+}
+impl<'i> ConnCheck<'i> {
+	// Writes the check into buff, signed with key. The attributes go in the order RFC 8445 expects:
+	// USERNAME, PRIORITY, the role, USE-CANDIDATE, then MESSAGE-INTEGRITY and FINGERPRINT.
+	pub fn build<K: IntegrityKey>(
+		&self,
+		key: &K,
+		buff: &mut [u8],
+	) -> Result<(TxId, usize), StunEncodeErr> {
+		let mut attrs = vec![
+			StunAttr::Username(self.username),
+			StunAttr::Priority(self.priority),
+			match self.role {
+				IceRole::Controlling(tie_breaker) => StunAttr::IceControlling(tie_breaker),
+				IceRole::Controlled(tie_breaker) => StunAttr::IceControlled(tie_breaker),
+			},
+		];
+		if self.use_candidate {
+			attrs.push(StunAttr::UseCandidate);
+		}
+		attrs.push(StunAttr::Integrity(Integrity::sign(key)));
+		attrs.push(StunAttr::Fingerprint);
+		let req = Stun::try_req(StunMethod::Binding, &attrs)?;
+		Ok((req.txid, req.encode(buff)?))
+	}
+	// Decodes a check, verifying its FINGERPRINT (if any) and integrity against key. Which USERNAME
+	// to expect is up to the caller.
+	pub fn verify<K: IntegrityKey + ?Sized>(
+		packet: &'i [u8],
+		key: &K,
+	) -> Result<(Stun<'i>, Self), ConnCheckErr> {
+		let msg = Stun::decode_and_verify(packet, key)?;
+		if !matches!(msg.typ, StunTyp::Req(StunMethod::Binding)) {
+			return Err(ConnCheckErr::NotACheck);
+		}
+		let flat = msg.flat();
+		let role = match (flat.ice_controlling, flat.ice_controlled) {
+			(Some(tie_breaker), _) => IceRole::Controlling(tie_breaker),
+			(None, Some(tie_breaker)) => IceRole::Controlled(tie_breaker),
+			(None, None) => return Err(ConnCheckErr::Missing(typ::ICE_CONTROLLING)),
+		};
+		let check = Self {
+			username: flat.username.ok_or(StunAuthErr::MissingUsername)?,
+			priority: flat.priority.ok_or(ConnCheckErr::Missing(typ::PRIORITY))?,
+			role,
+			use_candidate: flat.use_candidate.is_some(),
+		};
+		Ok((msg, check))
+	}
+	// Whether the check nominates its pair: only the controlling agent's USE-CANDIDATE counts
+	pub fn nominates(&self) -> bool {
+		self.use_candidate && matches!(self.role, IceRole::Controlling(_))
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct IceCredentials {
+	local_key: ShortTermKey,
+	remote_key: ShortTermKey,
+	// "remote:local", the USERNAME of our checks
+	outbound: String,
+	// "local:remote", the USERNAME of the peer's checks
+	inbound: String,
+}
+impl IceCredentials {
+	pub fn new(local_ufrag: &str, local_pwd: &str, remote_ufrag: &str, remote_pwd: &str) -> Self {
+		Self {
+			local_key: ShortTermKey::new(local_pwd),
+			remote_key: ShortTermKey::new(remote_pwd),
+			outbound: username::join(remote_ufrag, local_ufrag),
+			inbound: username::join(local_ufrag, remote_ufrag),
+		}
+	}
+	// Writes a Binding request for a check into buff. priority is what a peer reflexive candidate
+	// learned from this check would get, see prflx_priority.
+	pub fn request(
+		&self,
+		role: IceRole,
+		priority: u32,
+		use_candidate: bool,
+		buff: &mut [u8],
+	) -> Result<(TxId, usize), StunEncodeErr> {
+		let check = ConnCheck {
+			username: &self.outbound,
+			priority,
+			role,
+			use_candidate,
+		};
+		check.build(&self.remote_key, buff)
+	}
+	// Decodes a check from the peer, verifying its USERNAME and integrity
+	pub fn verify_request<'i>(&self, packet: &'i [u8]) -> Result<Stun<'i>, StunAuthErr> {
+		let msg = Stun::decode_and_verify(packet, &self.local_key)?;
+		match msg.username() {
+			None => Err(StunAuthErr::MissingUsername),
+			Some(username) if username != self.inbound => Err(StunAuthErr::UnknownUser),
+			Some(_) => Ok(msg),
+		}
+	}
+	// Writes the success response to a (verified) check that arrived from `from`
+	pub fn response(
+		&self,
+		req: &Stun<'_>,
+		from: SocketAddr,
+		buff: &mut [u8],
+	) -> Result<usize, StunEncodeErr> {
+		let attrs = [
+			StunAttr::XMapped(from),
+			StunAttr::Integrity(Integrity::sign(&self.local_key)),
+			StunAttr::Fingerprint,
+		];
+		req.res(&attrs).encode(buff)
+	}
+	// Writes the 487 Role Conflict response to a (verified) check. The peer switches roles and
+	// tries again.
+	pub fn role_conflict(&self, req: &Stun<'_>, buff: &mut [u8]) -> Result<usize, StunEncodeErr> {
+		let attrs = [
+			StunAttr::error_code(487),
+			StunAttr::Integrity(Integrity::sign(&self.local_key)),
+			StunAttr::Fingerprint,
+		];
+		req.err(&attrs).encode(buff)
+	}
+	// Decodes the response to one of our checks, verifying its integrity. Matching the transaction
+	// id is up to the caller.
+	pub fn verify_response<'i>(&self, packet: &'i [u8]) -> Result<Stun<'i>, StunAuthErr> {
+		Stun::decode_and_verify(packet, &self.remote_key)
+	}
+}
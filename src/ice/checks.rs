@@ -0,0 +1,201 @@
+// Paces our connectivity checks (RFC 8445 section 6.1.4.2): at most one new check every Ta, with
+// triggered checks going ahead of ordinary ones. Pairs are identified by whatever the caller uses
+// for them; the check's transaction id maps its response back to the pair. Checking that a
+// response came from the pair's remote address (section 7.2.5.2.1) is up to the caller.
+// Each check is a ClientTransaction, retransmitted on its own schedule: only new checks are paced.
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use super::nomination::Nomination;
+use super::{IceCredentials, IceRole};
+use crate::clock::Timestamp;
+use crate::transactions::{ClientTransaction, TransactionState, RTO};
+use crate::{StunMethod, StunTyp, TxId};
+
+// Ta (section 14.2)
+pub const PACING: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckEvent<K> {
+	// mapped is our address as the peer saw it, which may be a new peer reflexive candidate
+	Succeeded {
+		pair: K,
+		mapped: SocketAddr,
+		nominated: bool,
+	},
+	// 487: resolve the roles (set_role) and check the pair again
+	RoleConflict(K),
+	// code is None for a success response without XOR-MAPPED-ADDRESS
+	Failed {
+		pair: K,
+		code: Option<u16>,
+	},
+}
+
+#[derive(Debug, Clone)]
+struct Queued<K> {
+	pair: K,
+	priority: u32,
+	use_candidate: bool,
+}
+
+#[derive(Debug)]
+struct InFlight<K, T: Timestamp> {
+	check: Queued<K>,
+	tx: ClientTransaction<T>,
+}
+
+#[derive(Debug)]
+pub struct Checks<K, T: Timestamp = Instant> {
+	creds: IceCredentials,
+	role: IceRole,
+	nomination: Nomination,
+	pacing: Duration,
+	rto: Duration,
+	queue: VecDeque<Queued<K>>,
+	in_flight: HashMap<TxId, InFlight<K, T>>,
+	next_send: T,
+}
+impl<K: Clone, T: Timestamp> Checks<K, T> {
+	pub fn new(creds: IceCredentials, role: IceRole, now: T) -> Self {
+		Self {
+			creds,
+			role,
+			nomination: Nomination::Regular,
+			pacing: PACING,
+			rto: RTO,
+			queue: VecDeque::new(),
+			in_flight: HashMap::new(),
+			next_send: now,
+		}
+	}
+	// rto is the least that a check waits before its first retransmission, which is longer when
+	// there are a lot of checks (section 14.3)
+	pub fn with_timing(mut self, pacing: Duration, rto: Duration) -> Self {
+		self.pacing = pacing;
+		self.rto = rto;
+		self
+	}
+	pub fn with_nomination(mut self, nomination: Nomination) -> Self {
+		self.nomination = nomination;
+		self
+	}
+	pub fn role(&self) -> IceRole {
+		self.role
+	}
+	pub fn set_role(&mut self, role: IceRole) {
+		self.role = role;
+	}
+	// With aggressive nomination, every check the controlling agent sends carries USE-CANDIDATE
+	fn use_candidate(&self, use_candidate: bool) -> bool {
+		use_candidate
+			|| (self.nomination == Nomination::Aggressive
+				&& matches!(self.role, IceRole::Controlling(_)))
+	}
+	// Queues an ordinary check. priority is the PRIORITY the check carries (see prflx_priority).
+	pub fn queue(&mut self, pair: K, priority: u32, use_candidate: bool) {
+		let use_candidate = self.use_candidate(use_candidate);
+		self.queue.push_back(Queued {
+			pair,
+			priority,
+			use_candidate,
+		});
+	}
+	// Queues a triggered check, which is sent before any ordinary ones
+	pub fn trigger(&mut self, pair: K, priority: u32, use_candidate: bool) {
+		let use_candidate = self.use_candidate(use_candidate);
+		self.queue.push_front(Queued {
+			pair,
+			priority,
+			use_candidate,
+		});
+	}
+	// Regular nomination: once a pair has succeeded, the controlling agent checks it again with
+	// USE-CANDIDATE. The pair is nominated when that check succeeds.
+	pub fn nominate(&mut self, pair: K, priority: u32) {
+		self.trigger(pair, priority, true);
+	}
+	pub fn queued(&self) -> usize {
+		self.queue.len()
+	}
+	pub fn in_flight(&self) -> usize {
+		self.in_flight.len()
+	}
+	pub fn poll_timeout(&self) -> Option<T> {
+		let send = (!self.queue.is_empty()).then_some(self.next_send);
+		self.in_flight
+			.values()
+			.filter_map(|f| f.tx.poll_timeout())
+			.chain(send)
+			.min()
+	}
+	// Returns the pairs whose checks went unanswered, even after retransmitting
+	pub fn handle_timeout(&mut self, now: T) -> Vec<K> {
+		let mut ret = Vec::new();
+		self.in_flight.retain(|_, f| {
+			f.tx.handle_timeout(now);
+			let timed_out = f.tx.state() == TransactionState::TimedOut;
+			if timed_out {
+				ret.push(f.check.pair.clone());
+			}
+			!timed_out
+		});
+		ret
+	}
+	// Writes the next check (or retransmission of one) into buff if one is due, returning the pair
+	// to send it on
+	pub fn poll_transmit(&mut self, now: T, buff: &mut [u8]) -> Option<(K, usize)> {
+		for f in self.in_flight.values_mut() {
+			let Some(req) = f.tx.poll_transmit(now) else {
+				continue;
+			};
+			buff.get_mut(..req.len())?.copy_from_slice(req);
+			return Some((f.check.pair.clone(), req.len()));
+		}
+		if now < self.next_send {
+			return None;
+		}
+		let check = self.queue.front()?;
+		let (_, len) = self
+			.creds
+			.request(self.role, check.priority, check.use_candidate, buff)
+			.ok()?;
+		// RTO = MAX(500ms, Ta * (Num-Waiting + Num-In-Progress))
+		let checks = (self.queue.len() + self.in_flight.len()) as u32;
+		let rto = self.rto.max(self.pacing.saturating_mul(checks));
+		let mut tx = ClientTransaction::new(buff[..len].to_vec(), now)
+			.ok()?
+			.with_rto(rto);
+		tx.poll_transmit(now);
+		let check = self.queue.pop_front()?;
+		let pair = check.pair.clone();
+		self.in_flight.insert(tx.txid(), InFlight { check, tx });
+		self.next_send = now + self.pacing;
+		Some((pair, len))
+	}
+	// Handles a response to one of our checks. Anything else (including responses that fail the
+	// integrity check) is ignored.
+	pub fn handle(&mut self, packet: &[u8]) -> Option<CheckEvent<K>> {
+		let msg = self.creds.verify_response(packet).ok()?;
+		let success = match msg.typ {
+			StunTyp::Res(StunMethod::Binding) => true,
+			StunTyp::Err(StunMethod::Binding) => false,
+			_ => return None,
+		};
+		let InFlight { check, .. } = self.in_flight.remove(&msg.txid)?;
+		let flat = msg.flat();
+		Some(match (success, flat.xmapped, flat.error) {
+			(true, Some(mapped), _) => CheckEvent::Succeeded {
+				pair: check.pair,
+				mapped,
+				nominated: check.use_candidate && matches!(self.role, IceRole::Controlling(_)),
+			},
+			(false, _, Some(e)) if e.code == 487 => CheckEvent::RoleConflict(check.pair),
+			(_, _, e) => CheckEvent::Failed {
+				pair: check.pair,
+				code: e.map(|e| e.code),
+			},
+		})
+	}
+}
@@ -0,0 +1,122 @@
+// Consent freshness (RFC 7675): once a pair is in use we keep sending it connectivity checks, and
+// stop sending media if none of them has been answered for 30 seconds.
+use std::time::{Duration, Instant};
+
+use super::{IceCredentials, IceRole};
+use crate::clock::Timestamp;
+use crate::{StunMethod, StunTyp, TxId};
+
+// Checks are sent every 4 to 6 seconds (section 5.1)
+pub const CONSENT_INTERVAL: Duration = Duration::from_secs(5);
+pub const CONSENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Responses to checks older than this many intervals are no longer matched
+const MAX_OUTSTANDING: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsentState {
+	Granted,
+	// Nothing may be sent on the pair anymore (other than new checks, by starting over)
+	Expired,
+}
+
+#[derive(Debug, Clone)]
+pub struct Consent<T: Timestamp = Instant> {
+	creds: IceCredentials,
+	role: IceRole,
+	priority: u32,
+	interval: Duration,
+	timeout: Duration,
+	state: ConsentState,
+	next_send: T,
+	last_response: T,
+	txids: Vec<TxId>,
+	failures: usize,
+}
+impl<T: Timestamp> Consent<T> {
+	// Consent starts out granted by the successful check that validated the pair. priority is the
+	// PRIORITY of our checks.
+	pub fn new(creds: IceCredentials, role: IceRole, priority: u32, now: T) -> Self {
+		Self {
+			creds,
+			role,
+			priority,
+			interval: CONSENT_INTERVAL,
+			timeout: CONSENT_TIMEOUT,
+			state: ConsentState::Granted,
+			next_send: now,
+			last_response: now,
+			txids: Vec::new(),
+			failures: 0,
+		}
+	}
+	pub fn with_schedule(mut self, interval: Duration, timeout: Duration) -> Self {
+		self.interval = interval;
+		self.timeout = timeout;
+		self
+	}
+	// After resolving a role conflict
+	pub fn set_role(&mut self, role: IceRole) {
+		self.role = role;
+	}
+	pub fn state(&self) -> ConsentState {
+		self.state
+	}
+	// The number of checks in a row that went unanswered for a whole interval
+	pub fn failures(&self) -> usize {
+		self.failures
+	}
+	fn expires(&self) -> T {
+		self.last_response + self.timeout
+	}
+	pub fn poll_timeout(&self) -> Option<T> {
+		(self.state == ConsentState::Granted).then(|| self.next_send.min(self.expires()))
+	}
+	pub fn handle_timeout(&mut self, now: T) {
+		if self.state == ConsentState::Granted && now >= self.expires() {
+			self.state = ConsentState::Expired;
+			self.txids.clear();
+		}
+	}
+	// Writes the next check into buff if one is due
+	pub fn poll_transmit(&mut self, now: T, buff: &mut [u8]) -> Option<usize> {
+		self.handle_timeout(now);
+		if self.state != ConsentState::Granted || now < self.next_send {
+			return None;
+		}
+		let (txid, len) = self
+			.creds
+			.request(self.role, self.priority, false, buff)
+			.ok()?;
+		if !self.txids.is_empty() {
+			self.failures += 1;
+		}
+		if self.txids.len() >= MAX_OUTSTANDING {
+			self.txids.remove(0);
+		}
+		self.txids.push(txid);
+		// The transaction id is random, so it doubles as the source of the jitter: 0.8 to 1.2 times
+		// the interval
+		let r = u16::from_le_bytes([txid.0[0], txid.0[1]]) as f64 / u16::MAX as f64;
+		self.next_send = now + self.interval.mul_f64(0.8 + 0.4 * r);
+		Some(len)
+	}
+	// Handles a packet that arrived on the pair. Returns true if it was the (authenticated) success
+	// response to one of our checks, which refreshes consent.
+	pub fn handle(&mut self, packet: &[u8], now: T) -> bool {
+		if self.state != ConsentState::Granted {
+			return false;
+		}
+		let Ok(msg) = self.creds.verify_response(packet) else {
+			return false;
+		};
+		if !matches!(msg.typ, StunTyp::Res(StunMethod::Binding)) || !self.txids.contains(&msg.txid)
+		{
+			return false;
+		}
+		self.txids.clear();
+		self.failures = 0;
+		self.last_response = now;
+		true
+	}
+}
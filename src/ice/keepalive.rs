@@ -0,0 +1,71 @@
+// Keepalives for candidate pairs (RFC 8445 section 11): a pair that hasn't carried anything for Tr
+// gets a Binding indication, which keeps NAT bindings open without expecting a response.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use crate::attr::StunAttr;
+use crate::clock::Timestamp;
+use crate::{Stun, StunMethod};
+
+// Tr, which must not be less than 15 seconds
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// Pairs are identified by whatever the caller uses for them (a SocketAddr pair, an index...)
+#[derive(Debug, Clone)]
+pub struct Keepalives<K, T: Timestamp = Instant> {
+	interval: Duration,
+	last_sent: HashMap<K, T>,
+}
+impl<K: Hash + Eq + Clone, T: Timestamp> Default for Keepalives<K, T> {
+	fn default() -> Self {
+		Self::new(KEEPALIVE_INTERVAL)
+	}
+}
+impl<K: Hash + Eq + Clone, T: Timestamp> Keepalives<K, T> {
+	pub fn new(interval: Duration) -> Self {
+		Self {
+			interval,
+			last_sent: HashMap::new(),
+		}
+	}
+	pub fn insert(&mut self, pair: K, now: T) {
+		self.last_sent.insert(pair, now);
+	}
+	pub fn remove(&mut self, pair: &K) {
+		self.last_sent.remove(pair);
+	}
+	// Records that something (media, a check...) was sent on the pair, which pushes back its keepalive
+	pub fn sent(&mut self, pair: &K, now: T) {
+		if let Some(last) = self.last_sent.get_mut(pair) {
+			*last = (*last).max(now);
+		}
+	}
+	pub fn poll_timeout(&self) -> Option<T> {
+		self.last_sent
+			.values()
+			.min()
+			.map(|last| *last + self.interval)
+	}
+	// Writes a keepalive into buff for a pair that's due one, returning the pair. Call until it
+	// returns None.
+	pub fn poll_transmit(&mut self, now: T, buff: &mut [u8]) -> Option<(K, usize)> {
+		let (pair, last) = self
+			.last_sent
+			.iter_mut()
+			.find(|(_, last)| **last + self.interval <= now)?;
+		let attrs = [StunAttr::Fingerprint];
+		let len = Stun::try_ind(StunMethod::Binding, &attrs)
+			.ok()?
+			.encode(buff)
+			.ok()?;
+		*last = now;
+		Some((pair.clone(), len))
+	}
+	pub fn len(&self) -> usize {
+		self.last_sent.len()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.last_sent.is_empty()
+	}
+}
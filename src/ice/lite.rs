@@ -0,0 +1,93 @@
+// The responding half of an ICE-lite endpoint (RFC 8445 section 2.5), e.g. an SFU with a public
+// address. A lite agent never sends checks: it answers the full agent's checks and learns which
+// address was nominated from USE-CANDIDATE. Lite agents are always controlled, so the roles in the
+// checks aren't compared.
+use std::net::SocketAddr;
+
+use super::{username, ConnCheck, ConnCheckErr};
+use crate::attr::{Integrity, StunAttr};
+use crate::auth::{ShortTermKey, StunAuthErr};
+use crate::{Stun, StunMethod, StunTyp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Answered {
+	// The response's length in the buffer
+	pub len: usize,
+	// Whether this check nominated its source address
+	pub nominated: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct IceLite {
+	local_ufrag: String,
+	local_key: ShortTermKey,
+	remote_ufrag: Option<String>,
+	nominated: Option<SocketAddr>,
+}
+impl IceLite {
+	pub fn new(local_ufrag: &str, local_pwd: &str) -> Self {
+		Self {
+			local_ufrag: local_ufrag.to_string(),
+			local_key: ShortTermKey::new(local_pwd),
+			remote_ufrag: None,
+			nominated: None,
+		}
+	}
+	// Once the remote ufrag is known from signaling, checks must carry it
+	pub fn set_remote_ufrag(&mut self, ufrag: &str) {
+		self.remote_ufrag = Some(ufrag.to_string());
+	}
+	// The most recently nominated address
+	pub fn nominated(&self) -> Option<SocketAddr> {
+		self.nominated
+	}
+	fn username_ok(&self, username: &str) -> bool {
+		let Ok((local, remote)) = username::split(username) else {
+			return false;
+		};
+		local == self.local_ufrag && self.remote_ufrag.as_deref().is_none_or(|r| r == remote)
+	}
+	// Answers a check that arrived from `from`, writing the response into buff. Returns None for
+	// anything that isn't a Binding request.
+	pub fn handle(&mut self, packet: &[u8], from: SocketAddr, buff: &mut [u8]) -> Option<Answered> {
+		let msg = Stun::decode(packet).ok()?;
+		if !matches!(msg.typ, StunTyp::Req(StunMethod::Binding)) {
+			return None;
+		}
+		let mut reject = |code| {
+			let attrs = [StunAttr::error_code(code), StunAttr::Fingerprint];
+			let len = msg.err(&attrs).encode(buff).ok()?;
+			Some(Answered {
+				len,
+				nominated: false,
+			})
+		};
+		// Missing credentials are a malformed request, only wrong ones are unauthorized (RFC 8489
+		// section 9.1.3)
+		let flat = msg.flat();
+		let signed = flat.integrity.is_some() || flat.integrity_sha256.is_some();
+		if flat.username.is_none() || !signed {
+			return reject(400);
+		}
+		let check = match ConnCheck::verify(packet, &self.local_key) {
+			Ok((_, check)) if self.username_ok(check.username) => check,
+			Ok(_) => return reject(401),
+			Err(ConnCheckErr::Missing(_)) => return reject(400),
+			Err(ConnCheckErr::Auth(StunAuthErr::Decode(_)) | ConnCheckErr::NotACheck) => {
+				return None
+			}
+			Err(ConnCheckErr::Auth(_)) => return reject(401),
+		};
+		let nominated = check.nominates();
+		if nominated {
+			self.nominated = Some(from);
+		}
+		let attrs = [
+			StunAttr::XMapped(from),
+			StunAttr::Integrity(Integrity::sign(&self.local_key)),
+			StunAttr::Fingerprint,
+		];
+		let len = msg.res(&attrs).encode(buff).ok()?;
+		Some(Answered { len, nominated })
+	}
+}
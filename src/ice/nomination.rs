@@ -0,0 +1,63 @@
+// Nomination (RFC 8445 section 8.1). The controlling agent nominates a pair with a check carrying
+// USE-CANDIDATE: with regular nomination it repeats a check that already succeeded, with aggressive
+// nomination (RFC 5245) every check carries it. On the controlled side a pair is nominated once
+// such a check arrived and our own check on the pair succeeded, in either order (section 7.3.1.5).
+use std::collections::HashSet;
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Nomination {
+	#[default]
+	Regular,
+	Aggressive,
+}
+
+// The controlled agent's view of the nominations. Pairs are identified by whatever the caller uses
+// for them.
+#[derive(Debug, Clone)]
+pub struct Nominations<K> {
+	succeeded: HashSet<K>,
+	requested: HashSet<K>,
+	nominated: Vec<K>,
+}
+impl<K> Default for Nominations<K> {
+	fn default() -> Self {
+		Self {
+			succeeded: HashSet::new(),
+			requested: HashSet::new(),
+			nominated: Vec::new(),
+		}
+	}
+}
+impl<K: Hash + Eq + Clone> Nominations<K> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	fn nominate(&mut self, pair: &K) -> Option<K> {
+		let newly = self.succeeded.contains(pair)
+			&& self.requested.contains(pair)
+			&& !self.nominated.contains(pair);
+		newly.then(|| {
+			self.nominated.push(pair.clone());
+			pair.clone()
+		})
+	}
+	// A verified check that nominates (ConnCheck::nominates) arrived on the pair. Returns the pair if
+	// it's now nominated.
+	pub fn requested(&mut self, pair: K) -> Option<K> {
+		self.requested.insert(pair.clone());
+		self.nominate(&pair)
+	}
+	// Our check on the pair succeeded. Returns the pair if it's now nominated.
+	pub fn succeeded(&mut self, pair: K) -> Option<K> {
+		self.succeeded.insert(pair.clone());
+		self.nominate(&pair)
+	}
+	pub fn is_nominated(&self, pair: &K) -> bool {
+		self.nominated.contains(pair)
+	}
+	// Nominated pairs, in the order they were nominated
+	pub fn nominated(&self) -> &[K] {
+		&self.nominated
+	}
+}
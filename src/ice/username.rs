@@ -0,0 +1,37 @@
+// The USERNAME of an ICE check: "receiver ufrag:sender ufrag". A ufrag is 4 to 256 ice-chars
+// (ALPHA / DIGIT / "+" / "/", RFC 8839 section 5.4).
+pub const MIN_UFRAG: usize = 4;
+pub const MAX_UFRAG: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsernameErr {
+	NoColon,
+	Length,
+	InvalidChar,
+}
+
+pub fn validate_ufrag(ufrag: &str) -> Result<(), UsernameErr> {
+	if !(MIN_UFRAG..=MAX_UFRAG).contains(&ufrag.len()) {
+		return Err(UsernameErr::Length);
+	}
+	if !ufrag
+		.bytes()
+		.all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+	{
+		return Err(UsernameErr::InvalidChar);
+	}
+	Ok(())
+}
+
+// The USERNAME for a check we send
+pub fn join(remote_ufrag: &str, local_ufrag: &str) -> String {
+	format!("{remote_ufrag}:{local_ufrag}")
+}
+
+// Splits the USERNAME of a check we received into (local ufrag, remote ufrag), validating both
+pub fn split(username: &str) -> Result<(&str, &str), UsernameErr> {
+	let (local, remote) = username.split_once(':').ok_or(UsernameErr::NoColon)?;
+	validate_ufrag(local)?;
+	validate_ufrag(remote)?;
+	Ok((local, remote))
+}
@@ -1,10 +1,46 @@
+#![allow(clippy::unusual_byte_groupings, clippy::identity_op, clippy::len_without_is_empty)]
+
+use std::net::SocketAddr;
+
 use attr::StunAttrDecodeErr;
 
 pub mod attr;
 pub mod attrs;
+pub mod auth;
+pub mod batch;
+pub mod builder;
+pub mod client;
+pub mod clock;
+pub mod demux;
+pub mod diff;
+#[cfg(feature = "dns")]
+pub mod dns;
+#[cfg(feature = "ice")]
+pub mod ice;
+pub mod methods;
+pub mod mux;
+#[cfg(feature = "alloc")]
+pub mod owned;
+pub mod pool;
+pub mod prelude;
+pub mod punch;
+pub mod record;
+pub mod relay;
+pub mod server;
+pub mod stream;
+pub mod survey;
+pub mod testing;
+pub mod transactions;
+pub mod transport;
+#[cfg(feature = "turn")]
+pub mod turn;
+mod xor;
 use attr::StunAttr;
 use attrs::flat::Flat;
+use attrs::index::Indexed;
 use attrs::{StunAttrs, StunAttrsIter};
+#[cfg(feature = "alloc")]
+use owned::StunOwned;
 
 #[derive(Debug, Clone)]
 pub enum StunDecodeErr {
@@ -12,18 +48,187 @@ pub enum StunDecodeErr {
 	TypeOutOfRange,
 	UnalignedLength,
 	BadMagic,
+	LengthMismatch,
+	TooLarge,
 	AttrErr(StunAttrDecodeErr),
 }
 
+#[derive(Debug, Clone)]
+pub enum StunEncodeErr {
+	TooLarge,
+	// Carries the length that the buffer needed to be
+	BufferTooSmall(usize),
+	// The message needed a fresh transaction id and there wasn't one
+	Random(RandomErr),
+}
+impl From<RandomErr> for StunEncodeErr {
+	fn from(value: RandomErr) -> Self {
+		Self::Random(value)
+	}
+}
+
+// The OS's random source failed, so there's no transaction id. Without the rand feature ids are
+// made from std's hasher seeds instead, which can't fail.
+#[derive(Debug, Clone, Copy)]
+pub struct RandomErr(#[cfg(feature = "rand")] pub getrandom::Error);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimit(pub usize);
+impl SizeLimit {
+	// The largest message that the 16bit length field can describe
+	pub const WIRE: Self = Self(20 + 0xFFFC);
+	// Recommended limits for when the path MTU is unknown (IPv6 minimum MTU / IPv4 576 minus headers)
+	pub const IPV6: Self = Self(1280);
+	pub const IPV4: Self = Self(548);
+}
+impl Default for SizeLimit {
+	fn default() -> Self {
+		Self::WIRE
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TxId(pub [u8; 12]);
+impl TxId {
+	// From the OS's secure random source (RFC 8489 section 6 asks for cryptographic randomness)
+	#[cfg(feature = "rand")]
+	pub fn random() -> Result<Self, getrandom::Error> {
+		let mut ret = [0u8; 12];
+		getrandom::getrandom(&mut ret)?;
+		Ok(Self(ret))
+	}
+}
+impl From<[u8; 12]> for TxId {
+	fn from(value: [u8; 12]) -> Self {
+		Self(value)
+	}
+}
+impl From<TxId> for [u8; 12] {
+	fn from(value: TxId) -> Self {
+		value.0
+	}
+}
+impl std::fmt::Display for TxId {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		for b in self.0 {
+			write!(f, "{b:02x}")?;
+		}
+		Ok(())
+	}
+}
+
+fn random_txid() -> Result<TxId, RandomErr> {
+	// Quietly falling back to weaker ids would hide that the secure ones asked for aren't available
+	#[cfg(feature = "rand")]
+	return TxId::random().map_err(RandomErr);
+	#[cfg(not(feature = "rand"))]
+	Ok(weak_txid())
+}
+
+// Not cryptographically secure, but unpredictable enough for transaction ids
+#[cfg(any(not(feature = "rand"), feature = "dns"))]
+fn weak_txid() -> TxId {
+	use std::collections::hash_map::RandomState;
+	use std::hash::{BuildHasher, Hasher};
+	use std::sync::atomic::{AtomicU64, Ordering};
+	static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+	let state = RandomState::new();
+	let mut ret = [0u8; 12];
+	for chunk in ret.chunks_mut(4) {
+		let mut hasher = state.build_hasher();
+		hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+		chunk.copy_from_slice(&hasher.finish().to_be_bytes()[..4]);
+	}
+	TxId(ret)
+}
+
+// Standard (padded) base64, for HTTP Basic credentials and TURN REST API passwords
+pub(crate) fn base64(input: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut ret = String::new();
+	for chunk in input.chunks(3) {
+		let n = chunk
+			.iter()
+			.enumerate()
+			.fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+		for i in 0..4 {
+			if i <= chunk.len() {
+				ret.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3F] as char);
+			} else {
+				ret.push('=');
+			}
+		}
+	}
+	ret
+}
+
+// The registered methods. Other holds everything else; converting from a u16 never produces Other
+// for a method that has a name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StunMethod {
+	Binding,
+	// RFC 3489 only
+	SharedSecret,
+	// RFC 8656:
+	Allocate,
+	Refresh,
+	Send,
+	Data,
+	CreatePermission,
+	ChannelBind,
+	// RFC 6062:
+	Connect,
+	ConnectionBind,
+	ConnectionAttempt,
+	Other(u16),
+}
+impl From<u16> for StunMethod {
+	fn from(value: u16) -> Self {
+		match value {
+			methods::BINDING => Self::Binding,
+			methods::SHARED_SECRET => Self::SharedSecret,
+			methods::ALLOCATE => Self::Allocate,
+			methods::REFRESH => Self::Refresh,
+			methods::SEND => Self::Send,
+			methods::DATA => Self::Data,
+			methods::CREATE_PERMISSION => Self::CreatePermission,
+			methods::CHANNEL_BIND => Self::ChannelBind,
+			methods::CONNECT => Self::Connect,
+			methods::CONNECTION_BIND => Self::ConnectionBind,
+			methods::CONNECTION_ATTEMPT => Self::ConnectionAttempt,
+			m => Self::Other(m),
+		}
+	}
+}
+impl From<StunMethod> for u16 {
+	fn from(value: StunMethod) -> Self {
+		match value {
+			StunMethod::Binding => methods::BINDING,
+			StunMethod::SharedSecret => methods::SHARED_SECRET,
+			StunMethod::Allocate => methods::ALLOCATE,
+			StunMethod::Refresh => methods::REFRESH,
+			StunMethod::Send => methods::SEND,
+			StunMethod::Data => methods::DATA,
+			StunMethod::CreatePermission => methods::CREATE_PERMISSION,
+			StunMethod::ChannelBind => methods::CHANNEL_BIND,
+			StunMethod::Connect => methods::CONNECT,
+			StunMethod::ConnectionBind => methods::CONNECTION_BIND,
+			StunMethod::ConnectionAttempt => methods::CONNECTION_ATTEMPT,
+			StunMethod::Other(m) => m,
+		}
+	}
+}
+
 #[derive(Debug, Clone)]
 pub enum StunTyp {
-	Req(u16),
-	Ind(u16),
-	Res(u16),
-	Err(u16),
+	Req(StunMethod),
+	Ind(StunMethod),
+	Res(StunMethod),
+	Err(StunMethod),
 }
 impl StunTyp {
-	pub fn method(&self) -> u16 {
+	pub fn method(&self) -> StunMethod {
 		match self {
 			Self::Req(m) => *m,
 			Self::Ind(m) => *m,
@@ -39,9 +244,11 @@ impl TryFrom<[u8; 2]> for StunTyp {
 		if value >= 0x4000 {
 			return Err(StunDecodeErr::TypeOutOfRange);
 		}
-		let method = ((value & 0b00_00000_0_000_0_1111) >> 0)
-			| ((value & 0b00_00000_0_111_0_0000) >> 1)
-			| ((value & 0b00_11111_0_000_0_0000) >> 2);
+		let method = StunMethod::from(
+			((value & 0b00_00000_0_000_0_1111) >> 0)
+				| ((value & 0b00_00000_0_111_0_0000) >> 1)
+				| ((value & 0b00_11111_0_000_0_0000) >> 2),
+		);
 		Ok(match value & 0b00_00000_1_000_1_0000 {
 			0b00_000000_0_000_0_0000 => Self::Req(method),
 			0b00_000000_0_000_1_0000 => Self::Ind(method),
@@ -59,6 +266,7 @@ impl From<&StunTyp> for [u8; 2] {
 			StunTyp::Res(m) => (0b00_000000_1_000_0_0000, m),
 			StunTyp::Err(m) => (0b00_000000_1_000_1_0000, m),
 		};
+		let method = u16::from(*method);
 		let ret = ((method & 0b00_00000_0_000_0_1111) << 0)
 			| ((method & 0b00_00000_0_111_0_0000) << 1)
 			| ((method & 0b00_11111_0_000_0_0000) << 2)
@@ -70,15 +278,91 @@ impl From<&StunTyp> for [u8; 2] {
 #[derive(Debug, Clone)]
 pub struct Stun<'i> {
 	pub typ: StunTyp,
-	pub txid: &'i [u8; 12],
+	pub txid: TxId,
 	pub attrs: StunAttrs<'i>,
 }
 impl<'i> Stun<'i> {
 	pub fn flat(&self) -> Flat<'i> {
 		Flat::from_iter(self)
 	}
-	pub fn len(&self) -> usize {
-		20 + self.attrs.length() as usize
+	pub fn len(&self) -> Result<usize, StunEncodeErr> {
+		Ok(20 + self.attrs.length()? as usize)
+	}
+	// Lookups that stop at the first match, for when building a Flat would be overkill. Like
+	// iterating, these ignore anything after the integrity / fingerprint attributes.
+	pub fn attr(&self, typ: u16) -> Option<StunAttr<'i>> {
+		self.into_iter().find(|a| a.typ() == typ)
+	}
+	pub fn mapped(&self) -> Option<SocketAddr> {
+		self.into_iter().find_map(|a| match a {
+			StunAttr::Mapped(v) => Some(v.into()),
+			_ => None,
+		})
+	}
+	pub fn xmapped(&self) -> Option<SocketAddr> {
+		self.into_iter().find_map(|a| match a {
+			StunAttr::XMapped(v) => Some(v),
+			_ => None,
+		})
+	}
+	pub fn username(&self) -> Option<&'i str> {
+		self.into_iter().find_map(|a| match a {
+			StunAttr::Username(v) => Some(v),
+			_ => None,
+		})
+	}
+	pub fn realm(&self) -> Option<&'i str> {
+		self.into_iter().find_map(|a| match a {
+			StunAttr::Realm(v) => Some(v),
+			_ => None,
+		})
+	}
+	pub fn nonce(&self) -> Option<&'i str> {
+		self.into_iter().find_map(|a| match a {
+			StunAttr::Nonce(v) => Some(v),
+			_ => None,
+		})
+	}
+	pub fn software(&self) -> Option<&'i str> {
+		self.into_iter().find_map(|a| match a {
+			StunAttr::Software(v) => Some(v),
+			_ => None,
+		})
+	}
+	pub fn error(&self) -> Option<attr::Error<'i>> {
+		self.into_iter().find_map(|a| match a {
+			StunAttr::Error(v) => Some(v),
+			_ => None,
+		})
+	}
+	pub fn alternate_server(&self) -> Option<SocketAddr> {
+		self.into_iter().find_map(|a| match a {
+			StunAttr::AlternateServer(v) => Some(v.into()),
+			_ => None,
+		})
+	}
+	// A new request / indication, with a fresh transaction id (from the OS's secure random source
+	// with the rand feature). With the rand feature these panic if the random source fails, which
+	// try_req / try_ind return as an error instead; without it they can't fail.
+	pub fn req(method: StunMethod, attrs: &'i [StunAttr<'i>]) -> Self {
+		Self::try_req(method, attrs).expect("the OS random source failed")
+	}
+	pub fn ind(method: StunMethod, attrs: &'i [StunAttr<'i>]) -> Self {
+		Self::try_ind(method, attrs).expect("the OS random source failed")
+	}
+	pub fn try_req(method: StunMethod, attrs: &'i [StunAttr<'i>]) -> Result<Self, RandomErr> {
+		Ok(Self {
+			typ: StunTyp::Req(method),
+			txid: random_txid()?,
+			attrs: attrs.into(),
+		})
+	}
+	pub fn try_ind(method: StunMethod, attrs: &'i [StunAttr<'i>]) -> Result<Self, RandomErr> {
+		Ok(Self {
+			typ: StunTyp::Ind(method),
+			txid: random_txid()?,
+			attrs: attrs.into(),
+		})
 	}
 	pub fn res(&self, attrs: &'i [StunAttr<'i>]) -> Self {
 		Self {
@@ -94,53 +378,150 @@ impl<'i> Stun<'i> {
 			attrs: attrs.into(),
 		}
 	}
+	// An error response carrying just ERROR-CODE with the standard reason phrase
+	#[cfg(feature = "alloc")]
+	pub fn err_code(&self, code: u16) -> StunOwned {
+		let mut ret = StunOwned::new(StunTyp::Err(self.typ.method()), self.txid);
+		ret.extend([StunAttr::error_code(code)]);
+		ret
+	}
 	pub fn decode(buff: &'i [u8]) -> Result<Self, StunDecodeErr> {
-		if buff.len() < 20 {
+		Self::decode_limit(buff, SizeLimit::default())
+	}
+	pub fn decode_indexed(buff: &'i [u8]) -> Result<Indexed<'i>, StunDecodeErr> {
+		Indexed::decode(buff)
+	}
+	// Decodes the message at the front of a stream, see stream::decode
+	pub fn decode_stream(buff: &'i [u8]) -> Result<stream::Streamed<'i>, StunDecodeErr> {
+		stream::decode(buff, SizeLimit::default())
+	}
+	pub fn decode_limit(buff: &'i [u8], limit: SizeLimit) -> Result<Self, StunDecodeErr> {
+		Self::decode_header(buff, limit, false)?.validate_attrs()
+	}
+	// RFC 3489 predates the magic cookie: accepts any value in bytes 4..8, which classic servers treat
+	// as the start of a 16 byte transaction id (see classic_txid)
+	pub fn decode_classic(buff: &'i [u8]) -> Result<Self, StunDecodeErr> {
+		Self::decode_header(buff, SizeLimit::default(), true)?.validate_attrs()
+	}
+	// The full RFC 3489 transaction id, for decoded messages
+	pub fn classic_txid(&self) -> Option<&'i [u8; 16]> {
+		match self.attrs {
+			StunAttrs::Parse {
+				header: [_, _, _, _, txid @ ..],
+				..
+			} => Some(txid),
+			StunAttrs::List(_) => None,
+		}
+	}
+	fn validate_attrs(self) -> Result<Self, StunDecodeErr> {
+		for res in &self.attrs {
+			if let Err(e) = res {
+				return Err(StunDecodeErr::AttrErr(e));
+			}
+		}
+		Ok(self)
+	}
+	// Checks the header, but leaves validating the attributes up to the caller
+	pub(crate) fn decode_header(
+		buff: &'i [u8],
+		limit: SizeLimit,
+		classic: bool,
+	) -> Result<Self, StunDecodeErr> {
+		let Some((header, rest)) = buff.split_first_chunk::<20>() else {
 			return Err(StunDecodeErr::PacketTooSmall);
+		};
+		if buff.len() > limit.0 {
+			return Err(StunDecodeErr::TooLarge);
 		}
-		let typ = StunTyp::try_from(<[u8; 2]>::try_from(&buff[0..][..2]).unwrap())?;
+		let [t0, t1, l0, l1, m0, m1, m2, m3, ..] = *header;
+		let typ = StunTyp::try_from([t0, t1])?;
 
-		let length = u16::from_be_bytes((&buff[2..][..2]).try_into().unwrap());
+		let length = u16::from_be_bytes([l0, l1]);
 		if length % 4 != 0 {
 			return Err(StunDecodeErr::UnalignedLength);
 		}
-		if (20 + length as usize) < buff.len() {
+		if (20 + length as usize) > limit.0 {
+			return Err(StunDecodeErr::TooLarge);
+		}
+		if (length as usize) > rest.len() {
 			return Err(StunDecodeErr::PacketTooSmall);
 		}
+		if (length as usize) < rest.len() {
+			return Err(StunDecodeErr::LengthMismatch);
+		}
 
-		let magic = u32::from_be_bytes((&buff[4..][..4]).try_into().unwrap());
-		if magic != 0x2112A442 {
+		let magic = u32::from_be_bytes([m0, m1, m2, m3]);
+		if magic != 0x2112A442 && !classic {
 			return Err(StunDecodeErr::BadMagic);
 		}
 
-		let txid = (&buff[8..][..12]).try_into().unwrap();
-
-		let attrs = StunAttrs::Parse {
-			buff: &buff[20..][..length as usize],
-			header: (&buff[0..][..20]).try_into().unwrap(),
+		let Some(&txid) = header.last_chunk::<12>() else {
+			return Err(StunDecodeErr::PacketTooSmall);
 		};
-		for res in &attrs {
-			if let Err(e) = res {
-				return Err(StunDecodeErr::AttrErr(e));
-			}
-		}
+		let txid = TxId(txid);
+
+		let attrs = StunAttrs::Parse { buff: rest, header };
 
 		Ok(Self { typ, txid, attrs })
 	}
-	pub fn encode(&self, buff: &mut [u8]) -> Option<usize> {
-		let length = self.attrs.length();
+	pub fn encode(&self, buff: &mut [u8]) -> Result<usize, StunEncodeErr> {
+		self.encode_limit(buff, SizeLimit::default())
+	}
+	pub fn encode_limit(&self, buff: &mut [u8], limit: SizeLimit) -> Result<usize, StunEncodeErr> {
+		let length = self.attrs.length()?;
 		let len = 20 + length as usize;
-		if buff.len() < len {
-			return None;
+		if len > limit.0 {
+			return Err(StunEncodeErr::TooLarge);
 		}
-		buff[0..][..2].copy_from_slice(&<[u8; 2]>::from(&self.typ));
-		buff[2..][..2].copy_from_slice(&length.to_be_bytes());
-		buff[4..][..4].copy_from_slice(&0x2112A442u32.to_be_bytes());
-		buff[8..][..12].copy_from_slice(self.txid);
-		let (header, buff) = buff.split_at_mut(20);
-		let header = <&[u8; 20]>::try_from(&*header).unwrap();
-		self.attrs.encode(buff, header);
-		Some(len)
+		let Some((header, buff)) = buff
+			.get_mut(..len)
+			.and_then(|b| b.split_first_chunk_mut::<20>())
+		else {
+			return Err(StunEncodeErr::BufferTooSmall(len));
+		};
+		let [t0, t1] = <[u8; 2]>::from(&self.typ);
+		let [l0, l1] = length.to_be_bytes();
+		let [m0, m1, m2, m3] = 0x2112A442u32.to_be_bytes();
+		let [x0, x1, x2, x3, x4, x5, x6, x7, x8, x9, x10, x11] = self.txid.0;
+		*header = [
+			t0, t1, l0, l1, m0, m1, m2, m3, x0, x1, x2, x3, x4, x5, x6, x7, x8, x9, x10, x11,
+		];
+		self.attrs.encode(buff, header)?;
+		Ok(len)
+	}
+	// Writes the message exactly as it was received (including a classic message's transaction id
+	// and any padding bytes) when it was decoded, otherwise the same as encode. Only the decoded
+	// message itself is exact: attributes taken out of it and encoded again get zero padding,
+	// unless they're unknown ones.
+	pub fn copy_to(&self, buff: &mut [u8]) -> Result<usize, StunEncodeErr> {
+		let StunAttrs::Parse { buff: attrs, header } = self.attrs else {
+			return self.encode(buff);
+		};
+		let len = 20 + attrs.len();
+		let Some((out_header, out_attrs)) = buff
+			.get_mut(..len)
+			.and_then(|b| b.split_first_chunk_mut::<20>())
+		else {
+			return Err(StunEncodeErr::BufferTooSmall(len));
+		};
+		*out_header = *header;
+		out_attrs.copy_from_slice(attrs);
+		Ok(len)
+	}
+	#[cfg(feature = "std")]
+	pub fn encode_vec(&self) -> Result<Vec<u8>, StunEncodeErr> {
+		let mut ret = vec![0; self.len()?];
+		self.encode(&mut ret)?;
+		Ok(ret)
+	}
+	// Writes the whole message, returning its length
+	#[cfg(feature = "std")]
+	pub fn write_to<W: std::io::Write>(&self, mut w: W) -> std::io::Result<usize> {
+		let buff = self.encode_vec().map_err(|e| {
+			std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("{e:?}"))
+		})?;
+		w.write_all(&buff)?;
+		Ok(buff.len())
 	}
 }
 
@@ -150,6 +531,7 @@ impl<'i, 'a> IntoIterator for &'a Stun<'i> {
 	fn into_iter(self) -> Self::IntoIter {
 		StunIter {
 			integrity: false,
+			integrity_sha256: false,
 			fingerprint: false,
 			attrs: self.attrs.into_iter(),
 		}
@@ -157,16 +539,19 @@ impl<'i, 'a> IntoIterator for &'a Stun<'i> {
 }
 pub struct StunIter<'i, 'a> {
 	integrity: bool,
+	integrity_sha256: bool,
 	fingerprint: bool,
 	attrs: StunAttrsIter<'i, 'a>,
 }
 impl<'i, 'a> Iterator for StunIter<'i, 'a> {
 	type Item = StunAttr<'i>;
 	fn next(&mut self) -> Option<Self::Item> {
-		let attr = self.attrs.next()?.unwrap();
+		let attr = self.attrs.next()?.ok()?;
 		match attr {
 			_ if self.fingerprint => return None,
 			StunAttr::Fingerprint => self.fingerprint = true,
+			_ if self.integrity_sha256 => return None,
+			StunAttr::IntegritySha256(_) => self.integrity_sha256 = true,
 			_ if self.integrity => return None,
 			StunAttr::Integrity(_) => self.integrity = true,
 			_ => {}
@@ -0,0 +1,17 @@
+// Method numbers from the IANA STUN Methods registry. StunMethod names the same values.
+pub const BINDING: u16 = 0x001;
+// RFC 3489 only
+pub const SHARED_SECRET: u16 = 0x002;
+
+// RFC 8656:
+pub const ALLOCATE: u16 = 0x003;
+pub const REFRESH: u16 = 0x004;
+pub const SEND: u16 = 0x006;
+pub const DATA: u16 = 0x007;
+pub const CREATE_PERMISSION: u16 = 0x008;
+pub const CHANNEL_BIND: u16 = 0x009;
+
+// RFC 6062:
+pub const CONNECT: u16 = 0x00A;
+pub const CONNECTION_BIND: u16 = 0x00B;
+pub const CONNECTION_ATTEMPT: u16 = 0x00C;
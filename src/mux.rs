@@ -0,0 +1,99 @@
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+
+use crate::demux::{classify, PacketClass};
+use crate::record::{Direction, Recording};
+use crate::Stun;
+
+fn is_stun(packet: &[u8]) -> bool {
+	classify(packet) == PacketClass::Stun
+}
+
+#[derive(Debug)]
+pub enum Received<'b> {
+	Stun(Stun<'b>, SocketAddr),
+	// DTLS, RTP/RTCP, or anything else sharing the port (including STUN-looking packets that failed to decode)
+	Other(&'b [u8], SocketAddr),
+}
+fn demux<'b>(packet: &'b [u8], from: SocketAddr, recording: &Recording) -> Received<'b> {
+	if is_stun(packet) {
+		recording.record(Direction::Inbound, from, packet);
+		if let Ok(msg) = Stun::decode(packet) {
+			return Received::Stun(msg, from);
+		}
+	}
+	Received::Other(packet, from)
+}
+
+// A UDP socket shared between STUN and the application's own protocol
+#[derive(Debug)]
+pub struct MuxSocket {
+	sock: UdpSocket,
+	recording: Recording,
+}
+impl MuxSocket {
+	pub fn new(sock: UdpSocket) -> Self {
+		Self {
+			sock,
+			recording: Recording::default(),
+		}
+	}
+	// Only STUN messages are recorded
+	pub fn with_recording(mut self, recording: Recording) -> Self {
+		self.recording = recording;
+		self
+	}
+	pub fn socket(&self) -> &UdpSocket {
+		&self.sock
+	}
+	pub fn into_inner(self) -> UdpSocket {
+		self.sock
+	}
+	pub fn recv_from<'b>(&self, buff: &'b mut [u8]) -> io::Result<Received<'b>> {
+		let (len, from) = self.sock.recv_from(buff)?;
+		Ok(demux(&buff[..len], from, &self.recording))
+	}
+	pub fn send_to(&self, packet: &[u8], to: SocketAddr) -> io::Result<usize> {
+		if is_stun(packet) {
+			self.recording.record(Direction::Outbound, to, packet);
+		}
+		self.sock.send_to(packet, to)
+	}
+}
+
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+pub struct TokioMuxSocket {
+	sock: tokio::net::UdpSocket,
+	recording: Recording,
+}
+#[cfg(feature = "tokio")]
+impl TokioMuxSocket {
+	pub fn new(sock: tokio::net::UdpSocket) -> Self {
+		Self {
+			sock,
+			recording: Recording::default(),
+		}
+	}
+	// Only STUN messages are recorded
+	pub fn with_recording(mut self, recording: Recording) -> Self {
+		self.recording = recording;
+		self
+	}
+	pub fn socket(&self) -> &tokio::net::UdpSocket {
+		&self.sock
+	}
+	pub fn into_inner(self) -> tokio::net::UdpSocket {
+		self.sock
+	}
+	pub async fn recv_from<'b>(&self, buff: &'b mut [u8]) -> io::Result<Received<'b>> {
+		let (len, from) = self.sock.recv_from(buff).await?;
+		Ok(demux(&buff[..len], from, &self.recording))
+	}
+	pub async fn send_to(&self, packet: &[u8], to: SocketAddr) -> io::Result<usize> {
+		if is_stun(packet) {
+			self.recording.record(Direction::Outbound, to, packet);
+		}
+		self.sock.send_to(packet, to).await
+	}
+}
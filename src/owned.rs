@@ -0,0 +1,119 @@
+// An owned copy of a message, for when it has to outlive the packet buffer: queued, kept in a map,
+// or sent to another thread. Attributes are kept encoded, so converting back to a Stun is free.
+use crate::attr::{AttrContext, StunAttr};
+use crate::attrs::StunAttrs;
+use crate::{Stun, StunDecodeErr, StunEncodeErr, StunTyp, TxId};
+
+#[derive(Debug, Clone)]
+pub struct StunOwned {
+	pub typ: StunTyp,
+	// The message's header, which the xor'd and integrity attributes depend on
+	header: [u8; 20],
+	attrs: Vec<u8>,
+	// The first error hit while extending, reported by finish
+	err: Option<StunEncodeErr>,
+}
+impl StunOwned {
+	pub fn new(typ: StunTyp, txid: TxId) -> Self {
+		let [t0, t1] = <[u8; 2]>::from(&typ);
+		let [m0, m1, m2, m3] = 0x2112A442u32.to_be_bytes();
+		let [x0, x1, x2, x3, x4, x5, x6, x7, x8, x9, x10, x11] = txid.0;
+		Self {
+			typ,
+			header: [
+				t0, t1, 0, 0, m0, m1, m2, m3, x0, x1, x2, x3, x4, x5, x6, x7, x8, x9, x10, x11,
+			],
+			attrs: Vec::new(),
+			err: None,
+		}
+	}
+	// Keeps the attributes byte for byte (including any that failed to parse)
+	pub fn decode(buff: &[u8]) -> Result<Self, StunDecodeErr> {
+		let msg = Stun::decode(buff)?;
+		Ok(Self::try_from(&msg)
+			.unwrap_or_else(|_| unreachable!("decoded messages are never lists")))
+	}
+	pub fn txid(&self) -> TxId {
+		let [_, _, _, _, _, _, _, _, txid @ ..] = self.header;
+		TxId(txid)
+	}
+	pub fn as_stun(&self) -> Stun<'_> {
+		Stun {
+			typ: self.typ.clone(),
+			txid: self.txid(),
+			attrs: StunAttrs::Parse {
+				buff: &self.attrs,
+				header: &self.header,
+			},
+		}
+	}
+	// Encodes attr after the existing attributes. Integrity and fingerprint attributes cover
+	// everything pushed before them, just like when encoding a list.
+	pub fn push(&mut self, attr: StunAttr<'_>) -> Result<(), StunEncodeErr> {
+		let attr_len = attr.len()?;
+		let padded_len = attr.padded_len()? as usize;
+		let length = self.attrs.len() + padded_len;
+		if length > 0xFFFC {
+			return Err(StunEncodeErr::TooLarge);
+		}
+		let start = self.attrs.len();
+		self.attrs.resize(length, 0);
+		let (attrs_prefix, to_write) = self.attrs.split_at_mut(start);
+		let ctx = AttrContext {
+			header: &self.header,
+			attrs_prefix,
+			attr_len,
+			zero_xor_bytes: false,
+			padding: &[],
+		};
+		attr.encode(to_write, ctx)?;
+		self.header[2..4].copy_from_slice(&(length as u16).to_be_bytes());
+		Ok(())
+	}
+	// Returns the message, or the first error hit while extending
+	pub fn finish(self) -> Result<Self, StunEncodeErr> {
+		match self.err {
+			Some(e) => Err(e),
+			None => Ok(self),
+		}
+	}
+}
+impl TryFrom<&Stun<'_>> for StunOwned {
+	type Error = StunEncodeErr;
+	// Keeps a decoded message's attributes byte for byte (including any that failed to parse). A list
+	// of attributes is encoded, which fails if they don't fit in a message.
+	fn try_from(value: &Stun<'_>) -> Result<Self, Self::Error> {
+		match value.attrs {
+			StunAttrs::Parse { buff, header } => Ok(Self {
+				typ: value.typ.clone(),
+				header: *header,
+				attrs: buff.to_vec(),
+				err: None,
+			}),
+			StunAttrs::List(l) => {
+				let mut ret = Self::new(value.typ.clone(), value.txid);
+				ret.extend(l.iter().cloned());
+				ret.finish()
+			}
+		}
+	}
+}
+impl<'a> From<&'a StunOwned> for Stun<'a> {
+	fn from(value: &'a StunOwned) -> Self {
+		value.as_stun()
+	}
+}
+impl<'i> Extend<StunAttr<'i>> for StunOwned {
+	// Stops at the first attribute that can't be encoded
+	fn extend<T: IntoIterator<Item = StunAttr<'i>>>(&mut self, iter: T) {
+		if self.err.is_some() {
+			return;
+		}
+		for attr in iter {
+			if let Err(e) = self.push(attr) {
+				self.err = Some(e);
+				return;
+			}
+		}
+	}
+}
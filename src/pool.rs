@@ -0,0 +1,69 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// Comfortably above any path MTU a STUN message will be sent over
+pub const BUFF_SIZE: usize = 2048;
+
+type Buff = Box<[u8; BUFF_SIZE]>;
+
+// A pool of packet buffers, split into shards so that threads rarely contend on the same lock
+#[derive(Debug)]
+pub struct BufferPool {
+	shards: Box<[Mutex<Vec<Buff>>]>,
+	per_shard: usize,
+	next: AtomicUsize,
+}
+impl BufferPool {
+	// Keeps at most `per_shard` idle buffers in each shard; extra buffers are freed when returned
+	pub fn new(shards: usize, per_shard: usize) -> Self {
+		Self {
+			shards: (0..shards.max(1)).map(|_| Mutex::new(Vec::new())).collect(),
+			per_shard,
+			next: AtomicUsize::new(0),
+		}
+	}
+	pub fn get(&self) -> PooledBuffer<'_> {
+		let shard = self.next.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+		let buff = self.shards[shard].lock().unwrap().pop();
+		PooledBuffer {
+			buff: Some(buff.unwrap_or_else(|| Box::new([0; BUFF_SIZE]))),
+			pool: self,
+			shard,
+		}
+	}
+}
+impl Default for BufferPool {
+	fn default() -> Self {
+		let shards = std::thread::available_parallelism().map_or(4, |n| n.get());
+		Self::new(shards, 64)
+	}
+}
+
+// Returns itself to the pool when dropped. The contents are not cleared.
+#[derive(Debug)]
+pub struct PooledBuffer<'p> {
+	buff: Option<Buff>,
+	pool: &'p BufferPool,
+	shard: usize,
+}
+impl Deref for PooledBuffer<'_> {
+	type Target = [u8];
+	fn deref(&self) -> &[u8] {
+		self.buff.as_deref().unwrap()
+	}
+}
+impl DerefMut for PooledBuffer<'_> {
+	fn deref_mut(&mut self) -> &mut [u8] {
+		self.buff.as_deref_mut().unwrap()
+	}
+}
+impl Drop for PooledBuffer<'_> {
+	fn drop(&mut self) {
+		let Some(buff) = self.buff.take() else { return };
+		let mut shard = self.pool.shards[self.shard].lock().unwrap();
+		if shard.len() < self.pool.per_shard {
+			shard.push(buff);
+		}
+	}
+}
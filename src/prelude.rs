@@ -0,0 +1,8 @@
+pub use crate::attr::{Error, Integrity, IntegritySha256, StunAttr, StunAttrDecodeErr};
+pub use crate::attrs::flat::Flat;
+pub use crate::attrs::list::StunAttrsMut;
+pub use crate::auth::{IntegrityKey, LongTermKey, ShortTermKey};
+pub use crate::builder::StunBuilder;
+#[cfg(feature = "alloc")]
+pub use crate::owned::StunOwned;
+pub use crate::{SizeLimit, Stun, StunDecodeErr, StunEncodeErr, StunMethod, StunTyp, TxId};
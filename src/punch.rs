@@ -0,0 +1,110 @@
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use crate::attr::StunAttr;
+use crate::clock::Timestamp;
+use crate::record::{Direction, Recording};
+use crate::{Stun, StunMethod, StunTyp, TxId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PunchState {
+	Punching,
+	// Our binding request got a response, so the path is open in both directions
+	Connected { mapped: SocketAddr },
+	Failed,
+}
+
+// A single-pair connectivity check: keep sending Binding requests to the peer's reflexive address
+// while answering the Binding requests they send us, until one of ours is answered.
+#[derive(Debug, Clone)]
+pub struct Punch<T: Timestamp = Instant> {
+	peer: SocketAddr,
+	state: PunchState,
+	interval: Duration,
+	attempts: usize,
+	max_attempts: usize,
+	next_send: T,
+	txids: Vec<TxId>,
+	answered: bool,
+	recording: Recording,
+}
+impl<T: Timestamp> Punch<T> {
+	pub fn new(peer: SocketAddr, now: T) -> Self {
+		Self {
+			peer,
+			state: PunchState::Punching,
+			interval: Duration::from_millis(100),
+			attempts: 0,
+			max_attempts: 50,
+			next_send: now,
+			txids: Vec::new(),
+			answered: false,
+			recording: Recording::default(),
+		}
+	}
+	pub fn with_schedule(mut self, interval: Duration, max_attempts: usize) -> Self {
+		self.interval = interval;
+		self.max_attempts = max_attempts;
+		self
+	}
+	pub fn with_recording(mut self, recording: Recording) -> Self {
+		self.recording = recording;
+		self
+	}
+	pub fn peer(&self) -> SocketAddr {
+		self.peer
+	}
+	pub fn state(&self) -> PunchState {
+		self.state
+	}
+	// Whether we've answered at least one of the peer's requests (their packets reach us)
+	pub fn answered(&self) -> bool {
+		self.answered
+	}
+	pub fn poll_timeout(&self) -> Option<T> {
+		(self.state == PunchState::Punching).then_some(self.next_send)
+	}
+	// Writes the next Binding request into buff if one is due
+	pub fn poll_transmit(&mut self, now: T, buff: &mut [u8]) -> Option<usize> {
+		if self.state != PunchState::Punching || now < self.next_send {
+			return None;
+		}
+		if self.attempts >= self.max_attempts {
+			self.state = PunchState::Failed;
+			return None;
+		}
+		let attrs = [StunAttr::Fingerprint];
+		let req = Stun::try_req(StunMethod::Binding, &attrs).ok()?;
+		let len = req.encode(buff).ok()?;
+		self.recording.record(Direction::Outbound, self.peer, &buff[..len]);
+		self.txids.push(req.txid);
+		self.attempts += 1;
+		self.next_send = now + self.interval;
+		Some(len)
+	}
+	// Handles a packet received from `from`. If the packet was a Binding request from the peer then
+	// the response is written into buff and its length returned.
+	pub fn handle(&mut self, packet: &[u8], from: SocketAddr, buff: &mut [u8]) -> Option<usize> {
+		if from != self.peer {
+			return None;
+		}
+		self.recording.record(Direction::Inbound, from, packet);
+		let msg = Stun::decode(packet).ok()?;
+		match msg.typ {
+			StunTyp::Req(StunMethod::Binding) => {
+				self.answered = true;
+				let attrs = [StunAttr::XMapped(from), StunAttr::Fingerprint];
+				let len = msg.res(&attrs).encode(buff).ok()?;
+				self.recording.record(Direction::Outbound, from, &buff[..len]);
+				Some(len)
+			}
+			StunTyp::Res(StunMethod::Binding) if self.txids.contains(&msg.txid) => {
+				if let (PunchState::Punching, Some(mapped)) = (self.state, msg.flat().xmapped) {
+					self.state = PunchState::Connected { mapped };
+				}
+				None
+			}
+			_ => None,
+		}
+	}
+}
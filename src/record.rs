@@ -0,0 +1,124 @@
+// Hooks for recording every message a component sends or receives, e.g. to capture a session while
+// chasing an interop bug and replay it in a test.
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use crate::{Stun, StunTyp, TxId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+	Inbound,
+	Outbound,
+}
+
+#[derive(Debug, Clone)]
+pub struct Summary {
+	pub typ: StunTyp,
+	pub txid: TxId,
+	pub attrs: Vec<u16>,
+}
+impl From<&Stun<'_>> for Summary {
+	fn from(msg: &Stun<'_>) -> Self {
+		Self {
+			typ: msg.typ.clone(),
+			txid: msg.txid,
+			attrs: msg
+				.attrs
+				.into_iter()
+				.filter_map(|a| a.ok())
+				.map(|a| a.typ())
+				.collect(),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Record<'a> {
+	pub direction: Direction,
+	pub peer: SocketAddr,
+	pub bytes: &'a [u8],
+	// None if the bytes didn't decode as a STUN message
+	pub summary: Option<Summary>,
+}
+
+pub trait Recorder: Send + Sync {
+	fn record(&self, record: &Record<'_>);
+}
+
+// The recorder attached to a component (if any)
+#[derive(Clone, Default)]
+pub struct Recording(Option<Arc<dyn Recorder>>);
+impl Recording {
+	pub fn new<R: Recorder + 'static>(recorder: R) -> Self {
+		Self(Some(Arc::new(recorder)))
+	}
+	pub fn shared(recorder: Arc<dyn Recorder>) -> Self {
+		Self(Some(recorder))
+	}
+	pub(crate) fn record(&self, direction: Direction, peer: SocketAddr, bytes: &[u8]) {
+		let Some(recorder) = &self.0 else { return };
+		let summary = Stun::decode(bytes).ok().as_ref().map(Summary::from);
+		recorder.record(&Record {
+			direction,
+			peer,
+			bytes,
+			summary,
+		});
+	}
+}
+impl fmt::Debug for Recording {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self.0 {
+			Some(_) => "Recording(..)",
+			None => "Recording(None)",
+		})
+	}
+}
+
+// Writes one line per message: direction (< inbound, > outbound), peer, and the bytes as hex
+#[derive(Debug)]
+pub struct WriteRecorder<W>(Mutex<W>);
+impl<W: Write + Send> WriteRecorder<W> {
+	pub fn new(w: W) -> Self {
+		Self(Mutex::new(w))
+	}
+	pub fn into_inner(self) -> W {
+		self.0.into_inner().unwrap()
+	}
+}
+impl<W: Write + Send> Recorder for WriteRecorder<W> {
+	fn record(&self, record: &Record<'_>) {
+		let dir = match record.direction {
+			Direction::Inbound => '<',
+			Direction::Outbound => '>',
+		};
+		let hex: String = record.bytes.iter().map(|b| format!("{b:02x}")).collect();
+		let mut w = self.0.lock().unwrap();
+		let _ = writeln!(w, "{dir} {} {hex}", record.peer);
+	}
+}
+
+// Reads back the lines written by WriteRecorder
+pub fn replay<R: BufRead>(
+	r: R,
+) -> impl Iterator<Item = io::Result<(Direction, SocketAddr, Vec<u8>)>> {
+	let bad = || io::Error::new(io::ErrorKind::InvalidData, "malformed recording");
+	r.lines().map(move |line| {
+		let line = line?;
+		let mut parts = line.split(' ');
+		let direction = match parts.next() {
+			Some("<") => Direction::Inbound,
+			Some(">") => Direction::Outbound,
+			_ => return Err(bad()),
+		};
+		let peer = parts.next().and_then(|p| p.parse().ok()).ok_or_else(bad)?;
+		let hex = parts.next().ok_or_else(bad)?;
+		let bytes = (0..hex.len() / 2)
+			.map(|i| u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok())
+			.collect::<Option<_>>()
+			.ok_or_else(bad)?;
+		Ok((direction, peer, bytes))
+	})
+}
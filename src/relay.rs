@@ -0,0 +1,192 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::attr::{AttrContext, Integrity, IntegritySha256, StunAttr, ZeroXor};
+use crate::attrs::{parse_at, StunAttrs};
+use crate::auth::IntegrityKey;
+use crate::{Stun, StunDecodeErr, StunEncodeErr};
+
+// Maps IPv4 addresses into (and out of) an IPv6 /96 prefix, the way NAT64 gateways do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nat64 {
+	pub prefix: Ipv6Addr,
+}
+impl Nat64 {
+	// RFC 6052 well-known prefix: 64:ff9b::/96
+	pub const WELL_KNOWN: Self = Self {
+		prefix: Ipv6Addr::new(0x64, 0xff9b, 0, 0, 0, 0, 0, 0),
+	};
+	pub fn to_v6(&self, addr: SocketAddr) -> SocketAddr {
+		let IpAddr::V4(ip) = addr.ip() else {
+			return addr;
+		};
+		let mut octs = self.prefix.octets();
+		octs[12..].copy_from_slice(&ip.octets());
+		SocketAddr::new(Ipv6Addr::from(octs).into(), addr.port())
+	}
+	// Returns None if the address isn't an IPv6 address within the prefix
+	pub fn to_v4(&self, addr: SocketAddr) -> Option<SocketAddr> {
+		let IpAddr::V6(ip) = addr.ip() else {
+			return Some(addr);
+		};
+		let octs = ip.octets();
+		if octs[..12] != self.prefix.octets()[..12] {
+			return None;
+		}
+		let v4 = Ipv4Addr::new(octs[12], octs[13], octs[14], octs[15]);
+		Some(SocketAddr::new(v4.into(), addr.port()))
+	}
+}
+
+// Re-encodes msg into buff with every MAPPED / XOR-MAPPED / XOR-PEER / XOR-RELAYED address passed
+// through f. Any MESSAGE-INTEGRITY(-SHA256) is recomputed using key (or dropped if there's no key)
+// and the FINGERPRINT is recomputed if the original message had one.
+pub fn translate<F: FnMut(SocketAddr) -> SocketAddr>(
+	msg: &Stun<'_>,
+	key: Option<&dyn IntegrityKey>,
+	mut f: F,
+	buff: &mut [u8],
+) -> Result<usize, StunEncodeErr> {
+	let mut attrs = Vec::new();
+	let mut integrity = false;
+	let mut integrity_sha256 = false;
+	let mut fingerprint = false;
+	for attr in msg {
+		attrs.push(match attr {
+			StunAttr::Mapped(a) => StunAttr::Mapped(f(a.into()).into()),
+			StunAttr::XMapped(a) => StunAttr::XMapped(f(a)),
+			#[cfg(feature = "turn")]
+			StunAttr::XPeer(a) => StunAttr::XPeer(f(a)),
+			#[cfg(feature = "turn")]
+			StunAttr::XRelayed(a) => StunAttr::XRelayed(f(a)),
+			StunAttr::Integrity(_) => {
+				integrity = true;
+				continue;
+			}
+			StunAttr::IntegritySha256(_) => {
+				integrity_sha256 = true;
+				continue;
+			}
+			StunAttr::Fingerprint => {
+				fingerprint = true;
+				continue;
+			}
+			a => a,
+		});
+	}
+	if let (true, Some(key)) = (integrity, key) {
+		attrs.push(StunAttr::Integrity(Integrity::Set {
+			key_data: key.as_bytes(),
+		}));
+	}
+	if let (true, Some(key)) = (integrity_sha256, key) {
+		attrs.push(StunAttr::IntegritySha256(IntegritySha256::Set {
+			key_data: key.as_bytes(),
+		}));
+	}
+	if fingerprint {
+		attrs.push(StunAttr::Fingerprint);
+	}
+	Stun {
+		typ: msg.typ.clone(),
+		txid: msg.txid,
+		attrs: attrs[..].into(),
+	}
+	.encode(buff)
+}
+
+// Re-encodes msg into buff with fresh signatures: any MESSAGE-INTEGRITY(-SHA256) and FINGERPRINT are
+// dropped, then a MESSAGE-INTEGRITY computed with key and a FINGERPRINT are appended.
+pub fn resign(
+	msg: &Stun<'_>,
+	key: &dyn IntegrityKey,
+	buff: &mut [u8],
+) -> Result<usize, StunEncodeErr> {
+	let mut attrs: Vec<_> = msg
+		.into_iter()
+		.filter(|a| {
+			!matches!(
+				a,
+				StunAttr::Integrity(_) | StunAttr::IntegritySha256(_) | StunAttr::Fingerprint
+			)
+		})
+		.collect();
+	attrs.push(StunAttr::Integrity(Integrity::Set {
+		key_data: key.as_bytes(),
+	}));
+	attrs.push(StunAttr::Fingerprint);
+	Stun {
+		typ: msg.typ.clone(),
+		txid: msg.txid,
+		attrs: attrs[..].into(),
+	}
+	.encode(buff)
+}
+
+#[derive(Debug, Clone)]
+pub enum RewriteErr {
+	Decode(StunDecodeErr),
+	Missing,
+	NotAnAddress,
+	// The new address is a different family, so it wouldn't fit in the old one's place
+	FamilyMismatch,
+}
+impl From<StunDecodeErr> for RewriteErr {
+	fn from(value: StunDecodeErr) -> Self {
+		Self::Decode(value)
+	}
+}
+
+// Overwrites the address in the first attribute of type typ, in place, without re-encoding the rest
+// of the packet. XOR'd addresses are XOR'd against the packet's own header. MESSAGE-INTEGRITY and
+// FINGERPRINT are left alone, so a signed packet needs resign afterwards.
+pub fn rewrite_address(packet: &mut [u8], typ: u16, addr: SocketAddr) -> Result<(), RewriteErr> {
+	let msg = Stun::decode(packet)?;
+	let StunAttrs::Parse { buff, header } = msg.attrs else {
+		return Err(RewriteErr::Missing);
+	};
+	let header = *header;
+	let mut offset = 0;
+	let (old, offset) = loop {
+		let Some((Ok(attr), next)) = parse_at(buff, &header, offset) else {
+			return Err(RewriteErr::Missing);
+		};
+		if attr.typ() == typ {
+			break (attr, offset);
+		}
+		offset = next;
+	};
+	let new = match old {
+		StunAttr::Mapped(_) => StunAttr::Mapped(ZeroXor(addr)),
+		StunAttr::XMapped(_) => StunAttr::XMapped(addr),
+		StunAttr::AlternateServer(_) => StunAttr::AlternateServer(ZeroXor(addr)),
+		#[cfg(feature = "turn")]
+		StunAttr::XPeer(_) => StunAttr::XPeer(addr),
+		#[cfg(feature = "turn")]
+		StunAttr::XRelayed(_) => StunAttr::XRelayed(addr),
+		#[cfg(feature = "nat-discovery")]
+		StunAttr::ResponseOrigin(_) => StunAttr::ResponseOrigin(ZeroXor(addr)),
+		#[cfg(feature = "nat-discovery")]
+		StunAttr::OtherAddress(_) => StunAttr::OtherAddress(ZeroXor(addr)),
+		#[cfg(feature = "nat-discovery")]
+		StunAttr::ResponseAddress(_) => StunAttr::ResponseAddress(ZeroXor(addr)),
+		#[cfg(feature = "nat-discovery")]
+		StunAttr::SourceAddress(_) => StunAttr::SourceAddress(ZeroXor(addr)),
+		#[cfg(feature = "nat-discovery")]
+		StunAttr::ChangedAddress(_) => StunAttr::ChangedAddress(ZeroXor(addr)),
+		#[cfg(feature = "nat-discovery")]
+		StunAttr::ReflectedFrom(_) => StunAttr::ReflectedFrom(ZeroXor(addr)),
+		_ => return Err(RewriteErr::NotAnAddress),
+	};
+	if new.length() != old.length() {
+		return Err(RewriteErr::FamilyMismatch);
+	}
+	let ctx = AttrContext {
+		header: &header,
+		zero_xor_bytes: false,
+		attrs_prefix: &[],
+		attr_len: 0,
+		padding: &[],
+	};
+	// Same length as what it replaces, which decode already found inside the packet
+	new.encode(&mut packet[20 + offset..], ctx).map_err(|_| RewriteErr::Missing)
+}
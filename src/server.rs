@@ -0,0 +1,48 @@
+use std::net::SocketAddr;
+
+use crate::attr::StunAttr;
+use crate::record::{Direction, Recording};
+use crate::{Stun, StunDecodeErr, StunEncodeErr, StunMethod, StunTyp};
+
+pub mod cache;
+pub mod errors;
+pub mod stream;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub mod uring;
+
+#[derive(Debug, Clone)]
+pub enum ReflectErr {
+	Decode(StunDecodeErr),
+	NotBindingRequest,
+	Encode(StunEncodeErr),
+}
+impl From<StunDecodeErr> for ReflectErr {
+	fn from(value: StunDecodeErr) -> Self {
+		Self::Decode(value)
+	}
+}
+impl From<StunEncodeErr> for ReflectErr {
+	fn from(value: StunEncodeErr) -> Self {
+		Self::Encode(value)
+	}
+}
+
+// Answers a Binding request from src with its reflexive address, writing the response into out.
+pub fn reflect(
+	request: &[u8],
+	src: SocketAddr,
+	out: &mut [u8],
+	recording: &Recording,
+) -> Result<usize, ReflectErr> {
+	recording.record(Direction::Inbound, src, request);
+	let msg = Stun::decode(request)?;
+	if !matches!(msg.typ, StunTyp::Req(StunMethod::Binding)) {
+		return Err(ReflectErr::NotBindingRequest);
+	}
+	let attrs = [StunAttr::XMapped(src), StunAttr::Fingerprint];
+	let len = msg.res(&attrs).encode(out)?;
+	recording.record(Direction::Outbound, src, &out[..len]);
+	Ok(len)
+}
@@ -0,0 +1,116 @@
+// Replaying responses to retransmitted requests (RFC 8489 section 6.3.1). Over UDP a client resends
+// its request until a response gets through, and a non-idempotent request (a TURN Allocate, say)
+// mustn't be processed twice. Responses are remembered by (source, transaction id) for a window
+// that covers the client's whole retransmission schedule.
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::clock::Timestamp;
+use crate::{Stun, StunTyp, TxId};
+
+// Covers Ti (39.5 seconds)
+pub const CACHE_WINDOW: Duration = Duration::from_secs(40);
+pub const CACHE_ENTRIES: usize = 4096;
+
+type Key = (SocketAddr, TxId);
+
+#[derive(Debug)]
+struct Entries<T> {
+	responses: HashMap<Key, Box<[u8]>>,
+	// Insertion order, which is also expiry order since the window is the same for every entry
+	order: VecDeque<(T, Key)>,
+}
+
+#[derive(Debug)]
+pub struct ResponseCache<T: Timestamp = Instant> {
+	window: Duration,
+	max_entries: usize,
+	entries: Mutex<Entries<T>>,
+}
+impl<T: Timestamp> Default for ResponseCache<T> {
+	fn default() -> Self {
+		Self::new(CACHE_WINDOW, CACHE_ENTRIES)
+	}
+}
+impl<T: Timestamp> ResponseCache<T> {
+	// Once max_entries responses are cached, the oldest is dropped to make room
+	pub fn new(window: Duration, max_entries: usize) -> Self {
+		Self {
+			window,
+			max_entries: max_entries.max(1),
+			entries: Mutex::new(Entries {
+				responses: HashMap::new(),
+				order: VecDeque::new(),
+			}),
+		}
+	}
+	fn key(packet: &[u8], from: SocketAddr) -> Option<Key> {
+		let msg = Stun::decode(packet).ok()?;
+		matches!(msg.typ, StunTyp::Req(_)).then_some((from, msg.txid))
+	}
+	// Drops the entries that are too old, and then the oldest until there are at most `keep` left
+	fn expire(&self, entries: &mut Entries<T>, now: T, keep: usize) {
+		while let Some(&(inserted, key)) = entries.order.front() {
+			if inserted + self.window > now && entries.order.len() <= keep {
+				break;
+			}
+			entries.order.pop_front();
+			entries.responses.remove(&key);
+		}
+	}
+	// Copies the cached response to a retransmission of packet into buff
+	pub fn lookup(
+		&self,
+		packet: &[u8],
+		from: SocketAddr,
+		now: T,
+		buff: &mut [u8],
+	) -> Option<usize> {
+		let key = Self::key(packet, from)?;
+		let mut entries = self.entries.lock().unwrap();
+		self.expire(&mut entries, now, self.max_entries);
+		let response = entries.responses.get(&key)?;
+		let out = buff.get_mut(..response.len())?;
+		out.copy_from_slice(response);
+		Some(response.len())
+	}
+	// Remembers the response to a request
+	pub fn store(&self, packet: &[u8], from: SocketAddr, response: &[u8], now: T) {
+		let Some(key) = Self::key(packet, from) else {
+			return;
+		};
+		let mut entries = self.entries.lock().unwrap();
+		self.expire(&mut entries, now, self.max_entries - 1);
+		if entries.responses.insert(key, response.into()).is_none() {
+			entries.order.push_back((now, key));
+		}
+	}
+	// Wraps a handler (the same kind the server loops take): retransmitted requests get the cached
+	// response, anything else goes to the handler and its response to a request is cached.
+	pub fn handle<F>(
+		&self,
+		packet: &[u8],
+		from: SocketAddr,
+		now: T,
+		buff: &mut [u8],
+		handler: F,
+	) -> Option<usize>
+	where
+		F: FnOnce(&[u8], SocketAddr, &mut [u8]) -> Option<usize>,
+	{
+		if let Some(len) = self.lookup(packet, from, now, buff) {
+			return Some(len);
+		}
+		let len = handler(packet, from, buff)?;
+		self.store(packet, from, &buff[..len], now);
+		Some(len)
+	}
+	pub fn len(&self) -> usize {
+		self.entries.lock().unwrap().responses.len()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
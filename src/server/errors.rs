@@ -0,0 +1,192 @@
+// Error responses, each carrying the attributes that its error code requires. A FINGERPRINT is
+// appended when the request had one.
+use std::net::SocketAddr;
+
+use crate::attr::{Integrity, IntegritySha256, PasswordAlgorithms, StunAttr};
+use crate::auth::IntegrityKey;
+use crate::{Stun, StunEncodeErr};
+
+fn respond<'i>(
+	req: &Stun<'i>,
+	mut attrs: Vec<StunAttr<'i>>,
+	out: &mut [u8],
+) -> Result<usize, StunEncodeErr> {
+	if req.flat().fingerprint.is_some() {
+		attrs.push(StunAttr::Fingerprint);
+	}
+	req.err(&attrs).encode(out)
+}
+
+// Like the builders below, but for a request that has passed authentication: the response is
+// signed with the request's key, using the same integrity attribute(s) the request did (RFC 8489
+// section 9.2.4). Clients discard unsigned responses to authenticated requests.
+pub fn respond_signed<'i, K: IntegrityKey>(
+	req: &Stun<'i>,
+	mut attrs: Vec<StunAttr<'i>>,
+	key: &'i K,
+	out: &mut [u8],
+) -> Result<usize, StunEncodeErr> {
+	let flat = req.flat();
+	if flat.integrity.is_some() {
+		attrs.push(StunAttr::Integrity(Integrity::sign(key)));
+	}
+	if flat.integrity_sha256.is_some() {
+		attrs.push(StunAttr::IntegritySha256(IntegritySha256::sign(key)));
+	}
+	respond(req, attrs, out)
+}
+
+// 300 Try Alternate
+pub fn try_alternate(
+	req: &Stun<'_>,
+	alternate: SocketAddr,
+	out: &mut [u8],
+) -> Result<usize, StunEncodeErr> {
+	let attrs = vec![
+		StunAttr::error_code(300),
+		StunAttr::alternate_server(alternate),
+	];
+	respond(req, attrs, out)
+}
+
+// 300 Try Alternate for a request that arrived over (D)TLS: the client validates the alternate
+// server's certificate against domain
+pub fn try_alternate_domain<'i>(
+	req: &Stun<'i>,
+	alternate: SocketAddr,
+	domain: &'i str,
+	out: &mut [u8],
+) -> Result<usize, StunEncodeErr> {
+	let attrs = vec![
+		StunAttr::error_code(300),
+		StunAttr::alternate_server(alternate),
+		StunAttr::alternate_domain(domain),
+	];
+	respond(req, attrs, out)
+}
+
+// 400 Bad Request
+pub fn bad_request(req: &Stun<'_>, out: &mut [u8]) -> Result<usize, StunEncodeErr> {
+	respond(req, vec![StunAttr::error_code(400)], out)
+}
+
+// 401 Unauthorized: the challenge for long-term credentials
+pub fn unauthorized<'i>(
+	req: &Stun<'i>,
+	realm: &'i str,
+	nonce: &'i str,
+	out: &mut [u8],
+) -> Result<usize, StunEncodeErr> {
+	let attrs = vec![
+		StunAttr::error_code(401),
+		StunAttr::Realm(realm),
+		StunAttr::Nonce(nonce),
+	];
+	respond(req, attrs, out)
+}
+
+// 420 Unknown Attribute: lists the comprehension-required attributes that weren't understood
+pub fn unknown_attribute<'i>(
+	req: &Stun<'i>,
+	typs: &'i [u16],
+	out: &mut [u8],
+) -> Result<usize, StunEncodeErr> {
+	let attrs = vec![
+		StunAttr::error_code(420),
+		StunAttr::unknown_attributes(typs),
+	];
+	respond(req, attrs, out)
+}
+
+// 438 Stale Nonce: nonce should be a fresh nonce for the client to retry with
+pub fn stale_nonce<'i>(
+	req: &Stun<'i>,
+	realm: &'i str,
+	nonce: &'i str,
+	out: &mut [u8],
+) -> Result<usize, StunEncodeErr> {
+	let attrs = vec![
+		StunAttr::error_code(438),
+		StunAttr::Realm(realm),
+		StunAttr::Nonce(nonce),
+	];
+	respond(req, attrs, out)
+}
+
+// 437 Allocation Mismatch: an Allocate for a 5-tuple that already has an allocation, or another
+// request for one that doesn't
+pub fn allocation_mismatch(req: &Stun<'_>, out: &mut [u8]) -> Result<usize, StunEncodeErr> {
+	respond(req, vec![StunAttr::error_code(437)], out)
+}
+
+// 441 Wrong Credentials: the request's credentials aren't the ones that created the allocation
+pub fn wrong_credentials(req: &Stun<'_>, out: &mut [u8]) -> Result<usize, StunEncodeErr> {
+	respond(req, vec![StunAttr::error_code(441)], out)
+}
+
+// 442 Unsupported Transport Protocol
+pub fn unsupported_transport(req: &Stun<'_>, out: &mut [u8]) -> Result<usize, StunEncodeErr> {
+	respond(req, vec![StunAttr::error_code(442)], out)
+}
+
+// 443 Peer Address Family Mismatch: a peer address isn't of the relayed address's family
+pub fn peer_family_mismatch(req: &Stun<'_>, out: &mut [u8]) -> Result<usize, StunEncodeErr> {
+	respond(req, vec![StunAttr::error_code(443)], out)
+}
+
+// 500 Server Error
+pub fn server_error(req: &Stun<'_>, out: &mut [u8]) -> Result<usize, StunEncodeErr> {
+	respond(req, vec![StunAttr::error_code(500)], out)
+}
+
+// Issues and checks the nonces of one realm for the long-term credential mechanism
+pub trait NonceProvider {
+	fn realm(&self) -> &str;
+	// A fresh nonce for a client at from
+	fn nonce(&self, from: SocketAddr) -> String;
+	// Whether a nonce that the client sent back is still good
+	fn is_valid(&self, nonce: &str, from: SocketAddr) -> bool;
+}
+
+// Runs the credential checks that come before MESSAGE-INTEGRITY can be verified (RFC 8489 section
+// 9.2.4), writing the response when one fails:
+// - no MESSAGE-INTEGRITY(-SHA256): 401 Unauthorized with a fresh nonce
+// - integrity but a missing USERNAME, REALM or NONCE: 400 Bad Request
+// - a nonce the provider no longer accepts: 438 Stale Nonce with a fresh nonce
+// Returns Ok(None) when the request should go on to have its integrity verified. Any algorithms
+// are offered in the 401 and 438 responses as PASSWORD-ALGORITHMS.
+pub fn challenge<N: NonceProvider + ?Sized>(
+	req: &Stun<'_>,
+	from: SocketAddr,
+	nonces: &N,
+	algorithms: Option<PasswordAlgorithms<'_>>,
+	out: &mut [u8],
+) -> Result<Option<usize>, StunEncodeErr> {
+	let flat = req.flat();
+	let code = if flat.integrity.is_none() && flat.integrity_sha256.is_none() {
+		401
+	} else if flat.username.is_none() || flat.realm.is_none() {
+		return bad_request(req, out).map(Some);
+	} else {
+		match flat.nonce {
+			None => return bad_request(req, out).map(Some),
+			Some(nonce) if nonces.is_valid(nonce, from) => return Ok(None),
+			Some(_) => 438,
+		}
+	};
+	let nonce = nonces.nonce(from);
+	let mut attrs = vec![
+		StunAttr::error_code(code),
+		StunAttr::Realm(nonces.realm()),
+		StunAttr::Nonce(&nonce),
+	];
+	if let Some(algorithms) = algorithms {
+		attrs.push(StunAttr::PasswordAlgorithms(algorithms));
+	}
+	respond(req, attrs, out).map(Some)
+}
+
+// 508 Insufficient Capacity: no relayed address (or reservation) is available
+pub fn insufficient_capacity(req: &Stun<'_>, out: &mut [u8]) -> Result<usize, StunEncodeErr> {
+	respond(req, vec![StunAttr::error_code(508)], out)
+}
@@ -0,0 +1,194 @@
+// Stream (TCP / TLS) listeners. Frames carry their own lengths: STUN messages in the header, TURN
+// ChannelData in its 4 byte header (padded to a multiple of 4 on streams, RFC 8656 section 12.5).
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+
+use crate::record::{Direction, Recording};
+use crate::transactions::TI;
+
+pub const STUN_PORT: u16 = 3478;
+pub const STUNS_PORT: u16 = 5349;
+// How long a connection may go without sending anything. Ti is as long as a client waits for a
+// response, which suits plain STUN; TURN over TCP wants longer, since clients only refresh every few
+// minutes.
+pub const IDLE_TIMEOUT: Duration = TI;
+// How long to back off after a failed accept (e.g. out of file descriptors) before trying again
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+// The largest frame: a STUN header plus the largest aligned length
+const MAX_FRAME: usize = 20 + 0xFFFC;
+
+// Buffers the bytes read from a stream and splits them into frames. Holds at most one maximum sized
+// frame, so a connection's memory use is bounded no matter what the peer sends.
+#[derive(Debug)]
+pub struct Reassembler {
+	buff: Box<[u8]>,
+	start: usize,
+	end: usize,
+}
+impl Default for Reassembler {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl Reassembler {
+	pub fn new() -> Self {
+		Self {
+			buff: vec![0; MAX_FRAME].into_boxed_slice(),
+			start: 0,
+			end: 0,
+		}
+	}
+	// Reads more of the stream, returning 0 at the end of the stream
+	pub fn read_from<R: Read>(&mut self, stream: &mut R) -> io::Result<usize> {
+		// Move the partial frame to the front to make room
+		if self.start > 0 {
+			self.buff.copy_within(self.start..self.end, 0);
+			self.end -= self.start;
+			self.start = 0;
+		}
+		let n = stream.read(&mut self.buff[self.end..])?;
+		self.end += n;
+		Ok(n)
+	}
+	// Returns the next complete frame, or None if more needs to be read first. Errors if the bytes
+	// can't be the start of a STUN message or ChannelData, in which case the stream is unrecoverable.
+	pub fn next_frame(&mut self) -> io::Result<Option<&[u8]>> {
+		let start = self.start;
+		let Some(&[b0, _, l0, l1]) = self.buff[start..self.end].first_chunk::<4>() else {
+			return Ok(None);
+		};
+		let length = u16::from_be_bytes([l0, l1]) as usize;
+		let len = match b0 >> 6 {
+			0b00 if length.is_multiple_of(4) => 20 + length,
+			0b01 => (4 + length).next_multiple_of(4),
+			_ => {
+				return Err(io::Error::new(
+					io::ErrorKind::InvalidData,
+					"not a STUN message or ChannelData",
+				))
+			}
+		};
+		if start + len > self.end {
+			return Ok(None);
+		}
+		self.start += len;
+		Ok(Some(&self.buff[start..][..len]))
+	}
+}
+
+// Serves one connection until the peer closes it. The handler is the same one the UDP loops take:
+// it gets each frame and its source, and writes any response into the provided buffer. Responses
+// are written before anything more is read, so a peer that doesn't read its responses only stalls
+// its own connection. Every frame and every response is recorded.
+pub fn serve<S, F>(
+	mut stream: S,
+	peer: SocketAddr,
+	recording: &Recording,
+	handler: &F,
+) -> io::Result<()>
+where
+	S: Read + Write,
+	F: Fn(&[u8], SocketAddr, &mut [u8]) -> Option<usize>,
+{
+	let mut frames = Reassembler::new();
+	let mut out = vec![0; MAX_FRAME];
+	loop {
+		while let Some(frame) = frames.next_frame()? {
+			recording.record(Direction::Inbound, peer, frame);
+			if let Some(len) = handler(frame, peer, &mut out) {
+				recording.record(Direction::Outbound, peer, &out[..len]);
+				stream.write_all(&out[..len])?;
+			}
+		}
+		stream.flush()?;
+		if frames.read_from(&mut stream)? == 0 {
+			return Ok(());
+		}
+	}
+}
+
+// Counts the connections being served
+struct Slots {
+	count: Mutex<usize>,
+	freed: Condvar,
+}
+impl Slots {
+	fn acquire(&self, max: usize) -> Slot<'_> {
+		let mut count = self.count.lock().unwrap();
+		while *count >= max {
+			count = self.freed.wait(count).unwrap();
+		}
+		*count += 1;
+		Slot(self)
+	}
+}
+// Frees its slot when dropped, even if the connection's thread panics
+struct Slot<'a>(&'a Slots);
+impl Drop for Slot<'_> {
+	fn drop(&mut self) {
+		*self.0.count.lock().unwrap_or_else(|e| e.into_inner()) -= 1;
+		self.0.freed.notify_one();
+	}
+}
+
+// Accepts connections, running connection for each one on its own thread. Once max_connections
+// are being served, new connections wait in the listen backlog until one closes, and connections
+// that stay silent for idle_timeout are closed so that they can't hold the slots forever. Failing to
+// accept doesn't stop the loop, since the usual causes (running out of file descriptors, say) pass.
+pub(super) fn accept_loop<C>(
+	listener: &TcpListener,
+	max_connections: usize,
+	idle_timeout: Duration,
+	connection: C,
+) -> io::Result<()>
+where
+	C: Fn(TcpStream, SocketAddr) -> io::Result<()> + Sync,
+{
+	let slots = Slots {
+		count: Mutex::new(0),
+		freed: Condvar::new(),
+	};
+	let (slots, connection) = (&slots, &connection);
+	std::thread::scope(|s| loop {
+		let slot = slots.acquire(max_connections.max(1));
+		let (stream, peer) = match listener.accept() {
+			Ok(accepted) => accepted,
+			// The peer gave up before we got to it
+			Err(e) if e.kind() == io::ErrorKind::ConnectionAborted => continue,
+			Err(_) => {
+				std::thread::sleep(ACCEPT_BACKOFF);
+				continue;
+			}
+		};
+		s.spawn(move || {
+			let _slot = slot;
+			let _ = stream.set_nodelay(true);
+			// A zero timeout would mean none at all
+			if stream
+				.set_read_timeout(Some(idle_timeout.max(Duration::from_millis(1))))
+				.is_ok()
+			{
+				let _ = connection(stream, peer);
+			}
+		});
+	})
+}
+
+// Serves stun: over TCP (port 3478)
+pub fn run<F>(
+	listener: &TcpListener,
+	max_connections: usize,
+	idle_timeout: Duration,
+	recording: &Recording,
+	handler: F,
+) -> io::Result<()>
+where
+	F: Fn(&[u8], SocketAddr, &mut [u8]) -> Option<usize> + Sync,
+{
+	accept_loop(listener, max_connections, idle_timeout, |stream, peer| {
+		serve(stream, peer, recording, &handler)
+	})
+}
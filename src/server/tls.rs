@@ -0,0 +1,30 @@
+// stuns: / turns: listeners (port 5349). The crypto provider, certificates and ALPN are all up to
+// the ServerConfig.
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+
+use super::stream::{accept_loop, serve};
+use crate::record::Recording;
+
+// Serves TLS connections with the same handler as the UDP and TCP loops. Handshakes happen on the
+// connection's own thread, so a slow client doesn't hold up the others.
+pub fn run<F>(
+	listener: &TcpListener,
+	config: Arc<ServerConfig>,
+	max_connections: usize,
+	idle_timeout: Duration,
+	recording: &Recording,
+	handler: F,
+) -> io::Result<()>
+where
+	F: Fn(&[u8], SocketAddr, &mut [u8]) -> Option<usize> + Sync,
+{
+	accept_loop(listener, max_connections, idle_timeout, |tcp, peer| {
+		let conn = ServerConnection::new(config.clone()).map_err(io::Error::other)?;
+		serve(StreamOwned::new(conn, tcp), peer, recording, &handler)
+	})
+}
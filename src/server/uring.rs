@@ -0,0 +1,128 @@
+use std::io;
+use std::mem::{size_of, zeroed};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, UdpSocket};
+use std::os::fd::AsRawFd;
+
+use io_uring::{opcode, squeue, types, IoUring};
+
+use super::reflect;
+use crate::record::{Direction, Recording};
+
+const MTU: usize = 2048;
+
+// Per-receive state. The kernel holds pointers into this while operations are in flight, so slots
+// are boxed and never move.
+struct Slot {
+	recv: [u8; MTU],
+	recv_addr: libc::sockaddr_storage,
+	recv_iov: libc::iovec,
+	recv_hdr: libc::msghdr,
+	send: [u8; MTU],
+	send_addr: libc::sockaddr_storage,
+	send_iov: libc::iovec,
+	send_hdr: libc::msghdr,
+}
+
+fn from_storage(addr: &libc::sockaddr_storage) -> Option<SocketAddr> {
+	match addr.ss_family as libc::c_int {
+		libc::AF_INET => {
+			let addr = unsafe { &*(addr as *const _ as *const libc::sockaddr_in) };
+			let ip = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+			Some(SocketAddrV4::new(ip, u16::from_be(addr.sin_port)).into())
+		}
+		libc::AF_INET6 => {
+			let addr = unsafe { &*(addr as *const _ as *const libc::sockaddr_in6) };
+			let ip = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+			let port = u16::from_be(addr.sin6_port);
+			Some(SocketAddrV6::new(ip, port, addr.sin6_flowinfo, addr.sin6_scope_id).into())
+		}
+		_ => None,
+	}
+}
+
+// Runs a receive → handle → linked send loop over the socket. The handler gets the received packet
+// and its source and writes any response into the provided buffer, returning the response length.
+// `depth` receives are kept in flight at once. Every packet received and every response is recorded.
+pub fn run<F>(
+	sock: &UdpSocket,
+	depth: usize,
+	recording: &Recording,
+	mut handler: F,
+) -> io::Result<()>
+where
+	F: FnMut(&[u8], SocketAddr, &mut [u8]) -> Option<usize>,
+{
+	let fd = types::Fd(sock.as_raw_fd());
+	let mut ring = IoUring::new((depth * 2).next_power_of_two() as u32)?;
+	let mut slots: Vec<Box<Slot>> = (0..depth).map(|_| Box::new(unsafe { zeroed() })).collect();
+
+	let recv_entry = |slot: &mut Slot, i: usize| {
+		slot.recv_iov = libc::iovec {
+			iov_base: slot.recv.as_mut_ptr().cast(),
+			iov_len: MTU,
+		};
+		slot.recv_hdr.msg_name = (&mut slot.recv_addr as *mut libc::sockaddr_storage).cast();
+		slot.recv_hdr.msg_namelen = size_of::<libc::sockaddr_storage>() as u32;
+		slot.recv_hdr.msg_iov = &mut slot.recv_iov;
+		slot.recv_hdr.msg_iovlen = 1;
+		opcode::RecvMsg::new(fd, &mut slot.recv_hdr)
+			.build()
+			.user_data((i as u64) << 1)
+	};
+
+	for (i, slot) in slots.iter_mut().enumerate() {
+		let entry = recv_entry(slot, i);
+		unsafe { ring.submission().push(&entry) }.map_err(io::Error::other)?;
+	}
+	loop {
+		ring.submit_and_wait(1)?;
+		let completions: Vec<_> = ring.completion().map(|c| (c.user_data(), c.result())).collect();
+		for (user_data, result) in completions {
+			let i = (user_data >> 1) as usize;
+			// Send completions need no handling: the linked receive re-arms the slot
+			if user_data & 1 == 1 {
+				continue;
+			}
+			let slot = &mut *slots[i];
+			let response = match (result, from_storage(&slot.recv_addr)) {
+				(len, Some(from)) if len >= 0 => {
+					let packet = &slot.recv[..len as usize];
+					recording.record(Direction::Inbound, from, packet);
+					let response = handler(packet, from, &mut slot.send);
+					if let Some(n) = response {
+						recording.record(Direction::Outbound, from, &slot.send[..n]);
+					}
+					response.map(|n| (n, from))
+				}
+				_ => None,
+			};
+			let recv = recv_entry(slot, i);
+			let Some((len, _)) = response else {
+				unsafe { ring.submission().push(&recv) }.map_err(io::Error::other)?;
+				continue;
+			};
+			slot.send_addr = slot.recv_addr;
+			slot.send_iov = libc::iovec {
+				iov_base: slot.send.as_mut_ptr().cast(),
+				iov_len: len,
+			};
+			slot.send_hdr.msg_name = (&mut slot.send_addr as *mut libc::sockaddr_storage).cast();
+			slot.send_hdr.msg_namelen = slot.recv_hdr.msg_namelen;
+			slot.send_hdr.msg_iov = &mut slot.send_iov;
+			slot.send_hdr.msg_iovlen = 1;
+			let send = opcode::SendMsg::new(fd, &slot.send_hdr)
+				.build()
+				.flags(squeue::Flags::IO_LINK)
+				.user_data(((i as u64) << 1) | 1);
+			unsafe { ring.submission().push_multiple(&[send, recv]) }.map_err(io::Error::other)?;
+		}
+	}
+}
+
+// A complete Binding reflector: server::reflect driven by io_uring. The loop does the recording.
+pub fn run_reflector(sock: &UdpSocket, depth: usize, recording: &Recording) -> io::Result<()> {
+	let unrecorded = Recording::default();
+	run(sock, depth, recording, |packet, from, out| {
+		reflect(packet, from, out, &unrecorded).ok()
+	})
+}
@@ -0,0 +1,43 @@
+// Decoding STUN from a byte stream (TCP / TLS), where messages aren't delimited by datagrams. The
+// decoder never buffers: it looks at whatever the caller has read so far and either returns the
+// first message or says how many more bytes it needs. server::stream::Reassembler does the
+// buffering for sockets (and also frames TURN ChannelData).
+use crate::{SizeLimit, Stun, StunDecodeErr};
+
+#[derive(Debug, Clone)]
+pub enum Streamed<'i> {
+	// At least this many more bytes are needed before the message is complete
+	Incomplete(usize),
+	// A message, and the number of bytes it took up at the front of the buffer
+	Complete(Stun<'i>, usize),
+}
+
+// Errors mean the stream isn't carrying STUN (or has lost its framing) and can't be recovered
+pub fn decode(buff: &[u8], limit: SizeLimit) -> Result<Streamed<'_>, StunDecodeErr> {
+	// Reject garbage as early as possible instead of waiting on a length that will never arrive
+	if let Some(&t0) = buff.first() {
+		if t0 & 0b1100_0000 != 0 {
+			return Err(StunDecodeErr::TypeOutOfRange);
+		}
+	}
+	if let Some(&[_, _, _, _, m0, m1, m2, m3]) = buff.first_chunk::<8>() {
+		if u32::from_be_bytes([m0, m1, m2, m3]) != 0x2112A442 {
+			return Err(StunDecodeErr::BadMagic);
+		}
+	}
+	let Some(&[_, _, l0, l1]) = buff.first_chunk::<4>() else {
+		return Ok(Streamed::Incomplete(20 - buff.len()));
+	};
+	let length = u16::from_be_bytes([l0, l1]);
+	if length % 4 != 0 {
+		return Err(StunDecodeErr::UnalignedLength);
+	}
+	let len = 20 + length as usize;
+	if len > limit.0 {
+		return Err(StunDecodeErr::TooLarge);
+	}
+	match buff.get(..len) {
+		None => Ok(Streamed::Incomplete(len - buff.len())),
+		Some(msg) => Ok(Streamed::Complete(Stun::decode_limit(msg, limit)?, len)),
+	}
+}
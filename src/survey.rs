@@ -0,0 +1,210 @@
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::attr::StunAttr;
+#[cfg(not(feature = "nat-discovery"))]
+use crate::attr::{typ, AttrContext, StunAttrValue, ZeroXor};
+use crate::clock::{Clock, SystemClock, Timestamp};
+use crate::record::{Direction, Recording};
+use crate::{RandomErr, Stun, StunMethod, StunTyp};
+
+#[derive(Debug)]
+pub enum ProbeErr {
+	Io(io::Error),
+	Random(RandomErr),
+	Timeout,
+	NoMappedAddress,
+}
+impl From<io::Error> for ProbeErr {
+	fn from(value: io::Error) -> Self {
+		Self::Io(value)
+	}
+}
+impl From<RandomErr> for ProbeErr {
+	fn from(value: RandomErr) -> Self {
+		Self::Random(value)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ProbeRes {
+	pub mapped: SocketAddr,
+	pub rtt: Duration,
+	pub software: Option<String>,
+	// RFC 5780 support
+	pub response_origin: Option<SocketAddr>,
+	pub other_address: Option<SocketAddr>,
+}
+
+#[derive(Debug)]
+pub struct Probe {
+	pub server: SocketAddr,
+	pub result: Result<ProbeRes, ProbeErr>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Survey<C: Clock = SystemClock> {
+	pub parallelism: usize,
+	pub timeout: Duration,
+	pub retransmit: Duration,
+	pub recording: Recording,
+	// Times the retransmissions, the timeout and the rtt
+	pub clock: C,
+}
+impl Default for Survey {
+	fn default() -> Self {
+		Self {
+			parallelism: 8,
+			timeout: Duration::from_secs(3),
+			retransmit: Duration::from_millis(500),
+			recording: Recording::default(),
+			clock: SystemClock,
+		}
+	}
+}
+impl<C: Clock> Survey<C> {
+	// Probes every server with a Binding request, running at most `parallelism` probes at once.
+	// Results are returned in the same order as `servers`.
+	pub fn run(&self, servers: &[SocketAddr]) -> Vec<Probe>
+	where
+		C: Sync,
+	{
+		let next = AtomicUsize::new(0);
+		let results = Mutex::new(Vec::with_capacity(servers.len()));
+		std::thread::scope(|s| {
+			for _ in 0..self.parallelism.clamp(1, servers.len().max(1)) {
+				s.spawn(|| loop {
+					let i = next.fetch_add(1, Ordering::Relaxed);
+					let Some(server) = servers.get(i) else { break };
+					let result = self.probe(*server);
+					results.lock().unwrap().push((
+						i,
+						Probe {
+							server: *server,
+							result,
+						},
+					));
+				});
+			}
+		});
+		let mut results = results.into_inner().unwrap();
+		results.sort_by_key(|(i, _)| *i);
+		results.into_iter().map(|(_, p)| p).collect()
+	}
+	pub fn probe(&self, server: SocketAddr) -> Result<ProbeRes, ProbeErr> {
+		let bind: SocketAddr = match server {
+			SocketAddr::V4(_) => "0.0.0.0:0".parse().unwrap(),
+			SocketAddr::V6(_) => "[::]:0".parse().unwrap(),
+		};
+		let sock = UdpSocket::bind(bind)?;
+
+		let attrs = [StunAttr::Software("stun-zc: survey"), StunAttr::Fingerprint];
+		let req = Stun::try_req(StunMethod::Binding, &attrs)?;
+		let txid = req.txid;
+		let mut send_buff = [0u8; 128];
+		let len = req
+			.encode(&mut send_buff)
+			.expect("Binding request fits in 128 bytes");
+		let req = &send_buff[..len];
+
+		let mut recv_buff = [0u8; 2048];
+		let start = self.clock.now();
+		let deadline = start + self.timeout;
+		let mut next_send = start;
+		loop {
+			let now = self.clock.now();
+			if now >= deadline {
+				return Err(ProbeErr::Timeout);
+			}
+			if now >= next_send {
+				sock.send_to(req, server)?;
+				self.recording.record(Direction::Outbound, server, req);
+				next_send = now + self.retransmit;
+			}
+			// A zero timeout would mean blocking forever
+			let wait = next_send.min(deadline).saturating_duration_since(now);
+			sock.set_read_timeout(Some(wait.max(Duration::from_millis(1))))?;
+			let (len, from) = match sock.recv_from(&mut recv_buff) {
+				Ok(r) => r,
+				Err(e)
+					if matches!(
+						e.kind(),
+						io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+					) =>
+				{
+					continue
+				}
+				Err(e) => return Err(e.into()),
+			};
+			let rtt = self.clock.now().saturating_duration_since(start);
+			self.recording
+				.record(Direction::Inbound, from, &recv_buff[..len]);
+			let Ok(msg) = Stun::decode(&recv_buff[..len]) else {
+				continue;
+			};
+			if from != server
+				|| msg.txid != txid
+				|| !matches!(msg.typ, StunTyp::Res(StunMethod::Binding))
+			{
+				continue;
+			}
+			let flat = msg.flat();
+			// Classic (RFC 3489) servers send SOURCE-ADDRESS / CHANGED-ADDRESS instead
+			#[cfg(feature = "nat-discovery")]
+			let (response_origin, other_address) = (
+				flat.response_origin.or(flat.source_address),
+				flat.other_address.or(flat.changed_address),
+			);
+			#[cfg(not(feature = "nat-discovery"))]
+			let (response_origin, other_address) = other_addrs(&msg);
+			return Ok(ProbeRes {
+				mapped: flat
+					.xmapped
+					.or(flat.mapped)
+					.ok_or(ProbeErr::NoMappedAddress)?,
+				rtt,
+				software: flat.software.map(String::from),
+				response_origin,
+				other_address,
+			});
+		}
+	}
+}
+
+// Without the nat-discovery attributes, RESPONSE-ORIGIN and OTHER-ADDRESS arrive as Other
+#[cfg(not(feature = "nat-discovery"))]
+fn other_addrs(msg: &Stun<'_>) -> (Option<SocketAddr>, Option<SocketAddr>) {
+	let mut response_origin = None;
+	let mut other_address = None;
+	for attr in msg {
+		let StunAttr::Other(attr_typ, value) = attr else {
+			continue;
+		};
+		match (attr_typ, plain_addr(value.value)) {
+			(typ::RESPONSE_ORIGIN, Some(a)) if response_origin.is_none() => {
+				response_origin = Some(a)
+			}
+			(typ::OTHER_ADDRESS, Some(a)) if other_address.is_none() => other_address = Some(a),
+			_ => {}
+		}
+	}
+	(response_origin, other_address)
+}
+
+// RESPONSE-ORIGIN and OTHER-ADDRESS use the same (non-xor) encoding as MAPPED-ADDRESS
+#[cfg(not(feature = "nat-discovery"))]
+fn plain_addr(value: &[u8]) -> Option<SocketAddr> {
+	let ctx = AttrContext {
+		header: &[0; 20],
+		zero_xor_bytes: true,
+		attrs_prefix: &[],
+		attr_len: 0,
+		padding: &[],
+	};
+	ZeroXor::<SocketAddr>::decode(value, ctx)
+		.ok()
+		.map(ZeroXor::into)
+}
@@ -0,0 +1,147 @@
+// Helpers for producing deliberately invalid messages, for testing the rejection paths of decoders,
+// and a transport that captures what the sans-io components send.
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use crate::transport::Transport;
+use crate::SizeLimit;
+#[cfg(feature = "fault-injection")]
+use crate::{Stun, StunEncodeErr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutation {
+	// Flip the bits of the magic cookie
+	WrongCookie,
+	// Overwrite the message length field
+	Length(u16),
+	// Cut the message off in the middle of the nth attribute's value
+	TruncateAttr(usize),
+	// Overwrite the class (hundreds digit) of the first ERROR-CODE attribute
+	ErrorClass(u8),
+	// Overwrite the length field of the nth attribute
+	AttrLength(usize, u16),
+}
+
+#[derive(Debug, Clone)]
+pub struct Mutator<'i> {
+	valid: &'i [u8],
+}
+impl<'i> Mutator<'i> {
+	// valid should be a complete encoded message (e.g. the output of Stun::encode)
+	pub fn new(valid: &'i [u8]) -> Self {
+		Self { valid }
+	}
+	// Offsets and value lengths of each attribute in the message
+	fn attrs(&self) -> impl Iterator<Item = (usize, u16, usize)> + '_ {
+		let mut offset = 20;
+		std::iter::from_fn(move || {
+			let header = self.valid.get(offset..)?.get(..4)?;
+			let typ = u16::from_be_bytes([header[0], header[1]]);
+			let length = u16::from_be_bytes([header[2], header[3]]) as usize;
+			// Stop at an attribute that runs past the end of the message
+			self.valid.get(offset + 4..)?.get(..length)?;
+			let ret = (offset, typ, length);
+			offset += (4 + length).next_multiple_of(4);
+			Some(ret)
+		})
+	}
+	// Writes the mutated message into out, returning its length. Returns None if the mutation
+	// doesn't apply to this message (e.g. there's no ERROR-CODE attribute) or out is too small.
+	pub fn apply(&self, mutation: Mutation, out: &mut [u8]) -> Option<usize> {
+		if self.valid.len() < 20 || out.len() < self.valid.len() {
+			return None;
+		}
+		let mut len = self.valid.len();
+		out[..len].copy_from_slice(self.valid);
+		match mutation {
+			Mutation::WrongCookie => {
+				for b in &mut out[4..8] {
+					*b = !*b;
+				}
+			}
+			Mutation::Length(length) => out[2..4].copy_from_slice(&length.to_be_bytes()),
+			Mutation::TruncateAttr(n) => {
+				let (offset, _, length) = self.attrs().nth(n)?;
+				if length == 0 {
+					return None;
+				}
+				len = (offset + 4 + length / 2) / 4 * 4;
+				out[2..4].copy_from_slice(&((len - 20) as u16).to_be_bytes());
+			}
+			Mutation::ErrorClass(class) => {
+				let (offset, _, length) = self.attrs().find(|(_, typ, _)| *typ == 0x0009)?;
+				if length < 4 {
+					return None;
+				}
+				out[offset + 4 + 2] = class;
+			}
+			Mutation::AttrLength(n, length) => {
+				let (offset, ..) = self.attrs().nth(n)?;
+				out[offset + 2..][..2].copy_from_slice(&length.to_be_bytes());
+			}
+		}
+		Some(len)
+	}
+}
+
+// Keeps everything sent through it, in order
+#[derive(Debug, Clone, Default)]
+pub struct Captured {
+	pub reliable: bool,
+	// Reported as the transport's MTU, the wire limit if unset
+	pub mtu: Option<SizeLimit>,
+	pub sent: Vec<(Vec<u8>, SocketAddr)>,
+}
+impl Transport for Captured {
+	type Error = Infallible;
+	fn send(&mut self, msg: &[u8], to: SocketAddr) -> Result<(), Infallible> {
+		self.sent.push((msg.to_vec(), to));
+		Ok(())
+	}
+	fn reliable(&self) -> bool {
+		self.reliable
+	}
+	fn mtu(&self) -> SizeLimit {
+		self.mtu.unwrap_or_default()
+	}
+}
+
+#[cfg(feature = "fault-injection")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+	// Flip the first n bits of the MESSAGE-INTEGRITY value
+	Integrity(usize),
+	// Flip the first n bits of the MESSAGE-INTEGRITY-SHA256 value
+	IntegritySha256(usize),
+	// Flip the first n bits of the FINGERPRINT value
+	Fingerprint(usize),
+}
+
+#[cfg(feature = "fault-injection")]
+impl Stun<'_> {
+	// Encodes the message and then corrupts the requested attribute. When corrupting an integrity,
+	// the fingerprint is recomputed so that the integrity is the only thing wrong with the message.
+	pub fn encode_with_fault(&self, buff: &mut [u8], fault: Fault) -> Result<usize, StunEncodeErr> {
+		let len = self.encode(buff)?;
+		let (typ, bits) = match fault {
+			Fault::Integrity(bits) => (0x0008, bits),
+			Fault::IntegritySha256(bits) => (0x001C, bits),
+			Fault::Fingerprint(bits) => (0x8028, bits),
+		};
+		let attrs: Vec<_> = Mutator::new(&buff[..len]).attrs().collect();
+		let Some(&(offset, _, length)) = attrs.iter().find(|(_, t, _)| *t == typ) else {
+			return Ok(len);
+		};
+		let value = &mut buff[offset + 4..][..length];
+		for bit in 0..bits.min(length * 8) {
+			value[bit / 8] ^= 0x80 >> (bit % 8);
+		}
+		if typ != 0x8028 {
+			if let Some(&(offset, ..)) = attrs.iter().find(|(_, t, _)| *t == 0x8028) {
+				let fingerprint = crc32fast::hash(&buff[..offset]) ^ 0x5354554e;
+				buff[offset + 4..][..4].copy_from_slice(&fingerprint.to_be_bytes());
+			}
+		}
+		Ok(len)
+	}
+}
@@ -0,0 +1,309 @@
+use std::collections::{BTreeSet, HashMap};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::clock::Timestamp;
+use crate::transport::Transport;
+use crate::{Stun, StunDecodeErr, StunTyp, TxId};
+
+#[derive(Debug)]
+struct Shard<V, T> {
+	entries: HashMap<TxId, (V, T)>,
+	// Ordered by deadline so expiry only looks at the entries that are due
+	deadlines: BTreeSet<(T, TxId)>,
+}
+
+// Outstanding transactions keyed by transaction id, split across shards so that threads matching
+// responses don't all contend on one lock.
+#[derive(Debug)]
+pub struct TxTable<V, T: Timestamp = Instant> {
+	shards: Box<[Mutex<Shard<V, T>>]>,
+}
+impl<V, T: Timestamp> TxTable<V, T> {
+	pub fn new(shards: usize) -> Self {
+		Self {
+			shards: (0..shards.max(1))
+				.map(|_| {
+					Mutex::new(Shard {
+						entries: HashMap::new(),
+						deadlines: BTreeSet::new(),
+					})
+				})
+				.collect(),
+		}
+	}
+	// Transaction ids are random, so their leading bytes spread evenly over the shards
+	fn shard(&self, txid: &TxId) -> &Mutex<Shard<V, T>> {
+		let n = u32::from_le_bytes(txid.0[..4].try_into().unwrap()) as usize;
+		&self.shards[n % self.shards.len()]
+	}
+	// Returns the previous value if the transaction id was already in use
+	pub fn insert(&self, txid: TxId, value: V, deadline: T) -> Option<V> {
+		let mut shard = self.shard(&txid).lock().unwrap();
+		let old = shard.entries.insert(txid, (value, deadline));
+		if let Some((_, old_deadline)) = &old {
+			shard.deadlines.remove(&(*old_deadline, txid));
+		}
+		shard.deadlines.insert((deadline, txid));
+		old.map(|(v, _)| v)
+	}
+	// Removes a transaction, e.g. when its response arrives
+	pub fn take(&self, txid: &TxId) -> Option<V> {
+		let mut shard = self.shard(txid).lock().unwrap();
+		let (value, deadline) = shard.entries.remove(txid)?;
+		shard.deadlines.remove(&(deadline, *txid));
+		Some(value)
+	}
+	pub fn contains(&self, txid: &TxId) -> bool {
+		self.shard(txid).lock().unwrap().entries.contains_key(txid)
+	}
+	// Removes and returns every transaction whose deadline is at or before now
+	pub fn expire(&self, now: T) -> Vec<(TxId, V)> {
+		let mut ret = Vec::new();
+		for shard in self.shards.iter() {
+			let mut shard = shard.lock().unwrap();
+			while let Some(&(deadline, txid)) = shard.deadlines.first() {
+				if deadline > now {
+					break;
+				}
+				shard.deadlines.pop_first();
+				if let Some((value, _)) = shard.entries.remove(&txid) {
+					ret.push((txid, value));
+				}
+			}
+		}
+		ret
+	}
+	pub fn next_deadline(&self) -> Option<T> {
+		self.shards
+			.iter()
+			.filter_map(|s| s.lock().unwrap().deadlines.first().map(|(d, _)| *d))
+			.min()
+	}
+	pub fn len(&self) -> usize {
+		self.shards.iter().map(|s| s.lock().unwrap().entries.len()).sum()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+impl<V, T: Timestamp> Default for TxTable<V, T> {
+	fn default() -> Self {
+		Self::new(16)
+	}
+}
+
+// Retransmission parameters (RFC 8489 section 6.2.1)
+pub const RTO: Duration = Duration::from_millis(500);
+pub const RC: u32 = 7;
+pub const RM: u32 = 16;
+// Reliable transports send once and wait this long (section 6.2.2)
+pub const TI: Duration = Duration::from_millis(39_500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionState {
+	Pending,
+	// A response with the request's transaction id arrived
+	Completed,
+	TimedOut,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransmitErr<E> {
+	// The request is bigger than the transport's MTU, so it would be fragmented or dropped
+	TooLarge,
+	Transport(E),
+}
+
+// One request from a client: sent at 0, RTO, 3 RTO, 7 RTO... until Rc requests have gone out, then
+// given Rm RTO more to be answered. Over a reliable transport the request is sent once and given Ti.
+#[derive(Debug, Clone)]
+pub struct ClientTransaction<T: Timestamp = Instant> {
+	request: Vec<u8>,
+	txid: TxId,
+	rto: Duration,
+	rc: u32,
+	rm: u32,
+	reliable: bool,
+	sent: u32,
+	next: T,
+	state: TransactionState,
+}
+impl<T: Timestamp> ClientTransaction<T> {
+	// request is the encoded request, which is sent as is every time
+	pub fn new(request: Vec<u8>, now: T) -> Result<Self, StunDecodeErr> {
+		let txid = Stun::decode(&request)?.txid;
+		Ok(Self {
+			request,
+			txid,
+			rto: RTO,
+			rc: RC,
+			rm: RM,
+			reliable: false,
+			sent: 0,
+			next: now,
+			state: TransactionState::Pending,
+		})
+	}
+	pub fn with_rto(mut self, rto: Duration) -> Self {
+		self.rto = rto;
+		self
+	}
+	pub fn with_limits(mut self, rc: u32, rm: u32) -> Self {
+		self.rc = rc.max(1);
+		self.rm = rm;
+		self
+	}
+	pub fn with_reliable(mut self, reliable: bool) -> Self {
+		self.reliable = reliable;
+		self
+	}
+	// Retransmits (or not) to suit the transport
+	pub fn over<Tr: Transport + ?Sized>(self, transport: &Tr) -> Self {
+		self.with_reliable(transport.reliable())
+	}
+	pub fn txid(&self) -> TxId {
+		self.txid
+	}
+	pub fn state(&self) -> TransactionState {
+		self.state
+	}
+	// How many times the request has been sent
+	pub fn sent(&self) -> u32 {
+		self.sent
+	}
+	fn sends(&self) -> u32 {
+		if self.reliable {
+			1
+		} else {
+			self.rc
+		}
+	}
+	pub fn poll_timeout(&self) -> Option<T> {
+		(self.state == TransactionState::Pending).then_some(self.next)
+	}
+	pub fn handle_timeout(&mut self, now: T) {
+		let done_sending = self.sent >= self.sends();
+		if self.state == TransactionState::Pending && done_sending && now >= self.next {
+			self.state = TransactionState::TimedOut;
+		}
+	}
+	// Returns the request if it's due to be (re)sent
+	pub fn poll_transmit(&mut self, now: T) -> Option<&[u8]> {
+		self.handle_timeout(now);
+		if self.state != TransactionState::Pending || self.sent >= self.sends() || now < self.next {
+			return None;
+		}
+		self.sent += 1;
+		self.next = now
+			+ if self.reliable {
+				TI
+			} else if self.sent == self.rc {
+				self.rto.saturating_mul(self.rm)
+			} else {
+				self.rto.saturating_mul(1 << (self.sent - 1).min(31))
+			};
+		Some(&self.request)
+	}
+	// Sends the request through transport if it's due, returning whether it was sent. A request
+	// over the transport's MTU is never sent (re-encode it smaller, e.g. without SOFTWARE).
+	pub fn transmit<Tr: Transport + ?Sized>(
+		&mut self,
+		transport: &mut Tr,
+		to: SocketAddr,
+		now: T,
+	) -> Result<bool, TransmitErr<Tr::Error>> {
+		if self.request.len() > transport.mtu().0 {
+			return Err(TransmitErr::TooLarge);
+		}
+		let Some(req) = self.poll_transmit(now) else {
+			return Ok(false);
+		};
+		transport.send(req, to).map_err(TransmitErr::Transport)?;
+		Ok(true)
+	}
+	// Returns the response if packet answers the request. Authenticating it is up to the caller.
+	pub fn handle<'i>(&mut self, packet: &'i [u8]) -> Option<Stun<'i>> {
+		if self.state != TransactionState::Pending {
+			return None;
+		}
+		let msg = Stun::decode(packet).ok()?;
+		if msg.txid != self.txid || !matches!(msg.typ, StunTyp::Res(_) | StunTyp::Err(_)) {
+			return None;
+		}
+		self.state = TransactionState::Completed;
+		Some(msg)
+	}
+}
+
+// Redirects are followed at most this many times per request
+pub const MAX_REDIRECTS: usize = 3;
+
+// Where a 300 Try Alternate response sends us (RFC 8489 section 10). domain is the name to check the
+// alternate's certificate against, for TLS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alternate {
+	pub server: SocketAddr,
+	pub domain: Option<String>,
+}
+impl Alternate {
+	// Only for 300 error responses that carry an ALTERNATE-SERVER. Authenticating the response is up
+	// to the caller.
+	pub fn from_response(res: &Stun<'_>) -> Option<Self> {
+		if !matches!(res.typ, StunTyp::Err(_)) {
+			return None;
+		}
+		let flat = res.flat();
+		if flat.error?.code != 300 {
+			return None;
+		}
+		Some(Self {
+			server: flat.alternate_server?,
+			domain: flat.alternate_domain.map(str::to_string),
+		})
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectErr {
+	// We've already tried this server
+	Loop(SocketAddr),
+	TooMany,
+}
+
+// The servers a request has been sent to, so that redirects can't go in circles
+#[derive(Debug, Clone)]
+pub struct Redirects {
+	visited: Vec<SocketAddr>,
+	limit: usize,
+}
+impl Redirects {
+	pub fn new(server: SocketAddr) -> Self {
+		Self {
+			visited: vec![server],
+			limit: MAX_REDIRECTS,
+		}
+	}
+	pub fn with_limit(mut self, limit: usize) -> Self {
+		self.limit = limit;
+		self
+	}
+	// The server to send the request to next
+	pub fn current(&self) -> SocketAddr {
+		*self.visited.last().unwrap()
+	}
+	pub fn visited(&self) -> &[SocketAddr] {
+		&self.visited
+	}
+	pub fn follow(&mut self, to: SocketAddr) -> Result<(), RedirectErr> {
+		if self.visited.contains(&to) {
+			return Err(RedirectErr::Loop(to));
+		}
+		if self.visited.len() > self.limit {
+			return Err(RedirectErr::TooMany);
+		}
+		self.visited.push(to);
+		Ok(())
+	}
+}
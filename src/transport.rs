@@ -0,0 +1,80 @@
+use std::io::{self, Write};
+use std::net::{SocketAddr, UdpSocket};
+
+use crate::SizeLimit;
+
+pub mod proxy;
+pub mod stream;
+
+// Where the client machinery hands off encoded messages. Reliable transports (TCP, TLS) don't need
+// retransmissions, unreliable ones (UDP) do.
+pub trait Transport {
+	type Error;
+	fn send(&mut self, msg: &[u8], to: SocketAddr) -> Result<(), Self::Error>;
+	fn reliable(&self) -> bool;
+	// The largest message worth sending (for Stun::encode_limit). Datagrams over it may be
+	// fragmented or dropped along the way.
+	fn mtu(&self) -> SizeLimit {
+		SizeLimit::WIRE
+	}
+}
+impl<T: Transport + ?Sized> Transport for &mut T {
+	type Error = T::Error;
+	fn send(&mut self, msg: &[u8], to: SocketAddr) -> Result<(), T::Error> {
+		(**self).send(msg, to)
+	}
+	fn reliable(&self) -> bool {
+		(**self).reliable()
+	}
+	fn mtu(&self) -> SizeLimit {
+		(**self).mtu()
+	}
+}
+
+// Without path MTU discovery, the limits recommended for the socket's address family
+fn udp_mtu(sock: &UdpSocket) -> SizeLimit {
+	match sock.local_addr() {
+		Ok(SocketAddr::V6(_)) => SizeLimit::IPV6,
+		_ => SizeLimit::IPV4,
+	}
+}
+
+impl Transport for UdpSocket {
+	type Error = io::Error;
+	fn send(&mut self, msg: &[u8], to: SocketAddr) -> Result<(), io::Error> {
+		self.send_to(msg, to).map(|_| ())
+	}
+	fn reliable(&self) -> bool {
+		false
+	}
+	fn mtu(&self) -> SizeLimit {
+		udp_mtu(self)
+	}
+}
+impl Transport for &UdpSocket {
+	type Error = io::Error;
+	fn send(&mut self, msg: &[u8], to: SocketAddr) -> Result<(), io::Error> {
+		self.send_to(msg, to).map(|_| ())
+	}
+	fn reliable(&self) -> bool {
+		false
+	}
+	fn mtu(&self) -> SizeLimit {
+		udp_mtu(self)
+	}
+}
+
+// A connected byte stream (TcpStream, a TLS stream, a tunnel...). STUN messages carry their own
+// length so they can be written back to back; the destination address is ignored.
+#[derive(Debug)]
+pub struct Framed<W>(pub W);
+impl<W: Write> Transport for Framed<W> {
+	type Error = io::Error;
+	fn send(&mut self, msg: &[u8], _: SocketAddr) -> Result<(), io::Error> {
+		self.0.write_all(msg)?;
+		self.0.flush()
+	}
+	fn reliable(&self) -> bool {
+		true
+	}
+}
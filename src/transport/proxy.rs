@@ -0,0 +1,177 @@
+// Connecting the stream transports (TCP, and TLS layered on top by the caller) through a proxy.
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+
+use crate::base64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyAuth {
+	pub username: String,
+	pub password: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Proxy {
+	Socks5 {
+		addr: SocketAddr,
+		auth: Option<ProxyAuth>,
+	},
+	HttpConnect {
+		addr: SocketAddr,
+		auth: Option<ProxyAuth>,
+	},
+}
+impl Proxy {
+	// Opens a tunnel through the proxy to host:port. host may be a name (resolved by the proxy) or an
+	// IP address.
+	pub fn connect(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+		match self {
+			Self::Socks5 { addr, auth } => socks5(*addr, auth.as_ref(), host, port),
+			Self::HttpConnect { addr, auth } => http_connect(*addr, auth.as_ref(), host, port),
+		}
+	}
+}
+
+fn proxy_err(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::ConnectionRefused, msg)
+}
+
+// RFC 1928 (+ RFC 1929 username / password auth)
+fn socks5(
+	proxy: SocketAddr,
+	auth: Option<&ProxyAuth>,
+	host: &str,
+	port: u16,
+) -> io::Result<TcpStream> {
+	let mut sock = TcpStream::connect(proxy)?;
+	match auth {
+		None => sock.write_all(&[5, 1, 0])?,
+		Some(_) => sock.write_all(&[5, 2, 0, 2])?,
+	}
+	let mut reply = [0u8; 2];
+	sock.read_exact(&mut reply)?;
+	match (reply, auth) {
+		([5, 0], _) => {}
+		([5, 2], Some(auth)) => {
+			let (user, pass) = (auth.username.as_bytes(), auth.password.as_bytes());
+			if user.len() > 255 || pass.len() > 255 {
+				return Err(proxy_err("SOCKS5 credentials too long"));
+			}
+			let mut req = vec![1, user.len() as u8];
+			req.extend_from_slice(user);
+			req.push(pass.len() as u8);
+			req.extend_from_slice(pass);
+			sock.write_all(&req)?;
+			sock.read_exact(&mut reply)?;
+			if reply[1] != 0 {
+				return Err(proxy_err("SOCKS5 authentication failed"));
+			}
+		}
+		_ => {
+			return Err(proxy_err(
+				"SOCKS5 proxy rejected our authentication methods",
+			))
+		}
+	}
+
+	let mut req = vec![5, 1, 0];
+	match host.parse::<IpAddr>() {
+		Ok(IpAddr::V4(ip)) => {
+			req.push(1);
+			req.extend_from_slice(&ip.octets());
+		}
+		Ok(IpAddr::V6(ip)) => {
+			req.push(4);
+			req.extend_from_slice(&ip.octets());
+		}
+		Err(_) if host.len() <= 255 => {
+			req.extend_from_slice(&[3, host.len() as u8]);
+			req.extend_from_slice(host.as_bytes());
+		}
+		Err(_) => return Err(proxy_err("SOCKS5 host name too long")),
+	}
+	req.extend_from_slice(&port.to_be_bytes());
+	sock.write_all(&req)?;
+
+	let mut reply = [0u8; 4];
+	sock.read_exact(&mut reply)?;
+	if reply[1] != 0 {
+		return Err(proxy_err("SOCKS5 CONNECT failed"));
+	}
+	// Skip over the bound address
+	let skip = match reply[3] {
+		1 => 4,
+		4 => 16,
+		3 => {
+			let mut len = [0u8];
+			sock.read_exact(&mut len)?;
+			len[0] as usize
+		}
+		_ => return Err(proxy_err("SOCKS5 reply has an unknown address type")),
+	};
+	sock.read_exact(&mut vec![0; skip + 2])?;
+	Ok(sock)
+}
+
+fn http_connect(
+	proxy: SocketAddr,
+	auth: Option<&ProxyAuth>,
+	host: &str,
+	port: u16,
+) -> io::Result<TcpStream> {
+	let mut sock = TcpStream::connect(proxy)?;
+	let target = match host.parse::<IpAddr>() {
+		Ok(IpAddr::V6(ip)) => format!("[{ip}]:{port}"),
+		_ => format!("{host}:{port}"),
+	};
+	let mut req = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+	if let Some(auth) = auth {
+		let credentials = base64(format!("{}:{}", auth.username, auth.password).as_bytes());
+		req += &format!("Proxy-Authorization: Basic {credentials}\r\n");
+	}
+	req += "\r\n";
+	sock.write_all(req.as_bytes())?;
+
+	// Read the response one byte at a time so that nothing past the headers is consumed
+	let mut reader = BufReader::with_capacity(1, &sock);
+	let mut status = String::new();
+	reader.read_line(&mut status)?;
+	if status.split_whitespace().nth(1) != Some("200") {
+		return Err(proxy_err("HTTP CONNECT refused"));
+	}
+	loop {
+		let mut line = String::new();
+		if reader.read_line(&mut line)? == 0 {
+			return Err(io::ErrorKind::UnexpectedEof.into());
+		}
+		if line == "\r\n" || line == "\n" {
+			break;
+		}
+	}
+	Ok(sock)
+}
+
+// Chooses a proxy per target host. Rules are checked in order: a rule matches a host exactly, or
+// as a domain suffix when it starts with a '.'. A None proxy means connect directly.
+#[derive(Debug, Clone, Default)]
+pub struct ProxyRules {
+	pub rules: Vec<(String, Option<Proxy>)>,
+	pub default: Option<Proxy>,
+}
+impl ProxyRules {
+	pub fn proxy_for(&self, host: &str) -> Option<&Proxy> {
+		let rule = self.rules.iter().find(|(pattern, _)| {
+			pattern == host || (pattern.starts_with('.') && host.ends_with(pattern.as_str()))
+		});
+		match rule {
+			Some((_, proxy)) => proxy.as_ref(),
+			None => self.default.as_ref(),
+		}
+	}
+	pub fn connect(&self, host: &str, port: u16) -> io::Result<TcpStream> {
+		match self.proxy_for(host) {
+			Some(proxy) => proxy.connect(host, port),
+			None => TcpStream::connect((host, port)),
+		}
+	}
+}
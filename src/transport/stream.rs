@@ -0,0 +1,169 @@
+// The client side of STUN over TCP and TLS (RFC 8489 section 6.2.2 and 6.2.3). Nothing is
+// retransmitted on a stream: a request is sent once and given Ti (39.5 seconds) to be answered.
+// The connection is left open afterwards, since closing it would also close the NAT binding that
+// the response described.
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use crate::client::ClientErr;
+use crate::clock::{Clock, SystemClock, Timestamp};
+use crate::record::{Direction, Recording};
+use crate::server::stream::Reassembler;
+use crate::transactions::{ClientTransaction, TransactionState};
+use crate::transport::proxy::ProxyRules;
+use crate::Stun;
+
+// A stream with a TcpStream underneath, which is what read timeouts get set on
+pub trait Connection: Read + Write {
+	fn tcp(&self) -> &TcpStream;
+}
+impl Connection for TcpStream {
+	fn tcp(&self) -> &TcpStream {
+		self
+	}
+}
+#[cfg(feature = "tls")]
+impl Connection for rustls::StreamOwned<rustls::ClientConnection, TcpStream> {
+	fn tcp(&self) -> &TcpStream {
+		&self.sock
+	}
+}
+
+#[derive(Debug)]
+pub struct StreamClient<S, C: Clock = SystemClock> {
+	stream: S,
+	frames: Reassembler,
+	clock: C,
+	recording: Recording,
+}
+impl StreamClient<TcpStream> {
+	// stun: over TCP
+	pub fn connect(server: SocketAddr) -> io::Result<Self> {
+		let tcp = TcpStream::connect(server)?;
+		tcp.set_nodelay(true)?;
+		Ok(Self::new(tcp))
+	}
+	// stun: over TCP, through whichever proxy the rules pick for host (or directly)
+	pub fn connect_via(rules: &ProxyRules, host: &str, port: u16) -> io::Result<Self> {
+		let tcp = rules.connect(host, port)?;
+		tcp.set_nodelay(true)?;
+		Ok(Self::new(tcp))
+	}
+}
+#[cfg(feature = "tls")]
+impl StreamClient<rustls::StreamOwned<rustls::ClientConnection, TcpStream>> {
+	// stuns: the handshake happens along with the first request
+	pub fn connect_tls(
+		server: SocketAddr,
+		server_name: &str,
+		config: std::sync::Arc<rustls::ClientConfig>,
+	) -> io::Result<Self> {
+		Self::tls(server_name, config, || TcpStream::connect(server))
+	}
+	// stuns: through whichever proxy the rules pick for host, which is also the name the server's
+	// certificate is checked against
+	pub fn connect_tls_via(
+		rules: &ProxyRules,
+		host: &str,
+		port: u16,
+		config: std::sync::Arc<rustls::ClientConfig>,
+	) -> io::Result<Self> {
+		Self::tls(host, config, || rules.connect(host, port))
+	}
+	fn tls(
+		server_name: &str,
+		config: std::sync::Arc<rustls::ClientConfig>,
+		connect: impl FnOnce() -> io::Result<TcpStream>,
+	) -> io::Result<Self> {
+		let name = rustls::pki_types::ServerName::try_from(server_name.to_string())
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+		let conn = rustls::ClientConnection::new(config, name).map_err(io::Error::other)?;
+		let tcp = connect()?;
+		tcp.set_nodelay(true)?;
+		Ok(Self::new(rustls::StreamOwned::new(conn, tcp)))
+	}
+}
+impl<S: Connection> StreamClient<S> {
+	pub fn new(stream: S) -> Self {
+		Self {
+			stream,
+			frames: Reassembler::new(),
+			clock: SystemClock,
+			recording: Recording::default(),
+		}
+	}
+}
+impl<S: Connection, C: Clock> StreamClient<S, C> {
+	// The clock that times the requests
+	pub fn with_clock<C2: Clock>(self, clock: C2) -> StreamClient<S, C2> {
+		StreamClient {
+			stream: self.stream,
+			frames: self.frames,
+			clock,
+			recording: self.recording,
+		}
+	}
+	// Records every request sent and every message received
+	pub fn with_recording(mut self, recording: Recording) -> Self {
+		self.recording = recording;
+		self
+	}
+	pub fn get_ref(&self) -> &S {
+		&self.stream
+	}
+	pub fn into_inner(self) -> S {
+		self.stream
+	}
+	// Sends an encoded request and waits for the response with its transaction id, which is decoded
+	// from buff. Anything else that arrives in the meantime (indications, ChannelData, responses to
+	// requests that were given up on) is dropped.
+	pub fn request<'b>(
+		&mut self,
+		request: &[u8],
+		buff: &'b mut [u8],
+	) -> Result<Stun<'b>, ClientErr> {
+		let server = self.stream.tcp().peer_addr()?;
+		let mut tx =
+			ClientTransaction::new(request.to_vec(), self.clock.now())?.with_reliable(true);
+		if let Some(req) = tx.poll_transmit(self.clock.now()) {
+			self.recording.record(Direction::Outbound, server, req);
+			self.stream.write_all(req)?;
+			self.stream.flush()?;
+		}
+		loop {
+			while let Some(frame) = self.frames.next_frame()? {
+				self.recording.record(Direction::Inbound, server, frame);
+				if tx.handle(frame).is_some() {
+					let Some(buff) = buff.get_mut(..frame.len()) else {
+						let e = io::Error::new(io::ErrorKind::InvalidInput, "buff is too small");
+						return Err(e.into());
+					};
+					buff.copy_from_slice(frame);
+					return Ok(Stun::decode(buff)?);
+				}
+			}
+			let now = self.clock.now();
+			tx.handle_timeout(now);
+			let Some(deadline) = tx.poll_timeout() else {
+				debug_assert_eq!(tx.state(), TransactionState::TimedOut);
+				return Err(ClientErr::Timeout);
+			};
+			// A zero timeout would mean blocking forever
+			let timeout = deadline
+				.saturating_duration_since(now)
+				.max(Duration::from_millis(1));
+			self.stream.tcp().set_read_timeout(Some(timeout))?;
+			match self.frames.read_from(&mut self.stream) {
+				Ok(0) => return Err(ClientErr::Io(io::ErrorKind::UnexpectedEof.into())),
+				Ok(_) => {}
+				Err(e)
+					if matches!(
+						e.kind(),
+						io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+					) => {}
+				Err(e) => return Err(e.into()),
+			}
+		}
+	}
+}
@@ -0,0 +1,437 @@
+// Typed TURN messages (RFC 8656, plus RFC 6062 for TCP relaying). Each type holds the attributes
+// its message carries, so building one can't leave out a required attribute, and from_stun rejects
+// messages that are missing one. Authentication attributes (USERNAME, REALM, NONCE,
+// MESSAGE-INTEGRITY) and FINGERPRINT are passed to encode separately, since they have to come after
+// everything else.
+use std::net::SocketAddr;
+
+use crate::attr::{
+	typ, AddressFamily, ChannelNumber, Icmp, Lifetime, RequestedTransport, StunAttr,
+};
+use crate::attrs::flat::Flat;
+use crate::{Stun, StunEncodeErr, StunMethod, StunTyp, TxId};
+
+pub mod expiry;
+pub mod server;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TurnErr {
+	// The message has a different class or method
+	WrongType,
+	// A required attribute (by type) is missing
+	Missing(u16),
+	// An attribute (by type) has a value that isn't allowed, or isn't allowed in combination with
+	// the rest of the message
+	Invalid(u16),
+}
+
+pub trait TurnMessage<'i>: Sized {
+	fn typ(&self) -> StunTyp;
+	// Pushes the message's attributes, not including authentication or FINGERPRINT
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>);
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr>;
+
+	// Encodes the message with auth appended to its attributes
+	fn encode(
+		&self,
+		txid: TxId,
+		auth: &[StunAttr<'i>],
+		buff: &mut [u8],
+	) -> Result<usize, StunEncodeErr> {
+		let mut attrs = Vec::new();
+		self.push_attrs(&mut attrs);
+		attrs.extend_from_slice(auth);
+		Stun {
+			typ: self.typ(),
+			txid,
+			attrs: attrs[..].into(),
+		}
+		.encode(buff)
+	}
+}
+
+fn request<'i>(msg: &Stun<'i>, method: StunMethod) -> Result<Flat<'i>, TurnErr> {
+	match &msg.typ {
+		StunTyp::Req(m) if *m == method => Ok(msg.flat()),
+		_ => Err(TurnErr::WrongType),
+	}
+}
+fn indication<'i>(msg: &Stun<'i>, method: StunMethod) -> Result<Flat<'i>, TurnErr> {
+	match &msg.typ {
+		StunTyp::Ind(m) if *m == method => Ok(msg.flat()),
+		_ => Err(TurnErr::WrongType),
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocate {
+	pub transport: RequestedTransport,
+	// Without one the relayed address is IPv4 (RFC 8656 section 7.2)
+	pub family: Option<AddressFamily>,
+	pub lifetime: Option<Lifetime>,
+	// Some(true) asks for an even port and for the next port to be reserved
+	pub even_port: Option<bool>,
+	pub reservation_token: Option<u64>,
+	pub dont_fragment: bool,
+}
+impl Allocate {
+	pub fn new(transport: RequestedTransport) -> Self {
+		Self {
+			transport,
+			family: None,
+			lifetime: None,
+			even_port: None,
+			reservation_token: None,
+			dont_fragment: false,
+		}
+	}
+}
+impl<'i> TurnMessage<'i> for Allocate {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Req(StunMethod::Allocate)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.push(StunAttr::RequestedTransport(self.transport));
+		if let Some(family) = self.family {
+			out.push(StunAttr::RequestedFamily(family));
+		}
+		if let Some(lifetime) = self.lifetime {
+			out.push(StunAttr::Lifetime(lifetime));
+		}
+		if let Some(reserve) = self.even_port {
+			out.push(StunAttr::even_port(reserve));
+		}
+		if let Some(token) = self.reservation_token {
+			out.push(StunAttr::ReservationToken(token));
+		}
+		if self.dont_fragment {
+			out.push(StunAttr::DontFragment);
+		}
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		let flat = request(msg, StunMethod::Allocate)?;
+		let transport = flat
+			.requested_transport
+			.ok_or(TurnErr::Missing(typ::REQUESTED_TRANSPORT))?;
+		// A reservation token picks the port already, so it can't be combined with EVEN-PORT
+		if flat.even_port.is_some() && flat.reservation_token.is_some() {
+			return Err(TurnErr::Invalid(typ::RESERVATION_TOKEN));
+		}
+		Ok(Self {
+			transport,
+			family: flat.requested_family,
+			lifetime: flat.lifetime,
+			even_port: flat.even_port,
+			reservation_token: flat.reservation_token,
+			dont_fragment: flat.dont_fragment.is_some(),
+		})
+	}
+}
+
+// The success response to an Allocate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocated {
+	pub relayed: SocketAddr,
+	pub mapped: SocketAddr,
+	pub lifetime: Lifetime,
+	pub reservation_token: Option<u64>,
+}
+impl<'i> TurnMessage<'i> for Allocated {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Res(StunMethod::Allocate)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.push(StunAttr::XRelayed(self.relayed));
+		out.push(StunAttr::Lifetime(self.lifetime));
+		if let Some(token) = self.reservation_token {
+			out.push(StunAttr::ReservationToken(token));
+		}
+		out.push(StunAttr::XMapped(self.mapped));
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		let flat = match &msg.typ {
+			StunTyp::Res(StunMethod::Allocate) => msg.flat(),
+			_ => return Err(TurnErr::WrongType),
+		};
+		Ok(Self {
+			relayed: flat
+				.xrelayed
+				.ok_or(TurnErr::Missing(typ::XOR_RELAYED_ADDRESS))?,
+			mapped: flat
+				.xmapped
+				.ok_or(TurnErr::Missing(typ::XOR_MAPPED_ADDRESS))?,
+			lifetime: flat.lifetime.ok_or(TurnErr::Missing(typ::LIFETIME))?,
+			reservation_token: flat.reservation_token,
+		})
+	}
+}
+
+// A LIFETIME of 0 deletes the allocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Refresh {
+	pub lifetime: Option<Lifetime>,
+}
+impl<'i> TurnMessage<'i> for Refresh {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Req(StunMethod::Refresh)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		if let Some(lifetime) = self.lifetime {
+			out.push(StunAttr::Lifetime(lifetime));
+		}
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		let flat = request(msg, StunMethod::Refresh)?;
+		Ok(Self {
+			lifetime: flat.lifetime,
+		})
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendIndication<'i> {
+	pub peer: SocketAddr,
+	pub data: &'i [u8],
+	pub dont_fragment: bool,
+}
+impl<'i> TurnMessage<'i> for SendIndication<'i> {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Ind(StunMethod::Send)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.push(StunAttr::XPeer(self.peer));
+		if self.dont_fragment {
+			out.push(StunAttr::DontFragment);
+		}
+		out.push(StunAttr::data(self.data));
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		let flat = indication(msg, StunMethod::Send)?;
+		Ok(Self {
+			peer: flat.xpeer.ok_or(TurnErr::Missing(typ::XOR_PEER_ADDRESS))?,
+			data: flat.data.ok_or(TurnErr::Missing(typ::DATA))?,
+			dont_fragment: flat.dont_fragment.is_some(),
+		})
+	}
+}
+
+// Data relayed from a peer, or (with ICMP instead of DATA) an ICMP error about the peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataIndication<'i> {
+	pub peer: SocketAddr,
+	pub data: Option<&'i [u8]>,
+	pub icmp: Option<Icmp>,
+}
+impl<'i> TurnMessage<'i> for DataIndication<'i> {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Ind(StunMethod::Data)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.push(StunAttr::XPeer(self.peer));
+		if let Some(icmp) = self.icmp {
+			out.push(StunAttr::Icmp(icmp));
+		}
+		if let Some(data) = self.data {
+			out.push(StunAttr::data(data));
+		}
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		let flat = indication(msg, StunMethod::Data)?;
+		let peer = flat.xpeer.ok_or(TurnErr::Missing(typ::XOR_PEER_ADDRESS))?;
+		if flat.data.is_none() && flat.icmp.is_none() {
+			return Err(TurnErr::Missing(typ::DATA));
+		}
+		Ok(Self {
+			peer,
+			data: flat.data,
+			icmp: flat.icmp,
+		})
+	}
+}
+
+// Installs (or refreshes) permissions for the IP addresses of the peers. Ports are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatePermission {
+	pub peers: Vec<SocketAddr>,
+}
+impl<'i> TurnMessage<'i> for CreatePermission {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Req(StunMethod::CreatePermission)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.extend(self.peers.iter().map(|peer| StunAttr::XPeer(*peer)));
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		request(msg, StunMethod::CreatePermission)?;
+		let peers: Vec<_> = msg
+			.into_iter()
+			.filter_map(|attr| match attr {
+				StunAttr::XPeer(peer) => Some(peer),
+				_ => None,
+			})
+			.collect();
+		if peers.is_empty() {
+			return Err(TurnErr::Missing(typ::XOR_PEER_ADDRESS));
+		}
+		Ok(Self { peers })
+	}
+}
+
+// The channel numbers that ChannelData can use (RFC 8656 section 12)
+pub const CHANNELS: std::ops::RangeInclusive<u16> = ChannelNumber::MIN..=ChannelNumber::MAX;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelBind {
+	pub channel: ChannelNumber,
+	pub peer: SocketAddr,
+}
+impl<'i> TurnMessage<'i> for ChannelBind {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Req(StunMethod::ChannelBind)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.push(StunAttr::channel(self.channel));
+		out.push(StunAttr::XPeer(self.peer));
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		let flat = request(msg, StunMethod::ChannelBind)?;
+		let channel = flat.channel.ok_or(TurnErr::Missing(typ::CHANNEL_NUMBER))?;
+		if !channel.valid() {
+			return Err(TurnErr::Invalid(typ::CHANNEL_NUMBER));
+		}
+		Ok(Self {
+			channel,
+			peer: flat.xpeer.ok_or(TurnErr::Missing(typ::XOR_PEER_ADDRESS))?,
+		})
+	}
+}
+
+// RFC 6062 TCP relaying. Connect asks the server to open a TCP connection to the peer, and its
+// response carries the id of the new connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connect {
+	pub peer: SocketAddr,
+}
+impl<'i> TurnMessage<'i> for Connect {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Req(StunMethod::Connect)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.push(StunAttr::XPeer(self.peer));
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		let flat = request(msg, StunMethod::Connect)?;
+		Ok(Self {
+			peer: flat.xpeer.ok_or(TurnErr::Missing(typ::XOR_PEER_ADDRESS))?,
+		})
+	}
+}
+
+// The success response to a Connect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connected {
+	pub connection_id: u32,
+}
+impl<'i> TurnMessage<'i> for Connected {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Res(StunMethod::Connect)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.push(StunAttr::connection_id(self.connection_id));
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		let flat = match &msg.typ {
+			StunTyp::Res(StunMethod::Connect) => msg.flat(),
+			_ => return Err(TurnErr::WrongType),
+		};
+		Ok(Self {
+			connection_id: flat
+				.connection_id
+				.ok_or(TurnErr::Missing(typ::CONNECTION_ID))?,
+		})
+	}
+}
+
+// Sent on a new TCP connection to the server (the data connection) to join it to the peer
+// connection with this id. After the success response the data connection carries raw data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionBind {
+	pub connection_id: u32,
+}
+impl<'i> TurnMessage<'i> for ConnectionBind {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Req(StunMethod::ConnectionBind)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.push(StunAttr::connection_id(self.connection_id));
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		let flat = request(msg, StunMethod::ConnectionBind)?;
+		Ok(Self {
+			connection_id: flat
+				.connection_id
+				.ok_or(TurnErr::Missing(typ::CONNECTION_ID))?,
+		})
+	}
+}
+
+// Tells the client that a peer connected to its relayed address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionAttempt {
+	pub peer: SocketAddr,
+	pub connection_id: u32,
+}
+impl<'i> TurnMessage<'i> for ConnectionAttempt {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Ind(StunMethod::ConnectionAttempt)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.push(StunAttr::XPeer(self.peer));
+		out.push(StunAttr::connection_id(self.connection_id));
+	}
+	fn from_stun(msg: &Stun<'i>) -> Result<Self, TurnErr> {
+		let flat = indication(msg, StunMethod::ConnectionAttempt)?;
+		Ok(Self {
+			peer: flat.xpeer.ok_or(TurnErr::Missing(typ::XOR_PEER_ADDRESS))?,
+			connection_id: flat
+				.connection_id
+				.ok_or(TurnErr::Missing(typ::CONNECTION_ID))?,
+		})
+	}
+}
+
+// The 4 byte header framing that bound channels use instead of Send / Data indications
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelData<'i> {
+	pub channel: u16,
+	pub data: &'i [u8],
+}
+impl<'i> ChannelData<'i> {
+	// Bytes past the declared length (e.g. stream padding) are ignored
+	pub fn decode(buff: &'i [u8]) -> Option<Self> {
+		let (&[c0, c1, l0, l1], rest) = buff.split_first_chunk::<4>()?;
+		let channel = u16::from_be_bytes([c0, c1]);
+		if !CHANNELS.contains(&channel) {
+			return None;
+		}
+		let data = rest.get(..u16::from_be_bytes([l0, l1]) as usize)?;
+		Some(Self { channel, data })
+	}
+	pub fn len(&self) -> usize {
+		4 + self.data.len()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.data.is_empty()
+	}
+	// Writes the header and data, without padding. Returns the length written.
+	pub fn encode(&self, buff: &mut [u8]) -> Result<usize, StunEncodeErr> {
+		let len = u16::try_from(self.data.len()).map_err(|_| StunEncodeErr::TooLarge)?;
+		let buff = buff
+			.get_mut(..self.len())
+			.ok_or(StunEncodeErr::BufferTooSmall(self.len()))?;
+		buff[..2].copy_from_slice(&self.channel.to_be_bytes());
+		buff[2..4].copy_from_slice(&len.to_be_bytes());
+		buff[4..].copy_from_slice(self.data);
+		Ok(buff.len())
+	}
+}
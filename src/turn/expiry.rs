@@ -0,0 +1,86 @@
+// Expiry bookkeeping for an allocation's permissions and channel bindings. Deadlines are kept in
+// order (like TxTable's), so finding the next deadline or the expired entries only looks at the
+// front instead of scanning everything.
+use std::collections::{BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use crate::clock::Timestamp;
+
+// RFC 8656 sections 9 and 12
+pub const PERMISSION_LIFETIME: Duration = Duration::from_secs(300);
+pub const CHANNEL_LIFETIME: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Expiring {
+	Permission(IpAddr),
+	Channel(u16),
+}
+
+#[derive(Debug, Clone)]
+pub struct Expiries<T: Timestamp = Instant> {
+	deadlines: HashMap<Expiring, T>,
+	order: BTreeSet<(T, Expiring)>,
+}
+impl<T: Timestamp> Default for Expiries<T> {
+	fn default() -> Self {
+		Self {
+			deadlines: HashMap::new(),
+			order: BTreeSet::new(),
+		}
+	}
+}
+impl<T: Timestamp> Expiries<T> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	// Sets (or moves) an entry's deadline
+	pub fn set(&mut self, entry: Expiring, deadline: T) {
+		if let Some(old) = self.deadlines.insert(entry, deadline) {
+			self.order.remove(&(old, entry));
+		}
+		self.order.insert((deadline, entry));
+	}
+	// Installs or refreshes the permission for a peer's IP address
+	pub fn permit(&mut self, peer: IpAddr, now: T) {
+		self.set(Expiring::Permission(peer), now + PERMISSION_LIFETIME);
+	}
+	// Installs or refreshes a channel binding
+	pub fn bind(&mut self, channel: u16, now: T) {
+		self.set(Expiring::Channel(channel), now + CHANNEL_LIFETIME);
+	}
+	pub fn remove(&mut self, entry: &Expiring) -> Option<T> {
+		let deadline = self.deadlines.remove(entry)?;
+		self.order.remove(&(deadline, *entry));
+		Some(deadline)
+	}
+	// Whether the entry exists and hasn't reached its deadline, whether or not expire has run
+	pub fn is_live(&self, entry: &Expiring, now: T) -> bool {
+		self.deadlines.get(entry).is_some_and(|d| *d > now)
+	}
+	pub fn deadline(&self, entry: &Expiring) -> Option<T> {
+		self.deadlines.get(entry).copied()
+	}
+	pub fn next_deadline(&self) -> Option<T> {
+		self.order.first().map(|(d, _)| *d)
+	}
+	// Removes and returns every entry whose deadline is at or before now
+	pub fn expire(&mut self, now: T) -> Vec<Expiring> {
+		let mut ret = Vec::new();
+		while let Some(&(deadline, entry)) = self.order.first() {
+			if deadline > now {
+				break;
+			}
+			self.order.pop_first();
+			self.deadlines.remove(&entry);
+			ret.push(entry);
+		}
+		ret
+	}
+	pub fn len(&self) -> usize {
+		self.deadlines.len()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.deadlines.is_empty()
+	}
+}
@@ -0,0 +1,450 @@
+// A sans-io TURN server for UDP allocations. The caller owns the sockets: the ones clients talk to,
+// and one per relayed address. Relayed addresses are handed out from a pool given up front (e.g. a
+// range of ports bound at startup), so the server never has to open a socket itself. Requests are
+// authenticated with long-term credentials, challenging with a nonce that rotates every 10 minutes.
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use super::expiry::{Expiries, Expiring};
+use super::{
+	Allocate, Allocated, ChannelBind, ChannelData, CreatePermission, DataIndication, Refresh,
+	SendIndication, TurnMessage,
+};
+use crate::attr::{AddressFamily, Integrity, Lifetime, RequestedTransport, StunAttr};
+use crate::auth::{CredentialProvider, StunAuthErr};
+use crate::clock::Timestamp;
+use crate::record::{Direction, Recording};
+use crate::server::errors::{self, NonceProvider};
+use crate::{random_txid, RandomErr, Stun, StunMethod, StunTyp};
+
+const NONCE_LIFETIME: Duration = Duration::from_secs(600);
+
+// Identifies an allocation: the client's address, the server address it sent to, and the transport
+// protocol between them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FiveTuple {
+	pub client: SocketAddr,
+	pub server: SocketAddr,
+	pub transport: RequestedTransport,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TurnAction<'p> {
+	// A response to the client has been written into the buffer
+	Respond(usize),
+	// Send data from the relayed address's socket to the peer
+	Relay {
+		relayed: SocketAddr,
+		peer: SocketAddr,
+		data: &'p [u8],
+	},
+}
+
+#[derive(Debug)]
+struct Allocation<T: Timestamp> {
+	relayed: SocketAddr,
+	username: String,
+	expires: T,
+	expiries: Expiries<T>,
+	channels: HashMap<u16, SocketAddr>,
+}
+impl<T: Timestamp> Allocation<T> {
+	fn permitted(&self, peer: IpAddr, now: T) -> bool {
+		self.expiries.is_live(&Expiring::Permission(peer), now)
+	}
+	fn channel_peer(&self, channel: u16, now: T) -> Option<SocketAddr> {
+		let peer = self.channels.get(&channel)?;
+		self.expiries
+			.is_live(&Expiring::Channel(channel), now)
+			.then_some(*peer)
+	}
+	fn channel_for(&self, peer: SocketAddr, now: T) -> Option<u16> {
+		self.channels
+			.iter()
+			.find(|(channel, p)| {
+				**p == peer && self.expiries.is_live(&Expiring::Channel(**channel), now)
+			})
+			.map(|(channel, _)| *channel)
+	}
+}
+
+// The current nonce, and the one before it so that a rotation doesn't fail requests in flight
+struct Nonces<'a> {
+	realm: &'a str,
+	current: &'a str,
+	previous: &'a str,
+}
+impl NonceProvider for Nonces<'_> {
+	fn realm(&self) -> &str {
+		self.realm
+	}
+	fn nonce(&self, _: SocketAddr) -> String {
+		self.current.to_string()
+	}
+	fn is_valid(&self, nonce: &str, _: SocketAddr) -> bool {
+		nonce == self.current || nonce == self.previous
+	}
+}
+
+fn attrs_of<M: TurnMessage<'static>>(msg: M) -> Vec<StunAttr<'static>> {
+	let mut ret = Vec::new();
+	msg.push_attrs(&mut ret);
+	ret
+}
+
+#[derive(Debug)]
+pub struct TurnServer<P, T: Timestamp = Instant> {
+	realm: String,
+	credentials: P,
+	nonce: String,
+	previous_nonce: String,
+	nonce_expires: T,
+	free: Vec<SocketAddr>,
+	// The families in the pool, whether or not any are free right now
+	families: Vec<AddressFamily>,
+	allocations: HashMap<FiveTuple, Allocation<T>>,
+	relayed: HashMap<SocketAddr, FiveTuple>,
+	recording: Recording,
+}
+impl<P: CredentialProvider, T: Timestamp> TurnServer<P, T> {
+	pub fn new(
+		realm: &str,
+		credentials: P,
+		relays: impl IntoIterator<Item = SocketAddr>,
+		now: T,
+	) -> Result<Self, RandomErr> {
+		let nonce = random_txid()?.to_string();
+		let free: Vec<_> = relays.into_iter().collect();
+		let mut families: Vec<_> = free.iter().map(|a| AddressFamily::of(*a)).collect();
+		families.sort_by_key(|f| f.0);
+		families.dedup();
+		Ok(Self {
+			realm: realm.to_string(),
+			credentials,
+			previous_nonce: nonce.clone(),
+			nonce,
+			nonce_expires: now + NONCE_LIFETIME,
+			free,
+			families,
+			allocations: HashMap::new(),
+			relayed: HashMap::new(),
+			recording: Recording::default(),
+		})
+	}
+	// Records what clients send and what's written for them: responses, and data from peers. The
+	// data relayed to peers isn't STUN and isn't recorded.
+	pub fn with_recording(mut self, recording: Recording) -> Self {
+		self.recording = recording;
+		self
+	}
+	pub fn allocations(&self) -> usize {
+		self.allocations.len()
+	}
+	// The relayed address of the 5-tuple's allocation
+	pub fn relayed(&self, tuple: &FiveTuple) -> Option<SocketAddr> {
+		self.allocations.get(tuple).map(|a| a.relayed)
+	}
+
+	// Handles a packet from a client: a STUN message or ChannelData
+	pub fn handle<'p>(
+		&mut self,
+		packet: &'p [u8],
+		tuple: FiveTuple,
+		now: T,
+		buff: &mut [u8],
+	) -> Option<TurnAction<'p>> {
+		self.recording.record(Direction::Inbound, tuple.client, packet);
+		let action = self.handle_client(packet, tuple, now, buff);
+		if let Some(TurnAction::Respond(len)) = action {
+			self.recording.record(Direction::Outbound, tuple.client, &buff[..len]);
+		}
+		action
+	}
+	fn handle_client<'p>(
+		&mut self,
+		packet: &'p [u8],
+		tuple: FiveTuple,
+		now: T,
+		buff: &mut [u8],
+	) -> Option<TurnAction<'p>> {
+		if now >= self.nonce_expires {
+			// Without a new nonce the old one stays in use, and the next packet tries again
+			if let Ok(txid) = random_txid() {
+				self.previous_nonce = std::mem::replace(&mut self.nonce, txid.to_string());
+				self.nonce_expires = now + NONCE_LIFETIME;
+			}
+		}
+		if let Some(cd) = ChannelData::decode(packet) {
+			let alloc = self.allocations.get(&tuple).filter(|a| a.expires > now)?;
+			let peer = alloc.channel_peer(cd.channel, now)?;
+			if !alloc.permitted(peer.ip(), now) {
+				return None;
+			}
+			return Some(TurnAction::Relay {
+				relayed: alloc.relayed,
+				peer,
+				data: cd.data,
+			});
+		}
+		let msg = Stun::decode(packet).ok()?;
+		match msg.typ {
+			StunTyp::Ind(StunMethod::Send) => {
+				let send = SendIndication::from_stun(&msg).ok()?;
+				let alloc = self.allocations.get(&tuple).filter(|a| a.expires > now)?;
+				alloc
+					.permitted(send.peer.ip(), now)
+					.then_some(TurnAction::Relay {
+						relayed: alloc.relayed,
+						peer: send.peer,
+						data: send.data,
+					})
+			}
+			StunTyp::Req(StunMethod::Binding) => {
+				let attrs = [StunAttr::XMapped(tuple.client), StunAttr::Fingerprint];
+				msg.res(&attrs).encode(buff).ok().map(TurnAction::Respond)
+			}
+			StunTyp::Req(
+				StunMethod::Allocate
+				| StunMethod::Refresh
+				| StunMethod::CreatePermission
+				| StunMethod::ChannelBind,
+			) => self
+				.request(&msg, packet, tuple, now, buff)
+				.map(TurnAction::Respond),
+			StunTyp::Req(_) => errors::bad_request(&msg, buff)
+				.ok()
+				.map(TurnAction::Respond),
+			_ => None,
+		}
+	}
+	fn request(
+		&mut self,
+		msg: &Stun<'_>,
+		packet: &[u8],
+		tuple: FiveTuple,
+		now: T,
+		buff: &mut [u8],
+	) -> Option<usize> {
+		let nonces = Nonces {
+			realm: &self.realm,
+			current: &self.nonce,
+			previous: &self.previous_nonce,
+		};
+		if let Some(len) = errors::challenge(msg, tuple.client, &nonces, None, buff).ok()? {
+			return Some(len);
+		}
+		let auth = match Stun::decode_with_provider(packet, &self.credentials) {
+			Ok(auth) => auth,
+			Err(StunAuthErr::Decode(_)) => return None,
+			Err(StunAuthErr::Provider(_)) => return errors::server_error(msg, buff).ok(),
+			Err(_) => return errors::unauthorized(msg, &self.realm, &self.nonce, buff).ok(),
+		};
+		let result = match msg.typ.method() {
+			StunMethod::Allocate => self.allocate(msg, auth.username, tuple, now),
+			method => match self.allocations.get_mut(&tuple) {
+				Some(alloc) if alloc.expires > now && alloc.username != auth.username => Err(441),
+				Some(alloc) if alloc.expires > now => match method {
+					StunMethod::Refresh => Self::refresh(alloc, msg, now),
+					StunMethod::CreatePermission => Self::create_permission(alloc, msg, now),
+					_ => Self::channel_bind(alloc, msg, now),
+				},
+				_ => Err(437),
+			},
+		};
+		let mut attrs = match result {
+			Ok(attrs) => attrs,
+			// Signed like a success, since the request has been authenticated (RFC 8489 section 9.2.4)
+			Err(code) => {
+				let attrs = vec![StunAttr::error_code(code)];
+				return errors::respond_signed(msg, attrs, &auth.key, buff).ok();
+			}
+		};
+		// A refresh to 0 deletes the allocation
+		if let Some(alloc) = self.allocations.get(&tuple) {
+			if alloc.expires <= now {
+				self.release(&tuple);
+			}
+		}
+		attrs.push(StunAttr::Integrity(Integrity::sign(&auth.key)));
+		attrs.push(StunAttr::Fingerprint);
+		msg.res(&attrs).encode(buff).ok()
+	}
+	fn allocate(
+		&mut self,
+		msg: &Stun<'_>,
+		username: &str,
+		tuple: FiveTuple,
+		now: T,
+	) -> Result<Vec<StunAttr<'static>>, u16> {
+		match self.allocations.get(&tuple) {
+			Some(alloc) if alloc.expires > now => return Err(437),
+			Some(_) => self.release(&tuple),
+			None => {}
+		}
+		let req = Allocate::from_stun(msg).map_err(|_| 400_u16)?;
+		if req.transport != RequestedTransport::Udp {
+			return Err(442);
+		}
+		// Ports aren't held in reserve, so there's nothing for a token to refer to
+		if req.reservation_token.is_some() {
+			return Err(508);
+		}
+		// IPv4 unless asked otherwise, whatever the client's own family (RFC 8656 section 7.2)
+		let family = req.family.unwrap_or(AddressFamily::IPV4);
+		if !self.families.contains(&family) {
+			return Err(440);
+		}
+		// A relayed address of that family, with an even port if EVEN-PORT was given
+		let i = self
+			.free
+			.iter()
+			.position(|a| {
+				AddressFamily::of(*a) == family && (req.even_port.is_none() || a.port() % 2 == 0)
+			})
+			.ok_or(508_u16)?;
+		let relayed = self.free.swap_remove(i);
+		let lifetime = req
+			.lifetime
+			.unwrap_or(Lifetime::DEFAULT)
+			.clamp(Lifetime::DEFAULT, Lifetime::MAX);
+		self.allocations.insert(
+			tuple,
+			Allocation {
+				relayed,
+				username: username.to_string(),
+				expires: now + lifetime.into(),
+				expiries: Expiries::new(),
+				channels: HashMap::new(),
+			},
+		);
+		self.relayed.insert(relayed, tuple);
+		Ok(attrs_of(Allocated {
+			relayed,
+			mapped: tuple.client,
+			lifetime,
+			reservation_token: None,
+		}))
+	}
+	fn refresh(
+		alloc: &mut Allocation<T>,
+		msg: &Stun<'_>,
+		now: T,
+	) -> Result<Vec<StunAttr<'static>>, u16> {
+		let req = Refresh::from_stun(msg).map_err(|_| 400_u16)?;
+		// 0 deletes the allocation, anything else is at least the default (RFC 8656 section 7.3)
+		let lifetime = match req.lifetime.unwrap_or(Lifetime::DEFAULT) {
+			Lifetime::ZERO => Lifetime::ZERO,
+			lifetime => lifetime.clamp(Lifetime::DEFAULT, Lifetime::MAX),
+		};
+		alloc.expires = now + lifetime.into();
+		Ok(vec![StunAttr::Lifetime(lifetime)])
+	}
+	fn create_permission(
+		alloc: &mut Allocation<T>,
+		msg: &Stun<'_>,
+		now: T,
+	) -> Result<Vec<StunAttr<'static>>, u16> {
+		let req = CreatePermission::from_stun(msg).map_err(|_| 400_u16)?;
+		if req
+			.peers
+			.iter()
+			.any(|p| p.is_ipv4() != alloc.relayed.is_ipv4())
+		{
+			return Err(443);
+		}
+		for peer in req.peers {
+			alloc.expiries.permit(peer.ip(), now);
+		}
+		Ok(Vec::new())
+	}
+	fn channel_bind(
+		alloc: &mut Allocation<T>,
+		msg: &Stun<'_>,
+		now: T,
+	) -> Result<Vec<StunAttr<'static>>, u16> {
+		let req = ChannelBind::from_stun(msg).map_err(|_| 400_u16)?;
+		if req.peer.is_ipv4() != alloc.relayed.is_ipv4() {
+			return Err(443);
+		}
+		// A channel stays bound to one peer, and a peer to one channel
+		let channel = req.channel.get();
+		let bound = alloc.channel_peer(channel, now);
+		if bound.is_some_and(|peer| peer != req.peer)
+			|| alloc
+				.channel_for(req.peer, now)
+				.is_some_and(|c| c != channel)
+		{
+			return Err(400);
+		}
+		alloc.channels.insert(channel, req.peer);
+		alloc.expiries.bind(channel, now);
+		alloc.expiries.permit(req.peer.ip(), now);
+		Ok(Vec::new())
+	}
+	fn release(&mut self, tuple: &FiveTuple) {
+		if let Some(alloc) = self.allocations.remove(tuple) {
+			self.relayed.remove(&alloc.relayed);
+			self.free.push(alloc.relayed);
+		}
+	}
+
+	// Handles data that arrived at a relayed address from a peer. If the peer has a permission, a
+	// Data indication (or ChannelData, if the peer has a channel) is written into buff, to be sent
+	// to the client of the returned 5-tuple.
+	pub fn handle_peer(
+		&mut self,
+		relayed: SocketAddr,
+		peer: SocketAddr,
+		data: &[u8],
+		now: T,
+		buff: &mut [u8],
+	) -> Option<(FiveTuple, usize)> {
+		let tuple = *self.relayed.get(&relayed)?;
+		let alloc = self.allocations.get(&tuple)?;
+		if alloc.expires <= now || !alloc.permitted(peer.ip(), now) {
+			return None;
+		}
+		let len = match alloc.channel_for(peer, now) {
+			Some(channel) => ChannelData { channel, data }.encode(buff).ok()?,
+			None => DataIndication {
+				peer,
+				data: Some(data),
+				icmp: None,
+			}
+			.encode(random_txid().ok()?, &[], buff)
+			.ok()?,
+		};
+		self.recording.record(Direction::Outbound, tuple.client, &buff[..len]);
+		Some((tuple, len))
+	}
+
+	// The next time that something expires
+	pub fn poll_timeout(&self) -> Option<T> {
+		self.allocations
+			.values()
+			.flat_map(|a| [Some(a.expires), a.expiries.next_deadline()])
+			.flatten()
+			.min()
+	}
+	// Removes expired allocations (returning their relayed addresses to the pool), permissions and
+	// channel bindings
+	pub fn handle_timeout(&mut self, now: T) {
+		let expired: Vec<_> = self
+			.allocations
+			.iter()
+			.filter(|(_, a)| a.expires <= now)
+			.map(|(tuple, _)| *tuple)
+			.collect();
+		for tuple in expired {
+			self.release(&tuple);
+		}
+		for alloc in self.allocations.values_mut() {
+			for expired in alloc.expiries.expire(now) {
+				if let Expiring::Channel(channel) = expired {
+					alloc.channels.remove(&channel);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,36 @@
+// XOR-ing IPv6 addresses against the magic cookie + transaction id. With the simd feature this uses
+// explicit vector instructions; otherwise a u128 XOR, which the compiler usually vectorizes anyway.
+//
+// There's no equivalent switch for FINGERPRINT: crc32fast already detects PCLMULQDQ / the ARMv8 CRC
+// instructions at runtime and falls back to a table implementation.
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+pub(crate) fn xor16(a: [u8; 16], b: &[u8; 16]) -> [u8; 16] {
+	use std::arch::x86_64::{_mm_loadu_si128, _mm_storeu_si128, _mm_xor_si128};
+	let mut ret = [0u8; 16];
+	// SSE2 is part of the x86_64 baseline, and the loads/stores are unaligned
+	unsafe {
+		let x = _mm_xor_si128(_mm_loadu_si128(a.as_ptr().cast()), _mm_loadu_si128(b.as_ptr().cast()));
+		_mm_storeu_si128(ret.as_mut_ptr().cast(), x);
+	}
+	ret
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+pub(crate) fn xor16(a: [u8; 16], b: &[u8; 16]) -> [u8; 16] {
+	use std::arch::aarch64::{veorq_u8, vld1q_u8, vst1q_u8};
+	let mut ret = [0u8; 16];
+	// NEON is part of the aarch64 baseline
+	unsafe { vst1q_u8(ret.as_mut_ptr(), veorq_u8(vld1q_u8(a.as_ptr()), vld1q_u8(b.as_ptr()))) };
+	ret
+}
+
+#[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+pub(crate) fn xor16(a: [u8; 16], b: &[u8; 16]) -> [u8; 16] {
+	(u128::from_ne_bytes(a) ^ u128::from_ne_bytes(*b)).to_ne_bytes()
+}
+
+// Ports and IPv4 addresses only use the front of the xor bytes
+pub(crate) fn xor_prefix<const N: usize>(a: [u8; N], b: &[u8; 16]) -> [u8; N] {
+	std::array::from_fn(|i| a[i] ^ b[i])
+}
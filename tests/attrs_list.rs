@@ -0,0 +1,21 @@
+use stun_zc::prelude::*;
+
+#[test]
+fn try_extend() {
+	let mut list = StunAttrsMut::<[StunAttr; 2]>::default();
+	assert!(list.try_extend([StunAttr::Fingerprint]).is_ok());
+	// The second fills the storage, the third comes back
+	let extended = list.try_extend([StunAttr::Software("a"), StunAttr::Software("b")]);
+	assert!(matches!(extended, Err(StunAttr::Software("b"))));
+	assert!(matches!(
+		list.as_slice(),
+		[StunAttr::Fingerprint, StunAttr::Software("a")]
+	));
+}
+
+#[test]
+#[should_panic]
+fn extend_past_capacity() {
+	let mut list = StunAttrsMut::<[StunAttr; 1]>::default();
+	list.extend([StunAttr::Fingerprint, StunAttr::Fingerprint]);
+}
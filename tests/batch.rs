@@ -0,0 +1,37 @@
+use std::net::SocketAddr;
+
+use stun_zc::batch::{Batch, BatchErr, MAX_PAYLOAD};
+use stun_zc::prelude::*;
+
+fn addr(port: u16) -> SocketAddr {
+	SocketAddr::from(([192, 0, 2, 1], port))
+}
+
+#[test]
+fn one_destination() {
+	let mut buff = vec![0; 1024];
+	let mut batch = Batch::new(&mut buff);
+	let msg = Stun::req(StunMethod::Binding, &[]);
+	assert!(batch.push(addr(1), &msg).is_ok());
+	assert!(batch.push(addr(1), &msg).is_ok());
+	assert!(matches!(batch.push(addr(2), &msg), Err(BatchErr::Full)));
+	assert_eq!(batch.destination(), Some(addr(1)));
+	assert_eq!(batch.len(), 2);
+
+	batch.clear();
+	assert!(batch.push(addr(2), &msg).is_ok());
+	assert_eq!(batch.destination(), Some(addr(2)));
+}
+
+#[test]
+fn payload_limit() {
+	// Room for more, but a send can't carry more than a UDP payload
+	let mut buff = vec![0; 2 * MAX_PAYLOAD];
+	let mut batch = Batch::new(&mut buff);
+	let software = "a".repeat(1000);
+	let attrs = [StunAttr::Software(&software)];
+	let msg = Stun::req(StunMethod::Binding, &attrs);
+	while batch.push(addr(1), &msg).is_ok() {}
+	assert!(batch.as_bytes().len() <= MAX_PAYLOAD);
+	assert_eq!(batch.len(), MAX_PAYLOAD / 1024);
+}
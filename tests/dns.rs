@@ -0,0 +1,145 @@
+// SRV responses are built by hand, so the name decompression can be fed pointers a real server
+// would never send.
+#![cfg(feature = "dns")]
+use stun_zc::dns::{order, parse_srv, srv_query, SrvRecord};
+
+const ID: u16 = 0x1234;
+const NAME: &str = "_stun._udp.example.org";
+// Where "example.org" starts in the question
+const EXAMPLE_ORG: u8 = 12 + 6 + 5;
+
+// A response echoing the query's question, with the answers appended as given
+fn response(rcode: u8, answers: &[Vec<u8>]) -> Vec<u8> {
+	let mut msg = srv_query(NAME, ID).unwrap();
+	msg[2] |= 0x80;
+	msg[3] |= 0x80 | rcode;
+	msg[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+	for answer in answers {
+		msg.extend_from_slice(answer);
+	}
+	msg
+}
+
+// An SRV answer for the question's name; target is the encoded name that ends the record
+fn srv(priority: u16, weight: u16, port: u16, target: &[u8]) -> Vec<u8> {
+	let mut rdata = Vec::new();
+	for v in [priority, weight, port] {
+		rdata.extend_from_slice(&v.to_be_bytes());
+	}
+	rdata.extend_from_slice(target);
+	let mut ret = vec![0xC0, 12, 0, 33, 0, 1, 0, 0, 0x0E, 0x10];
+	ret.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+	ret.extend_from_slice(&rdata);
+	ret
+}
+
+fn record(priority: u16, weight: u16, target: &str) -> SrvRecord {
+	SrvRecord {
+		priority,
+		weight,
+		port: 3478,
+		target: target.to_string(),
+	}
+}
+
+#[test]
+fn compressed_names() {
+	// One target spelled out, one that ends in a pointer to the question's "example.org"
+	let msg = response(
+		0,
+		&[
+			srv(10, 5, 3478, b"\x03one\x07example\x03org\x00"),
+			srv(20, 0, 3479, &[3, b't', b'w', b'o', 0xC0, EXAMPLE_ORG]),
+		],
+	);
+	let records = parse_srv(&msg, ID).unwrap();
+	assert_eq!(
+		records,
+		vec![
+			SrvRecord {
+				priority: 10,
+				weight: 5,
+				port: 3478,
+				target: "one.example.org".to_string(),
+			},
+			SrvRecord {
+				priority: 20,
+				weight: 0,
+				port: 3479,
+				target: "two.example.org".to_string(),
+			},
+		]
+	);
+}
+
+#[test]
+fn bad_pointers() {
+	let answer_at = response(0, &[]).len() as u16;
+	// Into the record's own data, which comes after it
+	let target = answer_at + 12 + 6 + 2;
+	let forward = srv(10, 0, 3478, &[0xC0 | (target >> 8) as u8, target as u8, 0]);
+	assert!(parse_srv(&response(0, &[forward]), ID).is_err());
+	// At itself
+	let target = answer_at + 12 + 6;
+	let looping = srv(10, 0, 3478, &[0xC0 | (target >> 8) as u8, target as u8]);
+	assert!(parse_srv(&response(0, &[looping]), ID).is_err());
+	// Two pointers that point at each other can't both go backwards
+	let mut msg = response(0, &[srv(10, 0, 3478, &[0xC0, 0])]);
+	let len = msg.len();
+	msg[len - 2] = 0xC0;
+	msg[len - 1] = 0x0C;
+	msg[12] = 0xC0;
+	msg[13] = (len - 2) as u8;
+	assert!(parse_srv(&msg, ID).is_err());
+}
+
+#[test]
+fn truncated() {
+	let msg = response(0, &[srv(10, 5, 3478, b"\x03one\x07example\x03org\x00")]);
+	for len in [0, 11, 20, msg.len() - 20, msg.len() - 1] {
+		assert!(parse_srv(&msg[..len], ID).is_err(), "length {len}");
+	}
+	// A record claiming more data than there is: its RDLENGTH is just before the 23 bytes of data
+	let mut msg = msg;
+	let rdlength = msg.len() - 23 - 2;
+	msg[rdlength + 1] += 1;
+	assert!(parse_srv(&msg, ID).is_err());
+}
+
+#[test]
+fn no_records() {
+	// NXDOMAIN
+	assert_eq!(parse_srv(&response(3, &[]), ID).unwrap(), Vec::new());
+	assert_eq!(parse_srv(&response(0, &[]), ID).unwrap(), Vec::new());
+	// SERVFAIL, and a response to someone else's query
+	assert!(parse_srv(&response(2, &[]), ID).is_err());
+	assert!(parse_srv(&response(0, &[]), ID + 1).is_err());
+}
+
+#[test]
+fn ordering() {
+	let records = vec![
+		record(20, 0, "c.example.org"),
+		record(10, 0, "."),
+		record(10, 1, "a.example.org"),
+	];
+	let ordered = order(records);
+	assert_eq!(
+		ordered,
+		vec![
+			record(10, 1, "a.example.org"),
+			record(20, 0, "c.example.org")
+		]
+	);
+
+	// A weight of 0 has a small chance of going first (RFC 2782), whatever order it came in
+	let records = vec![
+		record(10, 10, "a.example.org"),
+		record(10, 0, "b.example.org"),
+	];
+	let first = (0..1000)
+		.map(|_| order(records.clone()).remove(0))
+		.filter(|r| r.weight == 0)
+		.count();
+	assert!(first > 0 && first < 500, "{first}");
+}
@@ -0,0 +1,68 @@
+// Checks are driven on VirtualTime, like the transactions in timers.rs
+#![cfg(feature = "ice")]
+use std::time::Duration;
+
+use stun_zc::clock::VirtualTime;
+use stun_zc::ice::checks::Checks;
+use stun_zc::ice::{IceCredentials, IceRole};
+use stun_zc::prelude::*;
+
+fn at(ms: u64) -> VirtualTime {
+	VirtualTime(Duration::from_millis(ms))
+}
+
+fn checks() -> Checks<u8, VirtualTime> {
+	let creds = IceCredentials::new("local", "local-password", "remote", "remote-password");
+	Checks::new(creds, IceRole::Controlling(1), at(0))
+}
+
+#[test]
+fn retransmissions() {
+	let mut checks = checks();
+	checks.queue(1, 100, false);
+	let mut buff = [0; 256];
+	let mut sent = Vec::new();
+	let mut txids = Vec::new();
+	let failed = loop {
+		let now = checks.poll_timeout().unwrap();
+		let failed = checks.handle_timeout(now);
+		if !failed.is_empty() {
+			break (failed, now);
+		}
+		while let Some((pair, len)) = checks.poll_transmit(now, &mut buff) {
+			assert_eq!(pair, 1);
+			sent.push(now);
+			txids.push(Stun::decode(&buff[..len]).unwrap().txid);
+		}
+	};
+	let expected: Vec<_> = [0, 500, 1500, 3500, 7500, 15500, 31500].map(at).into();
+	assert_eq!(sent, expected);
+	assert_eq!(failed, (vec![1], at(39_500)));
+	// Retransmissions are the same transaction
+	assert!(txids.iter().all(|t| *t == txids[0]));
+	assert_eq!(checks.in_flight(), 0);
+}
+
+#[test]
+fn retransmissions_skip_pacing() {
+	let mut checks = checks();
+	for pair in 0..3 {
+		checks.queue(pair, 100, false);
+	}
+	let mut buff = [0; 256];
+	assert_eq!(checks.poll_transmit(at(0), &mut buff).map(|s| s.0), Some(0));
+	assert!(checks.poll_transmit(at(10), &mut buff).is_none());
+	assert_eq!(
+		checks.poll_transmit(at(50), &mut buff).map(|s| s.0),
+		Some(1)
+	);
+	assert_eq!(
+		checks.poll_transmit(at(100), &mut buff).map(|s| s.0),
+		Some(2)
+	);
+	// The first check's retransmission goes out as soon as it's due
+	assert_eq!(
+		checks.poll_transmit(at(500), &mut buff).map(|s| s.0),
+		Some(0)
+	);
+}
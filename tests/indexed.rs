@@ -0,0 +1,55 @@
+use stun_zc::attrs::index::Indexed;
+use stun_zc::prelude::*;
+
+fn encode(attrs: &[StunAttr<'_>]) -> Vec<u8> {
+	let msg = Stun {
+		typ: StunTyp::Req(StunMethod::Binding),
+		txid: TxId([7; 12]),
+		attrs: attrs.into(),
+	};
+	let mut buff = vec![0; 512];
+	let len = msg.encode(&mut buff).unwrap();
+	buff.truncate(len);
+	buff
+}
+
+#[test]
+fn lookups() {
+	let packet = encode(&[
+		StunAttr::Username("first"),
+		StunAttr::Software("stun-zc"),
+		StunAttr::Username("second"),
+		StunAttr::Fingerprint,
+	]);
+	let indexed = Indexed::decode(&packet).unwrap();
+	// The first of a type wins, like with iterating
+	assert!(matches!(
+		indexed.get(0x0006),
+		Some(StunAttr::Username("first"))
+	));
+	assert!(indexed.contains(0x8028));
+	assert!(!indexed.contains(0x0020));
+	assert_eq!(indexed.flat().username, Some("first"));
+	assert_eq!(indexed.flat().software, Some("stun-zc"));
+
+	// Offsets into the attributes, which start after the header
+	let value = indexed.index().value(0x8022).unwrap();
+	assert_eq!(&packet[20..][value], b"stun-zc");
+	assert_eq!(indexed.index().offset(0x0006), Some(0));
+}
+
+#[test]
+fn after_fingerprint() {
+	// Nothing after FINGERPRINT counts, but it still has to decode
+	let mut packet = encode(&[StunAttr::Fingerprint, StunAttr::Software("late")]);
+	let indexed = Indexed::decode(&packet).unwrap();
+	assert!(!indexed.contains(0x8022));
+	assert!(indexed.get(0x8022).is_none());
+	assert_eq!(indexed.flat().software, None);
+
+	// The late SOFTWARE's value isn't UTF-8 anymore
+	let last = packet.len() - 1;
+	packet[last] = 0xFF;
+	assert!(Indexed::decode(&packet).is_err());
+	assert!(Stun::decode(&packet).is_err());
+}
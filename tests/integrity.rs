@@ -0,0 +1,91 @@
+// MESSAGE-INTEGRITY-SHA256 may be truncated (RFC 8489 section 14.6), so these are built by hand: a
+// placeholder of the right length is encoded, then overwritten with the HMAC.
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use stun_zc::attr::Unknown;
+use stun_zc::auth::StunAuthErr;
+use stun_zc::prelude::*;
+
+const PASSWORD: &str = "password";
+
+// A request whose MESSAGE-INTEGRITY-SHA256 is the first len bytes of the HMAC
+fn truncated(len: usize) -> Vec<u8> {
+	let placeholder = vec![0; len];
+	let attrs = [
+		StunAttr::Software("stun-zc"),
+		StunAttr::Other(
+			0x001C,
+			Unknown {
+				value: &placeholder,
+				padding: &[],
+			},
+		),
+	];
+	let msg = Stun {
+		typ: StunTyp::Req(StunMethod::Binding),
+		txid: TxId([7; 12]),
+		attrs: attrs[..].into(),
+	};
+	let mut packet = vec![0; 128];
+	let packet_len = msg.encode(&mut packet).unwrap();
+	packet.truncate(packet_len);
+
+	// The header's length already runs to the end of the integrity attribute
+	let value_at = packet_len - len;
+	let key = ShortTermKey::new(PASSWORD);
+	let mut hmac = Hmac::<Sha256>::new_from_slice(key.as_bytes()).unwrap();
+	hmac.update(&packet[..value_at - 4]);
+	let mac = hmac.finalize().into_bytes();
+	packet[value_at..].copy_from_slice(&mac[..len]);
+	packet
+}
+
+#[test]
+fn sha256_truncated() {
+	let key = ShortTermKey::new(PASSWORD);
+	for len in [16, 20, 32] {
+		assert!(
+			Stun::decode_and_verify(&truncated(len), &key).is_ok(),
+			"length {len}"
+		);
+	}
+	// Too short, or not a multiple of 4
+	for len in [4, 12, 18] {
+		assert!(
+			Stun::decode_and_verify(&truncated(len), &key).is_err(),
+			"length {len}"
+		);
+	}
+}
+
+#[test]
+fn sha256_tampered() {
+	let key = ShortTermKey::new(PASSWORD);
+	let mut packet = truncated(16);
+	let last = packet.len() - 1;
+	packet[last] ^= 1;
+	assert!(Stun::decode_and_verify(&packet, &key).is_err());
+	assert!(Stun::decode_and_verify(&truncated(16), &ShortTermKey::new("other")).is_err());
+}
+
+#[test]
+fn after_fingerprint() {
+	// Anything after FINGERPRINT is ignored, including a MESSAGE-INTEGRITY that would verify
+	let key = ShortTermKey::new(PASSWORD);
+	let attrs = [
+		StunAttr::Software("stun-zc"),
+		StunAttr::Fingerprint,
+		StunAttr::Integrity(Integrity::sign(&key)),
+	];
+	let msg = Stun {
+		typ: StunTyp::Req(StunMethod::Binding),
+		txid: TxId([7; 12]),
+		attrs: attrs[..].into(),
+	};
+	let mut packet = vec![0; 128];
+	let len = msg.encode(&mut packet).unwrap();
+	assert!(matches!(
+		Stun::decode_and_verify(&packet[..len], &key),
+		Err(StunAuthErr::MissingIntegrity)
+	));
+}
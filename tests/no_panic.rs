@@ -0,0 +1,162 @@
+// Throws random and mutated packets at the decode / encode paths. Any panic fails the test.
+use std::net::SocketAddr;
+
+use stun_zc::attr::Integrity;
+use stun_zc::attrs::index::Indexed;
+use stun_zc::prelude::*;
+use stun_zc::testing::{Mutation, Mutator};
+
+const ITERATIONS: usize = 20_000;
+
+// xorshift64, so that failures are reproducible
+struct Rng(u64);
+impl Rng {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+	fn below(&mut self, n: usize) -> usize {
+		(self.next() % n as u64) as usize
+	}
+	fn fill(&mut self, buff: &mut [u8]) {
+		for b in buff {
+			*b = self.next() as u8;
+		}
+	}
+}
+
+fn valid_messages(key: &ShortTermKey) -> Vec<Vec<u8>> {
+	let addr: SocketAddr = "[2001:db8::1]:3478".parse().unwrap();
+	let v4: SocketAddr = "192.0.2.1:3478".parse().unwrap();
+	let lists: &[&[StunAttr<'_>]] = &[
+		&[StunAttr::Fingerprint],
+		&[
+			StunAttr::Username("user"),
+			StunAttr::Software("stun-zc"),
+			#[cfg(feature = "ice")]
+			StunAttr::Priority(0x6e0001ff),
+			#[cfg(feature = "ice")]
+			StunAttr::UseCandidate,
+			StunAttr::Integrity(Integrity::sign(key)),
+			StunAttr::integrity_sha256(key),
+			StunAttr::Fingerprint,
+		],
+		&[
+			StunAttr::xor_mapped(addr),
+			StunAttr::mapped(v4),
+			StunAttr::error(420, "Unknown Attribute"),
+			StunAttr::unknown_attributes(&[0x0003, 0x0004, 0x0005]),
+			StunAttr::Fingerprint,
+		],
+		#[cfg(feature = "turn")]
+		&[
+			StunAttr::xor_peer(v4),
+			StunAttr::channel(stun_zc::attr::ChannelNumber::new(0x4000).unwrap()),
+			StunAttr::lifetime(600),
+			StunAttr::data(b"odd length"),
+			StunAttr::even_port(true),
+			StunAttr::requested_transport_udp(),
+			StunAttr::RequestedFamily(stun_zc::attr::AddressFamily::IPV6),
+			StunAttr::DontFragment,
+		],
+	];
+	lists
+		.iter()
+		.map(|attrs| {
+			let msg = Stun {
+				typ: StunTyp::Req(StunMethod::Binding),
+				txid: TxId([7; 12]),
+				attrs: (*attrs).into(),
+			};
+			let mut buff = vec![0; 512];
+			let len = msg.encode(&mut buff).unwrap();
+			buff.truncate(len);
+			buff
+		})
+		.collect()
+}
+
+fn exercise(packet: &[u8], key: &ShortTermKey, out: &mut [u8]) {
+	let _ = Stun::decode_limit(packet, SizeLimit::IPV4);
+	let _ = Stun::decode_authenticated(packet, |_, _| Some(key.clone()));
+	if let Ok(indexed) = Indexed::decode(packet) {
+		for typ in [0x0006, 0x0008, 0x0020, 0x8028, 0x1234] {
+			let _ = indexed.get(typ);
+		}
+	}
+	let Ok(msg) = Stun::decode(packet) else {
+		return;
+	};
+	for attr in &msg {
+		if let StunAttr::Integrity(integrity) = &attr {
+			integrity.verify(key);
+		}
+	}
+	for attr in &msg.attrs {
+		let _ = attr;
+	}
+	let _ = msg.flat();
+	let _ = msg.len();
+
+	// Re-encode into buffers that are too small, exactly sized, and oversized
+	let attrs: Vec<_> = msg.into_iter().collect();
+	let rebuilt = msg.res(&attrs);
+	for len in [0, 19, 20, packet.len() / 2, packet.len(), out.len()] {
+		let len = len.min(out.len());
+		let _ = msg.encode(&mut out[..len]);
+		let _ = rebuilt.encode(&mut out[..len]);
+	}
+}
+
+#[test]
+fn random_bytes() {
+	let key = ShortTermKey::new("password");
+	let mut rng = Rng(0x5354554e);
+	let mut packet = [0u8; 128];
+	let mut out = [0u8; 256];
+	for _ in 0..ITERATIONS {
+		let len = rng.below(packet.len());
+		rng.fill(&mut packet[..len]);
+		// Give most packets a plausible header so that they make it to the attribute parsing
+		if len >= 20 && rng.below(4) != 0 {
+			packet[0] &= 0b0011_1111;
+			packet[2..4].copy_from_slice(&((len as u16 - 20) & !3).to_be_bytes());
+			packet[4..8].copy_from_slice(&0x2112A442u32.to_be_bytes());
+		}
+		exercise(&packet[..len], &key, &mut out);
+	}
+}
+
+#[test]
+fn mutated_messages() {
+	let key = ShortTermKey::new("password");
+	let mut rng = Rng(0x2112A442);
+	let messages = valid_messages(&key);
+	let mut packet = [0u8; 512];
+	let mut out = [0u8; 512];
+	for _ in 0..ITERATIONS {
+		let valid = &messages[rng.below(messages.len())];
+		let n = rng.below(8);
+		let mutation = match rng.below(6) {
+			0 => Mutation::WrongCookie,
+			1 => Mutation::Length(rng.next() as u16),
+			2 => Mutation::TruncateAttr(n),
+			3 => Mutation::ErrorClass(rng.next() as u8),
+			4 => Mutation::AttrLength(n, rng.next() as u16),
+			_ => {
+				// Flip a few random bytes
+				packet[..valid.len()].copy_from_slice(valid);
+				for _ in 0..=n {
+					packet[rng.below(valid.len())] ^= rng.next() as u8;
+				}
+				exercise(&packet[..valid.len()], &key, &mut out);
+				continue;
+			}
+		};
+		if let Some(len) = Mutator::new(valid).apply(mutation, &mut packet) {
+			exercise(&packet[..len], &key, &mut out);
+		}
+	}
+}
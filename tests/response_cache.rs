@@ -0,0 +1,100 @@
+// The cache sits in front of a handler, so each test counts how often the handler actually ran.
+use std::cell::Cell;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use stun_zc::clock::VirtualTime;
+use stun_zc::prelude::*;
+use stun_zc::server::cache::{ResponseCache, CACHE_WINDOW};
+
+fn at(secs: u64) -> VirtualTime {
+	VirtualTime(Duration::from_secs(secs))
+}
+
+fn addr(port: u16) -> SocketAddr {
+	SocketAddr::from(([192, 0, 2, 1], port))
+}
+
+fn request(txid: u8) -> Vec<u8> {
+	let mut msg = Stun::req(StunMethod::Binding, &[]);
+	msg.txid = TxId([txid; 12]);
+	let mut buff = vec![0; 20];
+	let len = msg.encode(&mut buff).unwrap();
+	buff.truncate(len);
+	buff
+}
+
+// Answers with the number of times it has been called, so a replay is easy to tell apart
+struct Counting(Cell<u8>);
+impl Counting {
+	fn handle(
+		&self,
+		cache: &ResponseCache<VirtualTime>,
+		packet: &[u8],
+		from: SocketAddr,
+		now: VirtualTime,
+	) -> u8 {
+		let mut buff = [0; 8];
+		let len = cache
+			.handle(packet, from, now, &mut buff, |_, _, buff| {
+				self.0.set(self.0.get() + 1);
+				buff[0] = self.0.get();
+				Some(1)
+			})
+			.unwrap();
+		assert_eq!(len, 1);
+		buff[0]
+	}
+}
+
+#[test]
+fn replays_retransmissions() {
+	let cache = ResponseCache::default();
+	let handler = Counting(Cell::new(0));
+	assert_eq!(handler.handle(&cache, &request(1), addr(1), at(0)), 1);
+	assert_eq!(handler.handle(&cache, &request(1), addr(1), at(1)), 1);
+	assert_eq!(handler.handle(&cache, &request(1), addr(1), at(39)), 1);
+	assert_eq!(cache.len(), 1);
+
+	// A different request from the same source is new
+	assert_eq!(handler.handle(&cache, &request(2), addr(1), at(1)), 2);
+}
+
+#[test]
+fn keyed_by_source() {
+	let cache = ResponseCache::default();
+	let handler = Counting(Cell::new(0));
+	assert_eq!(handler.handle(&cache, &request(1), addr(1), at(0)), 1);
+	// Someone else reusing the transaction id doesn't get the first client's response
+	assert_eq!(handler.handle(&cache, &request(1), addr(2), at(0)), 2);
+	assert_eq!(handler.handle(&cache, &request(1), addr(2), at(1)), 2);
+	assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn expires() {
+	let cache = ResponseCache::default();
+	let handler = Counting(Cell::new(0));
+	assert_eq!(handler.handle(&cache, &request(1), addr(1), at(0)), 1);
+	let window = CACHE_WINDOW.as_secs();
+	assert_eq!(
+		handler.handle(&cache, &request(1), addr(1), at(window - 1)),
+		1
+	);
+	assert_eq!(handler.handle(&cache, &request(1), addr(1), at(window)), 2);
+	assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn evicts_oldest() {
+	let cache = ResponseCache::new(CACHE_WINDOW, 2);
+	let handler = Counting(Cell::new(0));
+	for txid in 1..=3 {
+		handler.handle(&cache, &request(txid), addr(1), at(0));
+	}
+	assert_eq!(cache.len(), 2);
+	// The first was dropped to make room for the third, the second is still there
+	assert_eq!(handler.handle(&cache, &request(2), addr(1), at(0)), 2);
+	assert_eq!(handler.handle(&cache, &request(1), addr(1), at(0)), 4);
+	assert_eq!(cache.len(), 2);
+}
@@ -0,0 +1,139 @@
+// Decoding a message and encoding it again has to reproduce the original bytes. That's exact for
+// copy_to and for encoding the decoded message; rebuilding it from its attributes is exact except
+// for the padding of known attributes, which is written as zeroes.
+use std::net::SocketAddr;
+
+use stun_zc::attr::Integrity;
+use stun_zc::prelude::*;
+
+fn to_vec(msg: &Stun<'_>) -> Vec<u8> {
+	let mut buff = vec![0; 512];
+	let len = msg.encode(&mut buff).unwrap();
+	buff.truncate(len);
+	buff
+}
+
+fn encode(attrs: &[StunAttr<'_>]) -> Vec<u8> {
+	to_vec(&Stun {
+		typ: StunTyp::Req(StunMethod::Binding),
+		txid: TxId([7; 12]),
+		attrs: attrs.into(),
+	})
+}
+
+// Re-encodes through the attribute list, so every attribute is decoded and encoded again
+fn reencode(packet: &[u8]) -> Vec<u8> {
+	let msg = Stun::decode(packet).unwrap();
+	let attrs: Vec<_> = msg.into_iter().collect();
+	to_vec(&Stun {
+		typ: msg.typ.clone(),
+		txid: msg.txid,
+		attrs: attrs[..].into(),
+	})
+}
+
+fn copy(packet: &[u8], msg: &Stun<'_>) -> Vec<u8> {
+	let mut out = vec![0; packet.len()];
+	let len = msg.copy_to(&mut out).unwrap();
+	out.truncate(len);
+	out
+}
+
+#[test]
+fn known_attributes() {
+	let key = ShortTermKey::new("password");
+	let addr: SocketAddr = "[2001:db8::1]:3478".parse().unwrap();
+	let v4: SocketAddr = "192.0.2.1:3478".parse().unwrap();
+	let lists: &[&[StunAttr<'_>]] = &[
+		&[],
+		&[StunAttr::Fingerprint],
+		&[
+			StunAttr::Username("odd"),
+			StunAttr::Software("stun-zc"),
+			#[cfg(feature = "ice")]
+			StunAttr::Priority(0x6e0001ff),
+			#[cfg(feature = "ice")]
+			StunAttr::IceControlling(0x0123456789abcdef),
+			StunAttr::Integrity(Integrity::sign(&key)),
+			StunAttr::integrity_sha256(&key),
+			StunAttr::Fingerprint,
+		],
+		&[
+			StunAttr::xor_mapped(addr),
+			StunAttr::mapped(v4),
+			StunAttr::error(420, "Unknown Attribute"),
+			StunAttr::unknown_attributes(&[0x0003, 0x0004, 0x0005]),
+			StunAttr::Realm("example.org"),
+			StunAttr::Nonce("abcdefg"),
+		],
+		#[cfg(feature = "turn")]
+		&[
+			StunAttr::xor_peer(v4),
+			StunAttr::channel(stun_zc::attr::ChannelNumber::new(0x4000).unwrap()),
+			StunAttr::lifetime(600),
+			StunAttr::data(b"odd length"),
+			StunAttr::even_port(true),
+			StunAttr::requested_transport_udp(),
+			StunAttr::RequestedFamily(stun_zc::attr::AddressFamily::IPV6),
+			StunAttr::DontFragment,
+		],
+	];
+	for attrs in lists {
+		let packet = encode(attrs);
+		let msg = Stun::decode(&packet).unwrap();
+		assert_eq!(reencode(&packet), packet);
+		assert_eq!(to_vec(&msg), packet);
+		assert_eq!(copy(&packet, &msg), packet);
+	}
+}
+
+#[test]
+fn unknown_attributes_and_padding() {
+	let mut packet = vec![
+		0x00, 0x01, 0x00, 0x14, 0x21, 0x12, 0xA4, 0x42, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12,
+	];
+	// An unknown comprehension-optional attribute with non-zero padding
+	packet.extend([
+		0xBE, 0xEF, 0x00, 0x05, b'h', b'e', b'l', b'l', b'o', 0xAA, 0xBB, 0xCC,
+	]);
+	// Then a known one, also with non-zero padding
+	packet.extend([0x00, 0x06, 0x00, 0x03, b'b', b'o', b'b', 0xDD]);
+	let reencoded = reencode(&packet);
+	// The list path keeps an unknown attribute's padding, but zeroes a known one's
+	assert_eq!(reencoded[..32], packet[..32]);
+	assert_eq!(
+		reencoded[32..],
+		[0x00, 0x06, 0x00, 0x03, b'b', b'o', b'b', 0x00]
+	);
+	// copy_to is exact
+	let msg = Stun::decode(&packet).unwrap();
+	assert_eq!(copy(&packet, &msg), packet);
+	assert_eq!(to_vec(&msg), packet);
+}
+
+#[test]
+fn classic_header() {
+	let mut packet = encode(&[StunAttr::mapped("192.0.2.1:3478".parse().unwrap())]);
+	// RFC 3489 transaction ids use the cookie's bytes too
+	packet[4..8].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+	let msg = Stun::decode_classic(&packet).unwrap();
+	assert_eq!(copy(&packet, &msg), packet);
+	assert_eq!(msg.classic_txid().unwrap()[..4], [0xDE, 0xAD, 0xBE, 0xEF]);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn owned() {
+	let packet = encode(&[StunAttr::Software("stun-zc"), StunAttr::Fingerprint]);
+	let owned = StunOwned::decode(&packet).unwrap();
+	assert_eq!(to_vec(&owned.as_stun()), packet);
+
+	// A list has to be encoded, and these don't fit in one message
+	let long = "a".repeat(0x8000);
+	let attrs = [StunAttr::Software(&long), StunAttr::Software(&long)];
+	let msg = Stun::req(StunMethod::Binding, &attrs);
+	assert!(matches!(
+		StunOwned::try_from(&msg),
+		Err(StunEncodeErr::TooLarge)
+	));
+}
@@ -0,0 +1,121 @@
+// The timer logic runs on whatever time it's handed, so schedules can be checked exactly with
+// VirtualTime instead of sleeping.
+use std::time::Duration;
+
+use stun_zc::clock::{Clock, ManualClock, VirtualTime};
+use stun_zc::prelude::*;
+use stun_zc::testing::Captured;
+use stun_zc::transactions::{ClientTransaction, TransactionState, TransmitErr};
+
+fn at(ms: u64) -> VirtualTime {
+	VirtualTime(Duration::from_millis(ms))
+}
+
+fn binding_request() -> Vec<u8> {
+	let attrs = [StunAttr::Fingerprint];
+	let mut buff = vec![0; 64];
+	let len = Stun::req(StunMethod::Binding, &attrs)
+		.encode(&mut buff)
+		.unwrap();
+	buff.truncate(len);
+	buff
+}
+
+// Runs the transaction to completion, returning when each request was sent and when it timed out
+fn schedule(mut tx: ClientTransaction<VirtualTime>) -> (Vec<VirtualTime>, VirtualTime) {
+	let clock = ManualClock::default();
+	let mut sent = Vec::new();
+	while let Some(deadline) = tx.poll_timeout() {
+		clock.set(deadline);
+		if tx.poll_transmit(clock.now()).is_some() {
+			sent.push(clock.now());
+		}
+	}
+	assert_eq!(tx.state(), TransactionState::TimedOut);
+	(sent, clock.now())
+}
+
+#[test]
+fn retransmissions() {
+	let tx = ClientTransaction::new(binding_request(), at(0)).unwrap();
+	let (sent, timed_out) = schedule(tx);
+	let expected: Vec<_> = [0, 500, 1500, 3500, 7500, 15500, 31500].map(at).into();
+	assert_eq!(sent, expected);
+	assert_eq!(timed_out, at(39_500));
+}
+
+#[test]
+fn reliable_transport() {
+	let tx = ClientTransaction::new(binding_request(), at(0))
+		.unwrap()
+		.with_reliable(true);
+	let (sent, timed_out) = schedule(tx);
+	assert_eq!(sent, vec![at(0)]);
+	assert_eq!(timed_out, at(39_500));
+}
+
+#[test]
+fn response_completes() {
+	let request = binding_request();
+	let mut tx = ClientTransaction::new(request.clone(), at(0)).unwrap();
+	assert!(tx.poll_transmit(at(0)).is_some());
+	assert!(tx.poll_transmit(at(499)).is_none());
+	assert!(tx.poll_transmit(at(500)).is_some());
+
+	let req = Stun::decode(&request).unwrap();
+	let addr = "192.0.2.1:3478".parse().unwrap();
+	let attrs = [StunAttr::XMapped(addr)];
+	let mut buff = [0; 64];
+	let len = req.res(&attrs).encode(&mut buff).unwrap();
+	assert!(tx.handle(&buff[..len]).is_some());
+	assert_eq!(tx.state(), TransactionState::Completed);
+	assert_eq!(tx.poll_timeout(), None);
+}
+
+#[test]
+fn transmit_within_mtu() {
+	let to = "192.0.2.1:3478".parse().unwrap();
+	let mut transport = Captured {
+		mtu: Some(SizeLimit(20)),
+		..Default::default()
+	};
+	let request = binding_request();
+	let mut tx = ClientTransaction::new(request.clone(), at(0)).unwrap();
+	assert_eq!(
+		tx.transmit(&mut transport, to, at(0)),
+		Err(TransmitErr::TooLarge)
+	);
+	assert_eq!(tx.sent(), 0);
+
+	transport.mtu = Some(SizeLimit::IPV4);
+	assert_eq!(tx.transmit(&mut transport, to, at(0)), Ok(true));
+	assert_eq!(tx.transmit(&mut transport, to, at(1)), Ok(false));
+	assert_eq!(transport.sent, vec![(request, to)]);
+}
+
+#[cfg(feature = "turn")]
+#[test]
+fn permission_expiry() {
+	use stun_zc::turn::expiry::{Expiries, Expiring, CHANNEL_LIFETIME, PERMISSION_LIFETIME};
+
+	let peer = "192.0.2.7".parse().unwrap();
+	let mut expiries = Expiries::new();
+	expiries.permit(peer, at(0));
+	expiries.bind(0x4000, at(0));
+	let permission = at(0) + PERMISSION_LIFETIME;
+	assert_eq!(expiries.next_deadline(), Some(permission));
+	assert!(expiries.expire(at(299_999)).is_empty());
+
+	// Refreshing moves the deadline back
+	expiries.permit(peer, at(1000));
+	assert!(expiries.expire(permission).is_empty());
+	assert_eq!(
+		expiries.expire(at(1000) + PERMISSION_LIFETIME),
+		vec![Expiring::Permission(peer)]
+	);
+	assert_eq!(
+		expiries.expire(at(0) + CHANNEL_LIFETIME),
+		vec![Expiring::Channel(0x4000)]
+	);
+	assert!(expiries.is_empty());
+}
@@ -0,0 +1,391 @@
+// Drives TurnServer through whole exchanges, the way a client would see them: challenged first,
+// then authenticated with the realm and nonce from the challenge.
+#![cfg(feature = "turn")]
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use stun_zc::attr::{AddressFamily, ChannelNumber, Lifetime, RequestedTransport, Unknown};
+use stun_zc::clock::VirtualTime;
+use stun_zc::prelude::*;
+use stun_zc::record::{Direction, Record, Recorder, Recording};
+use stun_zc::turn::server::{FiveTuple, TurnAction, TurnServer};
+use stun_zc::turn::{
+	Allocate, ChannelBind, ChannelData, CreatePermission, Refresh, SendIndication, TurnErr,
+	TurnMessage,
+};
+
+const REALM: &str = "example.org";
+const RELAYED: &str = "192.0.2.1:49152";
+const PEER: &str = "198.51.100.2:3478";
+
+type Provider = fn(&str, Option<&str>) -> Result<Option<LongTermKey>, Infallible>;
+
+fn at(secs: u64) -> VirtualTime {
+	VirtualTime(Duration::from_secs(secs))
+}
+
+fn tuple(client: &str) -> FiveTuple {
+	FiveTuple {
+		client: client.parse().unwrap(),
+		server: "192.0.2.1:3478".parse().unwrap(),
+		transport: RequestedTransport::Udp,
+	}
+}
+
+fn server() -> TurnServer<Provider, VirtualTime> {
+	with_relays(&[RELAYED])
+}
+
+fn with_relays(relays: &[&str]) -> TurnServer<Provider, VirtualTime> {
+	let credentials: Provider =
+		|username, _| {
+			Ok(matches!(username, "alice" | "bob")
+				.then(|| LongTermKey::new(username, REALM, "secret")))
+		};
+	let relays = relays.iter().map(|r| r.parse().unwrap());
+	TurnServer::new(REALM, credentials, relays, at(0)).unwrap()
+}
+
+struct Client {
+	tuple: FiveTuple,
+	username: &'static str,
+	key: LongTermKey,
+	nonce: String,
+}
+impl Client {
+	// Sends an unauthenticated Allocate and keeps the nonce from the 401
+	fn new(
+		server: &mut TurnServer<Provider, VirtualTime>,
+		client: &str,
+		username: &'static str,
+	) -> Self {
+		let tuple = tuple(client);
+		let mut buff = [0; 512];
+		let len = Allocate::new(RequestedTransport::Udp)
+			.encode(TxId([1; 12]), &[], &mut buff)
+			.unwrap();
+		let (code, res) = respond(server, &buff[..len], tuple, at(0));
+		assert_eq!(code, Some(401));
+		let res = Stun::decode(&res).unwrap();
+		let flat = res.flat();
+		assert_eq!(flat.realm, Some(REALM));
+		Self {
+			tuple,
+			username,
+			key: LongTermKey::new(username, REALM, "secret"),
+			nonce: flat.nonce.unwrap().to_string(),
+		}
+	}
+	// Sends an authenticated request, returning the response's error code and bytes
+	fn send<'a>(
+		&'a self,
+		server: &mut TurnServer<Provider, VirtualTime>,
+		msg: &impl TurnMessage<'a>,
+		now: VirtualTime,
+	) -> (Option<u16>, Vec<u8>) {
+		let auth = [
+			StunAttr::Username(self.username),
+			StunAttr::Realm(REALM),
+			StunAttr::Nonce(&self.nonce),
+			StunAttr::Integrity(Integrity::sign(&self.key)),
+		];
+		let mut buff = [0; 512];
+		let len = msg.encode(TxId([2; 12]), &auth, &mut buff).unwrap();
+		respond(server, &buff[..len], self.tuple, now)
+	}
+}
+
+fn respond(
+	server: &mut TurnServer<Provider, VirtualTime>,
+	packet: &[u8],
+	tuple: FiveTuple,
+	now: VirtualTime,
+) -> (Option<u16>, Vec<u8>) {
+	let mut buff = [0; 512];
+	let Some(TurnAction::Respond(len)) = server.handle(packet, tuple, now, &mut buff) else {
+		panic!("expected a response");
+	};
+	let res = Stun::decode(&buff[..len]).unwrap();
+	(res.flat().error.map(|e| e.code), buff[..len].to_vec())
+}
+
+fn lifetime(res: &[u8]) -> Option<Lifetime> {
+	Stun::decode(res).unwrap().flat().lifetime
+}
+
+fn allocated(server: &mut TurnServer<Provider, VirtualTime>, username: &'static str) -> Client {
+	let client = Client::new(server, "203.0.113.5:50000", username);
+	let (code, res) = client.send(server, &Allocate::new(RequestedTransport::Udp), at(0));
+	assert_eq!(code, None);
+	let flat = Stun::decode(&res).unwrap().flat();
+	assert_eq!(flat.xrelayed, Some(RELAYED.parse().unwrap()));
+	assert_eq!(flat.xmapped, Some(client.tuple.client));
+	assert!(flat.integrity.is_some());
+	client
+}
+
+#[test]
+fn allocate() {
+	let mut server = server();
+	let client = allocated(&mut server, "alice");
+	assert_eq!(server.allocations(), 1);
+	assert_eq!(
+		server.relayed(&client.tuple),
+		Some(RELAYED.parse().unwrap())
+	);
+
+	// A second Allocate on the same 5-tuple is a mismatch
+	let (code, _) = client.send(&mut server, &Allocate::new(RequestedTransport::Udp), at(1));
+	assert_eq!(code, Some(437));
+
+	// The only relayed address is taken
+	let other = Client::new(&mut server, "203.0.113.6:50000", "bob");
+	let (code, _) = other.send(&mut server, &Allocate::new(RequestedTransport::Udp), at(1));
+	assert_eq!(code, Some(508));
+}
+
+#[test]
+fn address_family() {
+	const RELAYED6: &str = "[2001:db8::1]:49152";
+	let mut server = with_relays(&[RELAYED6, RELAYED]);
+	let relayed = |res: &[u8]| Stun::decode(res).unwrap().flat().xrelayed;
+
+	// An IPv6 client still gets IPv4 unless it asks
+	let client = Client::new(&mut server, "[2001:db8::5]:50000", "alice");
+	let (code, res) = client.send(&mut server, &Allocate::new(RequestedTransport::Udp), at(0));
+	assert_eq!(code, None);
+	assert_eq!(relayed(&res), Some(RELAYED.parse().unwrap()));
+
+	let client = Client::new(&mut server, "203.0.113.5:50000", "bob");
+	let ipv6 = Allocate {
+		family: Some(AddressFamily::IPV6),
+		..Allocate::new(RequestedTransport::Udp)
+	};
+	let (code, res) = client.send(&mut server, &ipv6, at(0));
+	assert_eq!(code, None);
+	assert_eq!(relayed(&res), Some(RELAYED6.parse().unwrap()));
+
+	// A family the pool doesn't have at all
+	let mut server = with_relays(&[RELAYED]);
+	let client = Client::new(&mut server, "203.0.113.5:50000", "alice");
+	let (code, _) = client.send(&mut server, &ipv6, at(0));
+	assert_eq!(code, Some(440));
+}
+
+#[test]
+fn refresh() {
+	let mut server = server();
+	let client = allocated(&mut server, "alice");
+
+	// Too short is raised to the default, too long is cut to the maximum
+	let short = Refresh {
+		lifetime: Some(Lifetime(1)),
+	};
+	let (code, res) = client.send(&mut server, &short, at(10));
+	assert_eq!(code, None);
+	assert_eq!(lifetime(&res), Some(Lifetime::DEFAULT));
+	let long = Refresh {
+		lifetime: Some(Lifetime(100_000)),
+	};
+	let (_, res) = client.send(&mut server, &long, at(10));
+	assert_eq!(lifetime(&res), Some(Lifetime::MAX));
+	let (_, res) = client.send(&mut server, &Refresh { lifetime: None }, at(20));
+	assert_eq!(lifetime(&res), Some(Lifetime::DEFAULT));
+
+	// Once it has lapsed there's nothing left to refresh
+	let (code, _) = client.send(&mut server, &Refresh { lifetime: None }, at(620));
+	assert_eq!(code, Some(437));
+}
+
+#[test]
+fn delete() {
+	let mut server = server();
+	let client = allocated(&mut server, "alice");
+	let delete = Refresh {
+		lifetime: Some(Lifetime::ZERO),
+	};
+	let (code, res) = client.send(&mut server, &delete, at(10));
+	assert_eq!(code, None);
+	assert_eq!(lifetime(&res), Some(Lifetime::ZERO));
+	assert_eq!(server.allocations(), 0);
+	assert_eq!(server.relayed(&client.tuple), None);
+
+	// The relayed address went back to the pool
+	let (code, _) = client.send(&mut server, &Allocate::new(RequestedTransport::Udp), at(11));
+	assert_eq!(code, None);
+}
+
+#[test]
+fn create_permission() {
+	let mut server = server();
+	let client = allocated(&mut server, "alice");
+	let peer: SocketAddr = PEER.parse().unwrap();
+	let send = |server: &mut TurnServer<Provider, VirtualTime>, peer, now| {
+		let mut buff = [0; 512];
+		let len = SendIndication {
+			peer,
+			data: b"hello",
+			dont_fragment: false,
+		}
+		.encode(TxId([3; 12]), &[], &mut buff)
+		.unwrap();
+		server
+			.handle(&buff[..len], client.tuple, now, &mut [0; 512])
+			.is_some()
+	};
+
+	// Send indications are dropped until the peer has a permission
+	assert!(!send(&mut server, peer, at(1)));
+	let (code, _) = client.send(&mut server, &CreatePermission { peers: vec![peer] }, at(1));
+	assert_eq!(code, None);
+	assert!(send(&mut server, peer, at(2)));
+	assert!(!send(
+		&mut server,
+		"198.51.100.3:3478".parse().unwrap(),
+		at(2)
+	));
+
+	// Peers have to be the relayed address's family
+	let v6 = CreatePermission {
+		peers: vec!["[2001:db8::1]:3478".parse().unwrap()],
+	};
+	let (code, _) = client.send(&mut server, &v6, at(3));
+	assert_eq!(code, Some(443));
+}
+
+#[test]
+fn channel_bind() {
+	let mut server = server();
+	let client = allocated(&mut server, "alice");
+	let peer: SocketAddr = PEER.parse().unwrap();
+
+	let channel = ChannelNumber::new(0x4000).unwrap();
+	let bind = ChannelBind { channel, peer };
+	let (code, _) = client.send(&mut server, &bind, at(1));
+	assert_eq!(code, None);
+	// Binding also installs a permission, so ChannelData is relayed straight away
+	let mut buff = [0; 512];
+	let len = ChannelData {
+		channel: 0x4000,
+		data: b"hello",
+	}
+	.encode(&mut buff)
+	.unwrap();
+	assert_eq!(
+		server.handle(&buff[..len], client.tuple, at(2), &mut [0; 512]),
+		Some(TurnAction::Relay {
+			relayed: RELAYED.parse().unwrap(),
+			peer,
+			data: b"hello",
+		})
+	);
+
+	// A channel can't be moved to another peer
+	let moved = ChannelBind {
+		channel,
+		peer: "198.51.100.3:3478".parse().unwrap(),
+	};
+	let (code, _) = client.send(&mut server, &moved, at(3));
+	assert_eq!(code, Some(400));
+
+	// Numbers outside 0x4000..=0x4FFF still get an answer
+	let (code, _) = client.send(&mut server, &Reserved(peer), at(3));
+	assert_eq!(code, Some(400));
+}
+
+// A ChannelBind for 0x3FFF, which ChannelNumber::new won't build
+struct Reserved(SocketAddr);
+impl<'i> TurnMessage<'i> for Reserved {
+	fn typ(&self) -> StunTyp {
+		StunTyp::Req(StunMethod::ChannelBind)
+	}
+	fn push_attrs(&self, out: &mut Vec<StunAttr<'i>>) {
+		out.push(StunAttr::Other(
+			0x000C,
+			Unknown {
+				value: &[0x3F, 0xFF, 0, 0],
+				padding: &[],
+			},
+		));
+		out.push(StunAttr::XPeer(self.0));
+	}
+	fn from_stun(_: &Stun<'i>) -> Result<Self, TurnErr> {
+		unreachable!()
+	}
+}
+
+#[test]
+fn mismatch() {
+	let mut server = server();
+	let peer: SocketAddr = PEER.parse().unwrap();
+
+	// Anything but Allocate needs an allocation
+	let client = Client::new(&mut server, "203.0.113.5:50000", "alice");
+	let (code, res) = client.send(&mut server, &CreatePermission { peers: vec![peer] }, at(0));
+	assert_eq!(code, Some(437));
+	// Errors to authenticated requests are signed too
+	assert!(Stun::decode_and_verify(&res, &client.key).is_ok());
+
+	// Another user can't act on the allocation, even from the same 5-tuple
+	let client = allocated(&mut server, "alice");
+	let intruder = Client::new(&mut server, "203.0.113.5:50000", "bob");
+	let (code, res) = intruder.send(&mut server, &Refresh { lifetime: None }, at(1));
+	assert_eq!(code, Some(441));
+	assert!(Stun::decode_and_verify(&res, &intruder.key).is_ok());
+	let (code, _) = client.send(&mut server, &Refresh { lifetime: None }, at(1));
+	assert_eq!(code, None);
+}
+
+#[test]
+fn challenge() {
+	let mut server = server();
+	let mut client = Client::new(&mut server, "203.0.113.5:50000", "alice");
+
+	// Integrity that doesn't check out is challenged again
+	let wrong = Client {
+		key: LongTermKey::new("alice", REALM, "guess"),
+		nonce: client.nonce.clone(),
+		..Client::new(&mut server, "203.0.113.5:50000", "alice")
+	};
+	let (code, _) = wrong.send(&mut server, &Allocate::new(RequestedTransport::Udp), at(0));
+	assert_eq!(code, Some(401));
+
+	// The nonce rotates, and the previous one is honoured across a single rotation
+	let (code, _) = client.send(&mut server, &Refresh { lifetime: None }, at(600));
+	assert_eq!(code, Some(437));
+	let (code, res) = client.send(&mut server, &Refresh { lifetime: None }, at(1200));
+	assert_eq!(code, Some(438));
+
+	// The 438 carries the new nonce to retry with
+	let flat = Stun::decode(&res).unwrap().flat();
+	assert_eq!(flat.realm, Some(REALM));
+	client.nonce = flat.nonce.unwrap().to_string();
+	let (code, _) = client.send(
+		&mut server,
+		&Allocate::new(RequestedTransport::Udp),
+		at(1200),
+	);
+	assert_eq!(code, None);
+}
+
+#[test]
+fn recording() {
+	struct Log(Mutex<Vec<(Direction, Option<TxId>)>>);
+	impl Recorder for Log {
+		fn record(&self, record: &Record<'_>) {
+			let txid = record.summary.as_ref().map(|s| s.txid);
+			self.0.lock().unwrap().push((record.direction, txid));
+		}
+	}
+	let log = Arc::new(Log(Mutex::new(Vec::new())));
+	let mut server = server().with_recording(Recording::shared(log.clone()));
+	// The Allocate and its 401
+	Client::new(&mut server, "203.0.113.5:50000", "alice");
+	let txid = Some(TxId([1; 12]));
+	assert_eq!(
+		*log.0.lock().unwrap(),
+		[(Direction::Inbound, txid), (Direction::Outbound, txid)]
+	);
+}